@@ -1,10 +1,18 @@
 use argon2::Error as ArgonError;
 use reqwest::Error as ReqwestError;
 use reqwest_middleware::Error as MiddlewareReqwestError;
+use serde::Serialize;
 use tracing::{event, instrument, Level};
 use warp::body::BodyDeserializeError;
 use warp::cors::CorsForbidden;
-use warp::{http::StatusCode, reject::Reject, Rejection, Reply};
+use warp::http::header::{self, HeaderName, HeaderValue};
+use warp::http::StatusCode;
+use warp::reject::{
+    InvalidHeader, InvalidQuery, LengthRequired, MethodNotAllowed, MissingHeader, PayloadTooLarge,
+    Reject, UnsupportedMediaType,
+};
+use warp::reply::Response;
+use warp::{Rejection, Reply};
 
 #[derive(Debug, Clone)]
 pub struct APILayerError {
@@ -22,6 +30,10 @@ impl std::fmt::Display for APILayerError {
 pub enum Error {
     ParseError(std::num::ParseIntError),
     MissingParameters,
+    InvalidParameter(String),
+    /// `body::json`を通さずに読んだ本文をデシリアライズできなかった
+    InvalidBody(String),
+    NotFound,
     DatabaseQueryError(sqlx::Error),
     ClientError(APILayerError),
     ServerError(APILayerError),
@@ -31,6 +43,24 @@ pub enum Error {
     ArgonLibraryError(ArgonError),
     Unauthorized,
     CannotDecryptToken,
+    SessionRevoked,
+    InvalidRefreshToken,
+    AccountAlreadyExists,
+    SelfVote,
+    Forbidden,
+    InvalidResetToken,
+    InvalidVerificationToken,
+    EmailNotVerified,
+    MailError(String),
+    /// 再試行できるまでの秒数
+    TooManyAttempts(u64),
+    /// レート制限の上限と、バケットが満杯に戻るまでの秒数、再試行できるまでの秒数
+    RateLimited {
+        limit: u32,
+        reset: u64,
+        retry_after: u64,
+    },
+    RateLimitStoreError(String),
 }
 
 impl std::fmt::Display for Error {
@@ -38,6 +68,9 @@ impl std::fmt::Display for Error {
         match &self {
             Error::ParseError(ref err) => write!(f, "Cannot parse parameter: {}", err),
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::InvalidParameter(name) => write!(f, "Invalid value for parameter: {}", name),
+            Error::InvalidBody(err) => write!(f, "Request body deserialize error: {}", err),
+            Error::NotFound => write!(f, "Resource not found"),
             Error::DatabaseQueryError(_) => write!(f, "Query could not be executed"),
             Error::ClientError(err) => write!(f, "External Client error: {}", err),
             Error::ServerError(err) => write!(f, "External Server error: {}", err),
@@ -47,6 +80,24 @@ impl std::fmt::Display for Error {
             Error::ArgonLibraryError(_) => write!(f, "Cannot verify password"),
             Error::Unauthorized => write!(f, "No permission to change the underlying resource"),
             Error::CannotDecryptToken => write!(f, "Cannot decrypt error"),
+            Error::SessionRevoked => write!(f, "Session has been revoked"),
+            Error::InvalidRefreshToken => write!(f, "Invalid or expired refresh token"),
+            Error::AccountAlreadyExists => write!(f, "Account already exists"),
+            Error::SelfVote => write!(f, "Cannot vote on your own post"),
+            Error::Forbidden => write!(f, "Insufficient role"),
+            Error::InvalidResetToken => write!(f, "Invalid, expired or used reset token"),
+            Error::InvalidVerificationToken => {
+                write!(f, "Invalid, expired or used verification token")
+            }
+            Error::EmailNotVerified => write!(f, "Email address has not been verified"),
+            Error::MailError(err) => write!(f, "Cannot send mail: {}", err),
+            Error::TooManyAttempts(secs) => {
+                write!(f, "Too many failed attempts, retry in {} seconds", secs)
+            }
+            Error::RateLimited { retry_after, .. } => {
+                write!(f, "Rate limit exceeded, retry in {} seconds", retry_after)
+            }
+            Error::RateLimitStoreError(err) => write!(f, "Cannot check rate limit: {}", err),
         }
     }
 }
@@ -55,92 +106,412 @@ impl std::fmt::Display for Error {
 impl Reject for Error {}
 impl Reject for APILayerError {}
 
-const DUPLICATE_KEY: u32 = 23505;
+const DUPLICATE_KEY: &str = "23505";
+/// アカウントのemailの一意制約。PostgreSQLではemailが主キーなので主キーの制約になる
+const ACCOUNTS_EMAIL_CONSTRAINT: &str = "accounts_pkey";
 
-#[instrument]
-pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
-    if let Some(crate::Error::DatabaseQueryError(e)) = r.find() {
-        event!(Level::ERROR, "Database query error");
-
-        match e {
-            sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<u32>().unwrap() == DUPLICATE_KEY {
-                    Ok(warp::reply::with_status(
-                        "Account already exists".to_string(),
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                    ))
-                } else {
-                    Ok(warp::reply::with_status(
-                        "Cannot update data".to_string(),
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                    ))
-                }
-            }
+/// レート制限をかけたルートの応答に付ける`X-RateLimit-*`ヘッダー。
+/// `reset`はバケットが満杯に戻るまでの秒数
+pub fn rate_limit_headers(
+    limit: u32,
+    remaining: u32,
+    reset: u64,
+) -> [(HeaderName, HeaderValue); 3] {
+    [
+        (
+            HeaderName::from_static("x-ratelimit-limit"),
+            HeaderValue::from(limit),
+        ),
+        (
+            HeaderName::from_static("x-ratelimit-remaining"),
+            HeaderValue::from(remaining),
+        ),
+        (
+            HeaderName::from_static("x-ratelimit-reset"),
+            HeaderValue::from(reset),
+        ),
+    ]
+}
+
+/// 1つのパラメータやフィールドに関するエラー
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// エラーレスポンスの本文。RFC 7807の`application/problem+json`として返す
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// クライアントが分岐に使うための、文言が変わっても変わらない識別子
+    pub code: &'static str,
+    /// ログと突き合わせるためのid。エラーごとに発行する
+    pub request_id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// `Retry-After`ヘッダーに返す秒数
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
+    /// 本文の代わりにヘッダーで返す値
+    #[serde(skip)]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &'static str, detail: impl Into<String>) -> Self {
+        Problem {
+            kind: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            code,
+            request_id: uuid::Uuid::new_v4().to_string(),
+            errors: Vec::new(),
+            retry_after: None,
+            headers: Vec::new(),
+        }
+    }
 
-            _ => Ok(warp::reply::with_status(
-                "Cannot update data".to_string(),
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after = Some(secs);
+        self
+    }
+
+    pub fn with_headers(
+        mut self,
+        headers: impl IntoIterator<Item = (HeaderName, HeaderValue)>,
+    ) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>, message: impl Into<String>) -> Self {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+        self
+    }
+}
+
+impl Reply for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut res = warp::reply::json(&self).into_response();
+        *res.status_mut() = status;
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        if let Some(secs) = self.retry_after {
+            res.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        for (name, value) in self.headers {
+            res.headers_mut().insert(name, value);
+        }
+        res
+    }
+}
+
+impl Error {
+    /// エラーに対応するレスポンスの本文。外部APIやライブラリのエラーの詳細はクライアントに返さない
+    pub fn problem(&self) -> Problem {
+        match self {
+            Error::ParseError(_) => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                self.to_string(),
+            ),
+            Error::MissingParameters => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "missing_parameters",
+                self.to_string(),
+            ),
+            Error::InvalidParameter(name) => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                self.to_string(),
+            )
+            .with_field(name, "Invalid value"),
+            Error::InvalidBody(_) => Problem::new(
                 StatusCode::UNPROCESSABLE_ENTITY,
-            )),
+                "invalid_body",
+                self.to_string(),
+            ),
+            Error::NotFound | Error::DatabaseQueryError(sqlx::Error::RowNotFound) => {
+                Problem::new(StatusCode::NOT_FOUND, "not_found", "Resource not found")
+            }
+            Error::DatabaseQueryError(sqlx::Error::Database(err))
+                if err.code().as_deref() == Some(DUPLICATE_KEY)
+                    && err.constraint() == Some(ACCOUNTS_EMAIL_CONSTRAINT) =>
+            {
+                Problem::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "account_already_exists",
+                    "Account already exists",
+                )
+            }
+            // INFO: アカウント以外の一意制約の違反。どの制約かはクライアントに返さない
+            Error::DatabaseQueryError(sqlx::Error::Database(err))
+                if err.code().as_deref() == Some(DUPLICATE_KEY) =>
+            {
+                Problem::new(StatusCode::CONFLICT, "conflict", "Resource already exists")
+            }
+            Error::DatabaseQueryError(sqlx::Error::Database(_)) => Problem::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "cannot_update_data",
+                "Cannot update data",
+            ),
+            Error::DatabaseQueryError(_) => Problem::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+                self.to_string(),
+            ),
+            Error::AccountAlreadyExists => Problem::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "account_already_exists",
+                self.to_string(),
+            ),
+            Error::Unauthorized => {
+                Problem::new(StatusCode::UNAUTHORIZED, "unauthorized", self.to_string())
+            }
+            Error::CannotDecryptToken => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_token",
+                "Invalid or expired token",
+            ),
+            Error::SessionRevoked => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "session_revoked",
+                self.to_string(),
+            ),
+            Error::InvalidRefreshToken => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_refresh_token",
+                self.to_string(),
+            ),
+            Error::WrongPassword => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "wrong_password",
+                "Wrong E-Mail/Password combination",
+            ),
+            Error::SelfVote => Problem::new(StatusCode::FORBIDDEN, "self_vote", self.to_string()),
+            Error::Forbidden => Problem::new(StatusCode::FORBIDDEN, "forbidden", self.to_string()),
+            Error::InvalidResetToken => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_reset_token",
+                self.to_string(),
+            ),
+            Error::InvalidVerificationToken => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_verification_token",
+                self.to_string(),
+            ),
+            Error::EmailNotVerified => Problem::new(
+                StatusCode::FORBIDDEN,
+                "email_not_verified",
+                self.to_string(),
+            ),
+            Error::TooManyAttempts(secs) => Problem::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_attempts",
+                self.to_string(),
+            )
+            .with_retry_after(*secs),
+            Error::RateLimited {
+                limit,
+                reset,
+                retry_after,
+            } => Problem::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                self.to_string(),
+            )
+            .with_retry_after(*retry_after)
+            .with_headers(rate_limit_headers(*limit, 0, *reset)),
+            Error::ArgonLibraryError(_)
+            | Error::MailError(_)
+            | Error::RateLimitStoreError(_)
+            | Error::ClientError(_)
+            | Error::ServerError(_)
+            | Error::RequestAPIError(_)
+            | Error::MiddlewareReqwestAPIError(_) => Problem::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal Server Error",
+            ),
         }
-    } else if let Some(crate::Error::Unauthorized) = r.find() {
-        event!(Level::ERROR, "Not matching account id");
-        Ok(warp::reply::with_status(
-            "No pertmission to change underlying resource".to_string(),
-            StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(crate::Error::WrongPassword) = r.find() {
-        event!(Level::ERROR, "Entered wrong password");
-        Ok(warp::reply::with_status(
-            "Wrong E-Mail/Password combination".to_string(),
-            StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(crate::Error::ClientError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(crate::Error::ServerError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(crate::Error::RequestAPIError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(crate::Error::MiddlewareReqwestAPIError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+    }
+}
+
+#[instrument]
+pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
+    let problem = if let Some(error) = r.find::<Error>() {
+        let problem = error.problem();
+        event!(Level::ERROR, request_id = %problem.request_id, "{}", error);
+        problem
     } else if let Some(error) = r.find::<CorsForbidden>() {
-        event!(Level::ERROR, "CORS forbidden error: {}", error);
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::FORBIDDEN,
-        ))
+        let problem = Problem::new(StatusCode::FORBIDDEN, "cors_forbidden", error.to_string());
+        event!(Level::ERROR, request_id = %problem.request_id, "CORS forbidden error: {}", error);
+        problem
     } else if let Some(error) = r.find::<BodyDeserializeError>() {
-        event!(Level::ERROR, "Cannot deserialize request body: {}", error);
-        Ok(warp::reply::with_status(
-            error.to_string(),
+        let problem = Problem::new(
             StatusCode::UNPROCESSABLE_ENTITY,
-        ))
-    } else if let Some(error) = r.find::<Error>() {
-        event!(Level::ERROR, "{}", error);
-        Ok(warp::reply::with_status(
+            "invalid_body",
+            error.to_string(),
+        );
+        event!(Level::ERROR, request_id = %problem.request_id, "Cannot deserialize request body: {}", error);
+        problem
+    } else if let Some(error) = r.find::<InvalidQuery>() {
+        let problem = Problem::new(StatusCode::BAD_REQUEST, "invalid_query", error.to_string());
+        event!(Level::ERROR, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<MissingHeader>() {
+        // INFO: 認証が必要なルートでは`auth()`のヘッダーの取得で弾かれる
+        let problem = if error.name().eq_ignore_ascii_case("authorization") {
+            Problem::new(StatusCode::UNAUTHORIZED, "missing_token", error.to_string())
+        } else {
+            Problem::new(StatusCode::BAD_REQUEST, "missing_header", error.to_string())
+                .with_field(error.name(), "Missing header")
+        };
+        event!(Level::ERROR, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<InvalidHeader>() {
+        let problem = Problem::new(StatusCode::BAD_REQUEST, "invalid_header", error.to_string())
+            .with_field(error.name(), "Invalid value");
+        event!(Level::ERROR, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<UnsupportedMediaType>() {
+        let problem = Problem::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
             error.to_string(),
-            StatusCode::FORBIDDEN,
-        ))
+        );
+        event!(Level::WARN, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<PayloadTooLarge>() {
+        let problem = Problem::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+            error.to_string(),
+        );
+        event!(Level::WARN, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<LengthRequired>() {
+        let problem = Problem::new(
+            StatusCode::LENGTH_REQUIRED,
+            "length_required",
+            error.to_string(),
+        );
+        event!(Level::WARN, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if let Some(error) = r.find::<MethodNotAllowed>() {
+        // INFO: 他のルートのメソッドの不一致も含まれるので、パスもメソッドも一致したルートの理由を先に確かめる
+        let problem = Problem::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            error.to_string(),
+        );
+        event!(Level::WARN, request_id = %problem.request_id, "{}", error);
+        problem
+    } else if r.is_not_found() {
+        let problem = Problem::new(StatusCode::NOT_FOUND, "route_not_found", "Route not found");
+        event!(Level::WARN, request_id = %problem.request_id, "Requested route was not found");
+        problem
     } else {
-        event!(Level::WARN, "Requested route was not found");
-        Ok(warp::reply::with_status(
-            "Route not found".to_string(),
-            StatusCode::NOT_FOUND,
-        ))
+        // INFO: 想定していないRejection。内容はクライアントに返さない
+        let problem = Problem::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "Internal Server Error",
+        );
+        event!(Level::ERROR, request_id = %problem.request_id, "Unhandled rejection: {:?}", r);
+        problem
+    };
+
+    Ok(problem)
+}
+
+#[cfg(test)]
+mod return_error_tests {
+    use super::*;
+
+    /// 応答のステータスと`code`
+    async fn problem(r: Rejection) -> (StatusCode, String) {
+        let res = return_error(r).await.unwrap().into_response();
+        let status = res.status();
+        let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (status, problem["code"].as_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn builtin_rejections_keep_their_status() {
+        let r = warp::test::request()
+            .method("POST")
+            .filter(&warp::get())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            problem(r).await,
+            (StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed".into())
+        );
+
+        let r = warp::test::request()
+            .method("POST")
+            .header("Content-Type", "text/plain")
+            .body("{}")
+            .filter(&warp::body::json::<serde_json::Value>())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            problem(r).await,
+            (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type".into()
+            )
+        );
+
+        let r = warp::test::request()
+            .method("POST")
+            .body("too large")
+            .filter(&warp::body::content_length_limit(1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            problem(r).await,
+            (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large".into())
+        );
+
+        let r = warp::test::request()
+            .method("POST")
+            .filter(&warp::body::content_length_limit(1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            problem(r).await,
+            (StatusCode::LENGTH_REQUIRED, "length_required".into())
+        );
+
+        let r = warp::test::request()
+            .header("X-Count", "many")
+            .filter(&warp::header::<u32>("x-count"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            problem(r).await,
+            (StatusCode::BAD_REQUEST, "invalid_header".into())
+        );
+
+        assert_eq!(
+            problem(warp::reject::not_found()).await,
+            (StatusCode::NOT_FOUND, "route_not_found".into())
+        );
     }
 }
@@ -1,12 +1,15 @@
 
+use std::env;
 use std::process::Command;
 use std::io::{self, Write};
+use std::sync::Arc;
 
 use futures_util::future::FutureExt;
+use mock_server::CaptureMailer;
 use question_and_answer::{config, handle_errors, oneshot, setup_store};
+use question_and_answer::store::{memory::MemoryStore, DynStore};
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct User {
@@ -29,40 +32,31 @@ struct QuestionAnswer {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct Token(String);
+struct Token {
+    access_token: String,
+    refresh_token: String,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), handle_errors::Error> {
     dotenv::dotenv().ok();
     let config = config::Config::new().expect("Config can't be set");
 
-    let s = Command::new("sqlx")
-        .arg("database")
-        .arg("drop")
-        .arg("--database-url")
-        .arg(format!("postgres://{}:{}/{}", config.database_host, config.database_port, config.database_name))
-        .arg("-y")
-        .output()
-        .expect("sqlx command failed to start");
+    // INTEGRATION_STORE=memory でPostgreSQLを使わずにテストを実行できる
+    let store: DynStore = match env::var("INTEGRATION_STORE").as_deref() {
+        Ok("memory") => Arc::new(MemoryStore::new()),
+        _ => {
+            reset_database(&config);
 
-    io::stdout().write_all(&s.stderr).unwrap();
-
-    let s = Command::new("sqlx")
-            .arg("database")
-            .arg("create")
-            .arg("--database-url")
-            .arg(format!("postgres://{}:{}/{}", config.database_host, config.database_port, config.database_name))
-            .output()
-            .expect("sqlx command failed to start");
-
-    // Exdcute DB commands to drop and create a new test database
-    io::stdout().write_all(&s.stderr).unwrap();
-
-    // set up a new store instance with a db connection pool
-    let store = setup_store(&config).await?;
+            // set up a new store instance with a db connection pool
+            setup_store(&config).await?
+        }
+    };
 
     // start the server and listen for a sender signal to shut it down
-    let handler = oneshot(store).await;
+    // 確認メールのリンクを開くため、送信したメールを取り出せるMailerを使う
+    let mailer = CaptureMailer::new();
+    let handler = oneshot(store, Arc::new(mailer.clone())).await;
 
     let u = User {
         email: "test@example.com".to_string(),
@@ -81,6 +75,15 @@ async fn main() -> Result<(), handle_errors::Error> {
         }
     }
 
+    print!("Running verify_email...");
+    match std::panic::AssertUnwindSafe(verify_email(&mailer)).catch_unwind().await {
+        Ok(_) => println!("✓"),
+        Err(_) => {
+            let _ = handler.sender.send(1);
+            std::process::exit(1);
+        }
+    }
+
     print!("Running login...");
     match std::panic::AssertUnwindSafe(login(u)).catch_unwind().await {
         Ok(t) => {
@@ -107,25 +110,68 @@ async fn main() -> Result<(), handle_errors::Error> {
     Ok(())
 }
 
+fn reset_database(config: &config::Config) {
+    let s = Command::new("sqlx")
+        .arg("database")
+        .arg("drop")
+        .arg("--database-url")
+        .arg(format!("postgres://{}:{}/{}", config.database_host, config.database_port, config.database_name))
+        .arg("-y")
+        .output()
+        .expect("sqlx command failed to start");
+
+    io::stdout().write_all(&s.stderr).unwrap();
+
+    let s = Command::new("sqlx")
+            .arg("database")
+            .arg("create")
+            .arg("--database-url")
+            .arg(format!("postgres://{}:{}/{}", config.database_host, config.database_port, config.database_name))
+            .output()
+            .expect("sqlx command failed to start");
+
+    // Exdcute DB commands to drop and create a new test database
+    io::stdout().write_all(&s.stderr).unwrap();
+}
+
 async fn register_new_user(user: &User) {
     let client = reqwest::Client::new();
     let res = client
-        .post("http://localhost:3030/registration")
+        .post("http://localhost:3030/v1/registration")
         .json(&user)
         .send()
         .await
         .unwrap()
-        .json::<Value>()
+        .text()
         .await;
 
     assert_eq!(res.unwrap(), "Account added".to_string());
 
 }
 
+async fn verify_email(mailer: &CaptureMailer) {
+    let mail = mailer.sent().pop().expect("verification email was not sent");
+    let link = mail
+        .body
+        .lines()
+        .find(|line| line.contains("/v1/email/verify?token="))
+        .unwrap();
+    let path = &link[link.find("/v1/").unwrap()..];
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(format!("http://localhost:3030{}", path))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), 200);
+}
+
 async fn login(user: User) -> Token {
     let client = reqwest::Client::new();
     let res = client
-        .post("http://localhost:3030/login")
+        .post("http://localhost:3030/v1/login")
         .json(&user)
         .send()
         .await
@@ -147,8 +193,8 @@ async fn post_question(token: Token) {
 
     let client = reqwest::Client::new();
     let res = client
-        .post("http://localhost:3030/questions")
-        .header("Authorization", token.0)
+        .post("http://localhost:3030/v1/questions")
+        .header("Authorization", token.access_token)
         .json(&q)
         .send()
         .await
@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// 送信するメール。本文はプレーンテキストのみ扱う
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// メールを送信できなかった理由
+///
+/// INFO: `handle-errors`に依存せずにこのクレートと`mock-server`を単独でビルドできるよう、独自のエラー型にする
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailError(pub String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot send mail: {}", self.0)
+    }
+}
+
+impl std::error::Error for MailError {}
+
+/// メールの送信先を差し替えるためのトレイト。
+/// ログやファイルに書き出す実装とテスト用の実装は`mock-server`クレートにある
+#[async_trait]
+pub trait Mailer: Debug + Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), MailError>;
+}
+
+pub type DynMailer = Arc<dyn Mailer>;
+
+/// SMTPサーバにメールを渡すMailer
+///
+/// INFO: 認証とTLSには対応していないので、ローカルのリレー(MTAやMailHog等)に渡す用途に限る
+#[derive(Debug, Clone)]
+pub struct SmtpMailer {
+    /// `host:port`
+    addr: String,
+    from: String,
+}
+
+impl SmtpMailer {
+    pub fn new(addr: impl Into<String>, from: impl Into<String>) -> Self {
+        SmtpMailer {
+            addr: addr.into(),
+            from: from.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> Result<(), MailError> {
+        // INFO: 宛先や件名に改行が含まれていると、任意のSMTPコマンドやヘッダーを差し込めてしまう
+        if mail.to.contains(['\r', '\n', '<', '>']) || mail.subject.contains(['\r', '\n']) {
+            return Err(MailError("invalid address or subject".to_string()));
+        }
+
+        let stream = TcpStream::connect(&self.addr)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+        let mut conn = SmtpConnection {
+            stream: BufReader::new(stream),
+        };
+
+        conn.expect(220).await?;
+        conn.command("EHLO localhost", 250).await?;
+        conn.command(&format!("MAIL FROM:<{}>", self.from), 250)
+            .await?;
+        conn.command(&format!("RCPT TO:<{}>", mail.to), 250).await?;
+        conn.command("DATA", 354).await?;
+        conn.command(&message(&self.from, &mail), 250).await?;
+        conn.command("QUIT", 221).await?;
+
+        tracing::event!(tracing::Level::INFO, to = %mail.to, "mail sent via SMTP");
+        Ok(())
+    }
+}
+
+struct SmtpConnection {
+    stream: BufReader<TcpStream>,
+}
+
+impl SmtpConnection {
+    async fn command(&mut self, line: &str, code: u16) -> Result<(), MailError> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+        self.expect(code).await
+    }
+
+    /// 応答を読み、応答コードが`code`でなければエラーにする。
+    /// 複数行の応答(`250-...`)は最後の行(`250 ...`)まで読み飛ばす
+    async fn expect(&mut self, code: u16) -> Result<(), MailError> {
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stream
+                .read_line(&mut line)
+                .await
+                .map_err(|e| MailError(e.to_string()))?;
+            if read == 0 {
+                return Err(MailError("connection closed".to_string()));
+            }
+
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+
+            return match line.get(..3).and_then(|c| c.parse::<u16>().ok()) {
+                Some(c) if c == code => Ok(()),
+                _ => Err(MailError(format!("unexpected reply: {}", line.trim_end()))),
+            };
+        }
+    }
+}
+
+/// DATAで送る本文を組み立てる。`.`で始まる行は終端と区別するため`.`を重ねる
+fn message(from: &str, mail: &Mail) -> String {
+    let mut message = format!(
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        from, mail.to, mail.subject
+    );
+    for line in mail.body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push('.');
+
+    message
+}
+
+#[cfg(test)]
+mod mailer_tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn smtp_mailer_sends_a_dot_stuffed_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // INFO: 受け取ったコマンドをそのまま返す最小限のSMTPサーバ
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut received = Vec::new();
+            let mut in_data = false;
+            stream
+                .get_mut()
+                .write_all(b"220 localhost\r\n")
+                .await
+                .unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        b""
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 8BITMIME\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    b"221 bye\r\n"
+                } else {
+                    b"250 ok\r\n"
+                };
+                received.push(line);
+                stream.get_mut().write_all(reply).await.unwrap();
+            }
+            received
+        });
+
+        SmtpMailer::new(addr.to_string(), "noreply@example.com")
+            .send(Mail {
+                to: "user@example.com".to_string(),
+                subject: "Hello".to_string(),
+                body: "first\n.second".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(received[1], "MAIL FROM:<noreply@example.com>");
+        assert_eq!(received[2], "RCPT TO:<user@example.com>");
+        assert!(received.contains(&"Subject: Hello".to_string()));
+        assert!(received.contains(&"..second".to_string()));
+        assert_eq!(received.last().unwrap(), "QUIT");
+    }
+}
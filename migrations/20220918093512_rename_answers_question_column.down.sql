@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE answers
+RENAME COLUMN question_id TO corresponding_question;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS answers_search_vector_idx;
+ALTER TABLE answers
+DROP COLUMN search_vector;
+
+DROP INDEX IF EXISTS questions_search_vector_idx;
+ALTER TABLE questions
+DROP COLUMN search_vector;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- tags && / tags @> による絞り込み用
+CREATE INDEX IF NOT EXISTS questions_tags_idx ON questions USING GIN (tags);
@@ -0,0 +1,15 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS answers_question_id_idx;
+DROP INDEX IF EXISTS questions_title_idx;
+DROP INDEX IF EXISTS questions_last_activity_on_idx;
+DROP INDEX IF EXISTS questions_answer_count_idx;
+DROP INDEX IF EXISTS questions_created_on_idx;
+
+DROP TRIGGER IF EXISTS questions_touch_activity ON questions;
+DROP FUNCTION IF EXISTS questions_touch_activity();
+DROP TRIGGER IF EXISTS answers_track_questions ON answers;
+DROP FUNCTION IF EXISTS questions_track_answers();
+
+ALTER TABLE questions
+DROP COLUMN IF EXISTS last_activity_on,
+DROP COLUMN IF EXISTS answer_count;
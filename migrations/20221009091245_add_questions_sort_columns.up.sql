@@ -0,0 +1,50 @@
+-- Add up migration script here
+-- 並べ替え用に回答数と最終更新日時を非正規化して持つ
+ALTER TABLE questions
+ADD COLUMN answer_count INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN last_activity_on TIMESTAMP NOT NULL DEFAULT NOW();
+
+UPDATE questions SET
+  answer_count = (SELECT count(*) FROM answers WHERE answers.question_id = questions.id),
+  last_activity_on = GREATEST(
+    created_on,
+    (SELECT max(created_on) FROM answers WHERE answers.question_id = questions.id)
+  );
+
+-- 回答の追加・削除に合わせて回答数と最終更新日時を更新する
+CREATE OR REPLACE FUNCTION questions_track_answers() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    UPDATE questions
+    SET answer_count = answer_count + 1,
+        last_activity_on = GREATEST(last_activity_on, NEW.created_on)
+    WHERE id = NEW.question_id;
+  ELSE
+    UPDATE questions SET answer_count = answer_count - 1 WHERE id = OLD.question_id;
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER answers_track_questions
+AFTER INSERT OR DELETE ON answers
+FOR EACH ROW EXECUTE FUNCTION questions_track_answers();
+
+-- 質問の編集も最終更新日時に反映する
+CREATE OR REPLACE FUNCTION questions_touch_activity() RETURNS trigger AS $$
+BEGIN
+  NEW.last_activity_on = NOW();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER questions_touch_activity
+BEFORE UPDATE OF title, content, tags ON questions
+FOR EACH ROW EXECUTE FUNCTION questions_touch_activity();
+
+-- 各並び順はidを第2キーにして一意に決まるようにする
+CREATE INDEX IF NOT EXISTS questions_created_on_idx ON questions (created_on, id);
+CREATE INDEX IF NOT EXISTS questions_answer_count_idx ON questions (answer_count, id);
+CREATE INDEX IF NOT EXISTS questions_last_activity_on_idx ON questions (last_activity_on, id);
+CREATE INDEX IF NOT EXISTS questions_title_idx ON questions (lower(title), id);
+CREATE INDEX IF NOT EXISTS answers_question_id_idx ON answers (question_id);
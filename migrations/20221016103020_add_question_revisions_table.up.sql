@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- 質問を編集するたびに、編集前の内容を1行として記録する
+CREATE TABLE IF NOT EXISTS question_revisions (
+  id serial PRIMARY KEY,
+  question_id integer NOT NULL REFERENCES questions ON DELETE CASCADE,
+  revision integer NOT NULL,
+  account_id integer NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  content TEXT NOT NULL,
+  tags TEXT[],
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (question_id, revision)
+);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- 削除された質問は行を残し、削除日時を記録する
+ALTER TABLE questions ADD COLUMN deleted_at TIMESTAMP;
+
+-- 保持期間を過ぎた質問を完全に削除する際の検索用
+CREATE INDEX IF NOT EXISTS questions_deleted_at_idx ON questions (deleted_at)
+WHERE deleted_at IS NOT NULL;
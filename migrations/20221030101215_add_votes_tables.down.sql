@@ -0,0 +1,10 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS questions_score_idx;
+
+DROP TABLE IF EXISTS answer_votes;
+DROP TABLE IF EXISTS question_votes;
+DROP FUNCTION IF EXISTS answer_votes_track_score();
+DROP FUNCTION IF EXISTS question_votes_track_score();
+
+ALTER TABLE answers DROP COLUMN IF EXISTS score;
+ALTER TABLE questions DROP COLUMN IF EXISTS score;
@@ -0,0 +1,55 @@
+-- Add up migration script here
+-- 1アカウントにつき1つの投稿に1票(+1 / -1)まで
+CREATE TABLE IF NOT EXISTS question_votes (
+  question_id integer NOT NULL REFERENCES questions ON DELETE CASCADE,
+  account_id integer NOT NULL,
+  value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (question_id, account_id)
+);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+  answer_id integer NOT NULL REFERENCES answers ON DELETE CASCADE,
+  account_id integer NOT NULL,
+  value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (answer_id, account_id)
+);
+
+-- 票の合計を非正規化して持ち、投票の追加・変更・取り消しに合わせてトリガーで更新する
+ALTER TABLE questions ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE answers ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION question_votes_track_score() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    UPDATE questions SET score = score + NEW.value WHERE id = NEW.question_id;
+  END IF;
+  IF TG_OP IN ('UPDATE', 'DELETE') THEN
+    UPDATE questions SET score = score - OLD.value WHERE id = OLD.question_id;
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER question_votes_track_score
+AFTER INSERT OR UPDATE OR DELETE ON question_votes
+FOR EACH ROW EXECUTE FUNCTION question_votes_track_score();
+
+CREATE OR REPLACE FUNCTION answer_votes_track_score() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    UPDATE answers SET score = score + NEW.value WHERE id = NEW.answer_id;
+  END IF;
+  IF TG_OP IN ('UPDATE', 'DELETE') THEN
+    UPDATE answers SET score = score - OLD.value WHERE id = OLD.answer_id;
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER answer_votes_track_score
+AFTER INSERT OR UPDATE OR DELETE ON answer_votes
+FOR EACH ROW EXECUTE FUNCTION answer_votes_track_score();
+
+CREATE INDEX IF NOT EXISTS questions_score_idx ON questions (score, id);
@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- 質問者が採用した回答。採用した回答が削除された場合は未採用に戻す
+ALTER TABLE questions ADD COLUMN accepted_answer_id INTEGER REFERENCES answers ON DELETE SET NULL;
+
+-- 回答を削除する際に参照している質問を探すため
+CREATE INDEX IF NOT EXISTS questions_accepted_answer_id_idx ON questions (accepted_answer_id);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- ログインごとに1行作り、リフレッシュトークンのハッシュを持つ。
+-- リフレッシュのたびにハッシュを置き換え、ログアウトや再利用の検知で失効させる
+CREATE TABLE IF NOT EXISTS login_sessions (
+  id serial PRIMARY KEY,
+  account_id integer NOT NULL,
+  refresh_token_hash VARCHAR(64) NOT NULL,
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP NOT NULL,
+  revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS login_sessions_account_id_idx ON login_sessions (account_id);
@@ -0,0 +1,10 @@
+-- Add up migration script here
+-- パスワード再設定用のトークン。トークン自体は保存せず、SHA-256のハッシュで引く
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+  id serial PRIMARY KEY,
+  account_id integer NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP
+);
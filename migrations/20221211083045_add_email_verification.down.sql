@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS email_verification_tokens;
+ALTER TABLE accounts DROP COLUMN verified_at;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- メールアドレスを確認した日時。既存のアカウントは確認済みとして扱う
+ALTER TABLE accounts ADD COLUMN verified_at TIMESTAMP;
+UPDATE accounts SET verified_at = NOW();
+
+-- メールアドレス確認用のトークン。password_reset_tokensと同じくハッシュで引く
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+  id serial PRIMARY KEY,
+  account_id integer NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP
+);
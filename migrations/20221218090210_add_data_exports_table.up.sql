@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- 個人データの書き出し。バックグラウンドで作成し、完成したら内容とダウンロード用のトークンのハッシュを持つ
+CREATE TABLE IF NOT EXISTS data_exports (
+  id serial PRIMARY KEY,
+  account_id integer NOT NULL,
+  status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'ready', 'failed')),
+  archive TEXT,
+  token_hash VARCHAR(64) UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS data_exports_account_id_idx ON data_exports (account_id);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- tags TEXT[] はJSON配列の文字列として保存する
+CREATE TABLE IF NOT EXISTS questions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  title VARCHAR(255) NOT NULL,
+  content TEXT NOT NULL,
+  tags TEXT,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
\ No newline at end of file
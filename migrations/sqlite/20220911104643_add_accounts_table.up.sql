@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- SQLiteでは主キー以外を自動採番できないので、idを主キーにしてemailに一意制約を付ける
+CREATE TABLE IF NOT EXISTS accounts (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  email VARCHAR(255) NOT NULL UNIQUE,
+  password VARCHAR(255) NOT NULL
+);
\ No newline at end of file
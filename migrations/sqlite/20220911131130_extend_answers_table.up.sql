@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE answers
+ADD COLUMN account_id INTEGER;
\ No newline at end of file
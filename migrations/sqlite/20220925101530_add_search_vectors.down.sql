@@ -0,0 +1,10 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS answers_fts_update;
+DROP TRIGGER IF EXISTS answers_fts_delete;
+DROP TRIGGER IF EXISTS answers_fts_insert;
+DROP TABLE IF EXISTS answers_fts;
+
+DROP TRIGGER IF EXISTS questions_fts_update;
+DROP TRIGGER IF EXISTS questions_fts_delete;
+DROP TRIGGER IF EXISTS questions_fts_insert;
+DROP TABLE IF EXISTS questions_fts;
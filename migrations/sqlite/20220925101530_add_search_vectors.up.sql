@@ -0,0 +1,46 @@
+-- Add up migration script here
+-- tsvectorの代わりにFTS5の外部コンテンツテーブルをトリガーで同期する
+CREATE VIRTUAL TABLE IF NOT EXISTS questions_fts USING fts5(
+  title,
+  content,
+  content = 'questions',
+  content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_insert AFTER INSERT ON questions BEGIN
+  INSERT INTO questions_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_delete AFTER DELETE ON questions BEGIN
+  INSERT INTO questions_fts (questions_fts, rowid, title, content)
+  VALUES ('delete', old.id, old.title, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_update AFTER UPDATE OF title, content ON questions BEGIN
+  INSERT INTO questions_fts (questions_fts, rowid, title, content)
+  VALUES ('delete', old.id, old.title, old.content);
+  INSERT INTO questions_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS answers_fts USING fts5(
+  content,
+  content = 'answers',
+  content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS answers_fts_insert AFTER INSERT ON answers BEGIN
+  INSERT INTO answers_fts (rowid, content) VALUES (new.id, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS answers_fts_delete AFTER DELETE ON answers BEGIN
+  INSERT INTO answers_fts (answers_fts, rowid, content) VALUES ('delete', old.id, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS answers_fts_update AFTER UPDATE OF content ON answers BEGIN
+  INSERT INTO answers_fts (answers_fts, rowid, content) VALUES ('delete', old.id, old.content);
+  INSERT INTO answers_fts (rowid, content) VALUES (new.id, new.content);
+END;
+
+-- 既存の行をインデックスに取り込む
+INSERT INTO questions_fts (questions_fts) VALUES ('rebuild');
+INSERT INTO answers_fts (answers_fts) VALUES ('rebuild');
@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS answers_question_id_idx;
+DROP INDEX IF EXISTS questions_title_idx;
+DROP INDEX IF EXISTS questions_last_activity_on_idx;
+DROP INDEX IF EXISTS questions_answer_count_idx;
+DROP INDEX IF EXISTS questions_created_on_idx;
+
+DROP TRIGGER IF EXISTS answers_track_delete;
+DROP TRIGGER IF EXISTS answers_track_insert;
+DROP TRIGGER IF EXISTS questions_touch_activity;
+DROP TRIGGER IF EXISTS questions_init_activity;
+
+ALTER TABLE questions DROP COLUMN last_activity_on;
+ALTER TABLE questions DROP COLUMN answer_count;
@@ -0,0 +1,38 @@
+-- Add up migration script here
+-- 並べ替え用に回答数と最終更新日時を非正規化して持つ
+-- INFO: ALTER TABLEでは既定値にCURRENT_TIMESTAMPを使えないので、挿入時にトリガーで埋める
+ALTER TABLE questions ADD COLUMN answer_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE questions ADD COLUMN last_activity_on TIMESTAMP;
+
+UPDATE questions SET
+  answer_count = (SELECT count(*) FROM answers WHERE answers.question_id = questions.id),
+  last_activity_on = max(
+    created_on,
+    coalesce((SELECT max(created_on) FROM answers WHERE answers.question_id = questions.id), created_on)
+  );
+
+CREATE TRIGGER IF NOT EXISTS questions_init_activity AFTER INSERT ON questions BEGIN
+  UPDATE questions SET last_activity_on = new.created_on WHERE id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS questions_touch_activity AFTER UPDATE OF title, content, tags ON questions BEGIN
+  UPDATE questions SET last_activity_on = CURRENT_TIMESTAMP WHERE id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS answers_track_insert AFTER INSERT ON answers BEGIN
+  UPDATE questions
+  SET answer_count = answer_count + 1,
+      last_activity_on = max(last_activity_on, new.created_on)
+  WHERE id = new.question_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS answers_track_delete AFTER DELETE ON answers BEGIN
+  UPDATE questions SET answer_count = answer_count - 1 WHERE id = old.question_id;
+END;
+
+-- 各並び順はidを第2キーにして一意に決まるようにする
+CREATE INDEX IF NOT EXISTS questions_created_on_idx ON questions (created_on, id);
+CREATE INDEX IF NOT EXISTS questions_answer_count_idx ON questions (answer_count, id);
+CREATE INDEX IF NOT EXISTS questions_last_activity_on_idx ON questions (last_activity_on, id);
+CREATE INDEX IF NOT EXISTS questions_title_idx ON questions (lower(title), id);
+CREATE INDEX IF NOT EXISTS answers_question_id_idx ON answers (question_id);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- 質問を編集するたびに、編集前の内容を1行として記録する
+CREATE TABLE IF NOT EXISTS question_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  question_id INTEGER NOT NULL REFERENCES questions ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  account_id INTEGER NOT NULL,
+  title TEXT NOT NULL,
+  content TEXT NOT NULL,
+  tags TEXT,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (question_id, revision)
+);
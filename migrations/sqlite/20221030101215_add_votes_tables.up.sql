@@ -0,0 +1,47 @@
+-- Add up migration script here
+-- 1アカウントにつき1つの投稿に1票(+1 / -1)まで
+CREATE TABLE IF NOT EXISTS question_votes (
+  question_id INTEGER NOT NULL REFERENCES questions ON DELETE CASCADE,
+  account_id INTEGER NOT NULL,
+  value INTEGER NOT NULL CHECK (value IN (-1, 1)),
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (question_id, account_id)
+);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+  answer_id INTEGER NOT NULL REFERENCES answers ON DELETE CASCADE,
+  account_id INTEGER NOT NULL,
+  value INTEGER NOT NULL CHECK (value IN (-1, 1)),
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (answer_id, account_id)
+);
+
+-- 票の合計を非正規化して持ち、投票の追加・変更・取り消しに合わせてトリガーで更新する
+ALTER TABLE questions ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE answers ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+
+CREATE TRIGGER IF NOT EXISTS question_votes_insert AFTER INSERT ON question_votes BEGIN
+  UPDATE questions SET score = score + new.value WHERE id = new.question_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS question_votes_update AFTER UPDATE ON question_votes BEGIN
+  UPDATE questions SET score = score - old.value + new.value WHERE id = new.question_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS question_votes_delete AFTER DELETE ON question_votes BEGIN
+  UPDATE questions SET score = score - old.value WHERE id = old.question_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS answer_votes_insert AFTER INSERT ON answer_votes BEGIN
+  UPDATE answers SET score = score + new.value WHERE id = new.answer_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS answer_votes_update AFTER UPDATE ON answer_votes BEGIN
+  UPDATE answers SET score = score - old.value + new.value WHERE id = new.answer_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS answer_votes_delete AFTER DELETE ON answer_votes BEGIN
+  UPDATE answers SET score = score - old.value WHERE id = old.answer_id;
+END;
+
+CREATE INDEX IF NOT EXISTS questions_score_idx ON questions (score, id);
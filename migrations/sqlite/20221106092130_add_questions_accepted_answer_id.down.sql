@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS questions_accepted_answer_id_idx;
+ALTER TABLE questions DROP COLUMN accepted_answer_id;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- 質問か回答のどちらか一方に付く短いコメント
+CREATE TABLE IF NOT EXISTS comments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  content TEXT NOT NULL,
+  question_id INTEGER REFERENCES questions ON DELETE CASCADE,
+  answer_id INTEGER REFERENCES answers ON DELETE CASCADE,
+  account_id INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS comments_question_id_idx ON comments (question_id);
+CREATE INDEX IF NOT EXISTS comments_answer_id_idx ON comments (answer_id);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- ログインごとに1行作り、リフレッシュトークンのハッシュを持つ。
+-- リフレッシュのたびにハッシュを置き換え、ログアウトや再利用の検知で失効させる
+CREATE TABLE IF NOT EXISTS login_sessions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  account_id INTEGER NOT NULL,
+  refresh_token_hash TEXT NOT NULL,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS login_sessions_account_id_idx ON login_sessions (account_id);
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- アカウントのロール。最初の管理者はSQLで直接昇格させる
+ALTER TABLE accounts ADD COLUMN role TEXT NOT NULL DEFAULT 'user'
+  CHECK (role IN ('user', 'moderator', 'admin'));
@@ -0,0 +1,10 @@
+-- Add up migration script here
+-- パスワード再設定用のトークン。トークン自体は保存せず、SHA-256のハッシュで引く
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  account_id INTEGER NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP
+);
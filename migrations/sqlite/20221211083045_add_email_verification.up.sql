@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- メールアドレスを確認した日時。既存のアカウントは確認済みとして扱う
+ALTER TABLE accounts ADD COLUMN verified_at TIMESTAMP;
+UPDATE accounts SET verified_at = CURRENT_TIMESTAMP;
+
+-- メールアドレス確認用のトークン。password_reset_tokensと同じくハッシュで引く
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  account_id INTEGER NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP
+);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- 個人データの書き出し。バックグラウンドで作成し、完成したら内容とダウンロード用のトークンのハッシュを持つ
+CREATE TABLE IF NOT EXISTS data_exports (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  account_id INTEGER NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'ready', 'failed')),
+  archive TEXT,
+  token_hash TEXT UNIQUE,
+  created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS data_exports_account_id_idx ON data_exports (account_id);
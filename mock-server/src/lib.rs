@@ -4,6 +4,10 @@ use std::{collections::HashMap, net::SocketAddr};
 use tokio::sync::{oneshot, oneshot::Sender};
 use warp::{http, Filter, Reply};
 
+mod mail;
+
+pub use crate::mail::{CaptureMailer, LogMailer};
+
 #[derive(Clone, Debug)]
 pub struct MockServer {
     socket: SocketAddr,
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use mailer::{Mail, MailError, Mailer};
+use parking_lot::Mutex;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// SMTPサーバなしで動かすためのMailer。送信する代わりにログかファイルに書き出す
+#[derive(Debug, Clone, Default)]
+pub struct LogMailer {
+    /// 指定されていればメールを追記する。未指定の場合はログに出力する
+    path: Option<PathBuf>,
+}
+
+impl LogMailer {
+    pub fn new() -> Self {
+        LogMailer { path: None }
+    }
+
+    pub fn to_file(path: impl Into<PathBuf>) -> Self {
+        LogMailer {
+            path: Some(path.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: Mail) -> Result<(), MailError> {
+        match &self.path {
+            Some(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| MailError(e.to_string()))?;
+                writeln!(
+                    file,
+                    "To: {}\nSubject: {}\n\n{}\n",
+                    mail.to, mail.subject, mail.body
+                )
+                .map_err(|e| MailError(e.to_string()))
+            }
+            None => {
+                tracing::event!(
+                    tracing::Level::INFO,
+                    to = %mail.to,
+                    subject = %mail.subject,
+                    body = %mail.body,
+                    "mail not sent (log mailer)"
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// テスト用のMailer。送信したメールを保持し、後から取り出せる
+#[derive(Debug, Clone, Default)]
+pub struct CaptureMailer {
+    sent: Arc<Mutex<Vec<Mail>>>,
+}
+
+impl CaptureMailer {
+    pub fn new() -> Self {
+        CaptureMailer::default()
+    }
+
+    /// これまでに送信されたメールを古い順に返す
+    pub fn sent(&self) -> Vec<Mail> {
+        self.sent.lock().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for CaptureMailer {
+    async fn send(&self, mail: Mail) -> Result<(), MailError> {
+        self.sent.lock().push(mail);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use question_and_answer::{config, run, setup_store};
+use question_and_answer::{config, run, setup_mailer, setup_rate_limiter, setup_store};
 
 #[tokio::main]
 async fn main() -> Result<(), handle_errors::Error> {
@@ -7,13 +7,15 @@ async fn main() -> Result<(), handle_errors::Error> {
 
     let config = config::Config::new().expect("Config can't be set");
     let store = setup_store(&config).await?;
+    let mailer = setup_mailer(&config)?;
+    let rate_limiter = setup_rate_limiter(&config)?;
 
     tracing::info!(
         "Q&A service build ID {}",
         env!("QUESTION_AND_ANSWER_VERSION")
     );
 
-    run(config, store).await;
+    run(config, store, mailer, rate_limiter).await;
 
     Ok(())
 }
@@ -1,6 +1,9 @@
 use clap::Parser;
 use std::env;
 
+use crate::ratelimit::RateLimit;
+use crate::types::account::AccountDeletion;
+
 #[derive(Debug, Parser, PartialEq)]
 #[clap(author, version, about, long_about = None)]
 pub struct Config {
@@ -18,6 +21,41 @@ pub struct Config {
     pub port: u16,
     #[clap(long, default_value = "password")]
     pub database_password: String,
+    /// 接続先のURL。`sqlite://`で始まる場合はSQLite、`memory://`の場合はインメモリのストアを使う。
+    /// 未指定の場合は`database_*`の各項目からPostgreSQLのURLを組み立てる
+    #[clap(long)]
+    pub database_url: Option<String>,
+    /// 削除された質問を完全に削除するまでの日数
+    #[clap(long, default_value = "30")]
+    pub deleted_retention_days: u32,
+    /// メールの送信先。`smtp://host:port`の場合はSMTPサーバ、`file:///path`の場合はファイルに追記し、
+    /// `log:`の場合はログに出力するだけで送信しない
+    #[clap(long, default_value = "log:")]
+    pub mailer_url: String,
+    /// 送信するメールの差出人
+    #[clap(long, default_value = "noreply@localhost")]
+    pub mail_from: String,
+    /// アカウントを削除する際の投稿の扱い。`anonymize`の場合は投稿を残して匿名化し、
+    /// `cascade`の場合は投稿も削除する
+    #[clap(long, default_value = "anonymize")]
+    pub account_deletion: AccountDeletion,
+    /// 全てのルートを合わせたレート制限。クライアントのIPアドレスごとに適用する
+    #[clap(long, default_value = "600/min")]
+    pub rate_limit_global: RateLimit,
+    /// 読み取りのルートのレート制限。`120/min`のように回数と期間の単位(`s`、`min`、`h`)で指定し、
+    /// ルートごと、クライアントごとに適用する
+    #[clap(long, default_value = "120/min")]
+    pub rate_limit: RateLimit,
+    /// 認証したアカウントによる書き込みのルートのレート制限
+    #[clap(long, default_value = "30/min")]
+    pub rate_limit_write: RateLimit,
+    /// ログインや登録、メールを送るルートのレート制限
+    #[clap(long, default_value = "10/min")]
+    pub rate_limit_auth: RateLimit,
+    /// レート制限の状態の保存先。`memory:`の場合はプロセスのメモリに持ち、
+    /// `redis://host:port`の場合はRedis互換のサーバに保存して複数のプロセスで共有する
+    #[clap(long, default_value = "memory:")]
+    pub rate_limit_url: String,
 }
 
 impl Config {
@@ -42,9 +80,18 @@ impl Config {
             .unwrap_or(Ok(config.port))
             .map_err(handle_errors::Error::ParseError)?;
 
+        let database_url = env::var("DATABASE_URL")
+            .ok()
+            .or_else(|| config.database_url.to_owned());
+
         let database_user =
             env::var("POSTGRES_USER").unwrap_or_else(|_| config.database_user.to_owned());
-        let database_password = env::var("POSTGRES_PASSWORD").unwrap();
+        // INFO: PostgreSQLのURLを組み立てる場合のみパスワードを必須とする
+        let database_password = match database_url {
+            Some(_) => env::var("POSTGRES_PASSWORD")
+                .unwrap_or_else(|_| config.database_password.to_owned()),
+            None => env::var("POSTGRES_PASSWORD").unwrap(),
+        };
         let database_host =
             env::var("POSTGRES_HOST").unwrap_or_else(|_| config.database_host.to_owned());
         let database_port =
@@ -52,6 +99,36 @@ impl Config {
         let database_name =
             env::var("POSTGRES_DB").unwrap_or_else(|_| config.database_name.to_owned());
 
+        let mailer_url = env::var("MAILER_URL").unwrap_or_else(|_| config.mailer_url.to_owned());
+        let mail_from = env::var("MAIL_FROM").unwrap_or_else(|_| config.mail_from.to_owned());
+        let account_deletion = match env::var("ACCOUNT_DELETION") {
+            Ok(value) => value.parse().map_err(|_| {
+                handle_errors::Error::InvalidParameter("account_deletion".to_string())
+            })?,
+            Err(_) => config.account_deletion,
+        };
+        let deleted_retention_days = match env::var("DELETED_RETENTION_DAYS") {
+            Ok(value) => value.parse::<u32>().map_err(|_| {
+                handle_errors::Error::InvalidParameter("deleted_retention_days".to_string())
+            })?,
+            Err(_) => config.deleted_retention_days,
+        };
+        let rate_limit_global = rate_limit_from_env(
+            "RATE_LIMIT_GLOBAL",
+            "rate_limit_global",
+            config.rate_limit_global,
+        )?;
+        let rate_limit = rate_limit_from_env("RATE_LIMIT", "rate_limit", config.rate_limit)?;
+        let rate_limit_write = rate_limit_from_env(
+            "RATE_LIMIT_WRITE",
+            "rate_limit_write",
+            config.rate_limit_write,
+        )?;
+        let rate_limit_auth =
+            rate_limit_from_env("RATE_LIMIT_AUTH", "rate_limit_auth", config.rate_limit_auth)?;
+        let rate_limit_url =
+            env::var("RATE_LIMIT_URL").unwrap_or_else(|_| config.rate_limit_url.to_owned());
+
         Ok(Config {
             log_level: config.log_level,
             port,
@@ -62,10 +139,34 @@ impl Config {
                 .parse::<u16>()
                 .map_err(handle_errors::Error::ParseError)?,
             database_name,
+            database_url,
+            deleted_retention_days,
+            mailer_url,
+            mail_from,
+            account_deletion,
+            rate_limit_global,
+            rate_limit,
+            rate_limit_write,
+            rate_limit_auth,
+            rate_limit_url,
         })
     }
 }
 
+/// 環境変数`name`が設定されていればレート制限として読み、なければ`default`を返す
+fn rate_limit_from_env(
+    name: &str,
+    parameter: &str,
+    default: RateLimit,
+) -> Result<RateLimit, handle_errors::Error> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| handle_errors::Error::InvalidParameter(parameter.to_string())),
+        Err(_) => Ok(default),
+    }
+}
+
 #[cfg(test)]
 mod config_tests {
     use super::*;
@@ -96,10 +197,26 @@ mod config_tests {
             database_host: "localhost".to_string(),
             database_port: 5432,
             database_name: "rustwebdev".to_string(),
+            database_url: None,
+            deleted_retention_days: 30,
+            mailer_url: "log:".to_string(),
+            mail_from: "noreply@localhost".to_string(),
+            account_deletion: AccountDeletion::Anonymize,
+            rate_limit_global: "600/min".parse().unwrap(),
+            rate_limit: "120/min".parse().unwrap(),
+            rate_limit_write: "30/min".parse().unwrap(),
+            rate_limit_auth: "10/min".parse().unwrap(),
+            rate_limit_url: "memory:".to_string(),
         };
 
         let config = Config::new().unwrap();
 
         assert_eq!(expected, config);
+
+        env::set_var("DELETED_RETENTION_DAYS", "7");
+        assert_eq!(Config::new().unwrap().deleted_retention_days, 7);
+        env::set_var("DELETED_RETENTION_DAYS", "never");
+        assert!(Config::new().is_err());
+        env::remove_var("DELETED_RETENTION_DAYS");
     }
 }
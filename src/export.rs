@@ -0,0 +1,82 @@
+use chrono::prelude::*;
+use mailer::{DynMailer, Mail};
+use tokio::task::JoinHandle;
+use tracing::{event, Level};
+
+use crate::routes::authentication::{hash_secret, random_secret};
+use crate::routes::email::public_url;
+use crate::store::DynStore;
+use crate::types::account::AccountInfo;
+
+/// 書き出しをダウンロードできる期間
+pub const EXPORT_LIFETIME_HOURS: i64 = 24;
+/// 作成中の書き出しがあれば新たに依頼させない期間。
+/// 作成中にプロセスが終了した書き出しは作成中のまま残るので、この期間を過ぎたら作り直せるようにする
+pub const PENDING_EXPORT_TIMEOUT_MINUTES: i64 = 10;
+
+/// アカウントのデータを書き出すタスクを起動する。完成したらダウンロード用のリンクをメールで送る
+pub fn spawn(
+    store: DynStore,
+    mailer: DynMailer,
+    export_id: i32,
+    account: AccountInfo,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        match run(&store, &mailer, export_id, account).await {
+            Ok(()) => event!(Level::INFO, export_id, "data export is ready"),
+            Err(e) => {
+                event!(
+                    Level::ERROR,
+                    export_id,
+                    "could not export account data: {}",
+                    e
+                );
+                // INFO: 記録できずに作成中のまま残っても、`PENDING_EXPORT_TIMEOUT_MINUTES`を過ぎれば再度依頼できる
+                if let Err(e) = store.fail_data_export(export_id).await {
+                    event!(
+                        Level::ERROR,
+                        export_id,
+                        "could not mark export as failed: {}",
+                        e
+                    );
+                }
+            }
+        }
+    })
+}
+
+async fn run(
+    store: &DynStore,
+    mailer: &DynMailer,
+    export_id: i32,
+    account: AccountInfo,
+) -> Result<(), handle_errors::Error> {
+    let export = store.get_account_export(account.id).await?;
+    let archive =
+        serde_json::to_string_pretty(&export).expect("account exports are always serializable");
+
+    let token = random_secret();
+    let expires_at = (Utc::now() + chrono::Duration::hours(EXPORT_LIFETIME_HOURS)).naive_utc();
+    store
+        .complete_data_export(export_id, archive, hash_secret(&token), expires_at)
+        .await?;
+
+    let mail = Mail {
+        to: account.email,
+        subject: "Your data export is ready".to_string(),
+        body: format!(
+            "Download your data from the following link within {} hours.\n\n\
+            {}/v1/account/export/download?token={}\n\n\
+            If you did not request an export, change your password.",
+            EXPORT_LIFETIME_HOURS,
+            public_url(),
+            token
+        ),
+    };
+    // INFO: 送信に失敗しても書き出しは完成しているので、ログに残して再度の依頼に任せる
+    if let Err(e) = mailer.send(mail).await {
+        event!(Level::ERROR, export_id, "could not send export link: {}", e);
+    }
+
+    Ok(())
+}
@@ -1,34 +1,145 @@
 #![warn(clippy::all)]
+// INFO: ルートを`or`で連結したフィルターの型が深くなるため
+#![recursion_limit = "256"]
 pub use handle_errors;
 
+use mailer::{DynMailer, SmtpMailer};
+use mock_server::LogMailer;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
 use tokio::sync::{oneshot, oneshot::Sender};
 use tracing_subscriber::fmt::format::FmtSpan;
+use warp::filters::BoxedFilter;
+use warp::hyper::body::Bytes;
 use warp::hyper::Method;
+use warp::path::FullPath;
 use warp::Filter;
 
+use crate::ratelimit::RateLimiter;
+use crate::types::account::{AccountDeletion, Role};
+use crate::types::comment::CommentTarget;
+
 pub mod config;
+mod export;
+mod lockout;
+mod openapi;
 mod profanity;
+mod purge;
+pub mod ratelimit;
 mod routes;
-mod store;
-mod types;
+pub mod store;
+pub mod types;
 
-async fn build_routes(store: store::Store) -> impl Filter<Extract = impl warp::Reply> + Clone {
-    // INFO: storeをmapのコールバック内に所有権を移動しているので、各storeの操作が終わった後にfilter化
-    let store_filter = warp::any().map(move || store.clone());
+/// バージョンを付けないパスを廃止する日時(`Sunset`ヘッダーの値)
+const UNVERSIONED_SUNSET: &str = "Sat, 01 Jul 2023 00:00:00 GMT";
 
+async fn build_routes(
+    store: store::DynStore,
+    mailer: DynMailer,
+    account_deletion: AccountDeletion,
+    rate_limiter: RateLimiter,
+) -> impl Filter<Extract = impl warp::Reply> + Clone {
     // CORS
     let cors = warp::cors()
         .allow_any_origin()
         .allow_header("content-type")
-        .allow_methods(&[Method::PUT, Method::DELETE, Method::GET, Method::POST]);
+        .allow_methods(&[
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::GET,
+            Method::POST,
+        ]);
+
+    let global = rate_limiter.global();
+    let v1 = v1_routes(store, mailer, account_deletion, rate_limiter);
+
+    // INFO: バージョンを付けないパスは/v1の非推奨の別名として残す
+    let unversioned = warp::path::full().and(v1.clone()).map(deprecated);
+
+    // INFO: 存在しないパスへのリクエストも数えるよう、ルートより先に全体の制限をかける
+    global
+        .and(warp::path("v1").and(v1).or(unversioned))
+        .with(cors)
+        .with(warp::trace::request())
+        .recover(handle_errors::return_error)
+}
+
+/// 非推奨の別名で呼ばれたことを記録し、レスポンスに`Deprecation`と`Sunset`ヘッダーを付ける
+fn deprecated(path: FullPath, reply: Box<dyn warp::Reply>) -> impl warp::Reply {
+    tracing::event!(
+        tracing::Level::WARN,
+        path = %path.as_str(),
+        "deprecated unversioned route was used"
+    );
+    let reply = warp::reply::with_header(reply, "Deprecation", "true");
+    warp::reply::with_header(reply, "Sunset", UNVERSIONED_SUNSET)
+}
+
+/// JSONかフォーム(x-www-form-urlencoded)の本文
+///
+/// INFO: `body::json`はContent-Typeが合わなければ本文を読まずに弾くので、続けて`body::form`を試せる
+fn json_or_form<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::json().or(warp::body::form()).unify()
+}
+
+/// JSON Merge Patch(RFC 7396)の本文。`application/merge-patch+json`に加えて`application/json`も受け付ける
+///
+/// INFO: `body::json`は`application/merge-patch+json`を受け付けないので、本文をそのまま読んでデシリアライズする
+fn merge_patch<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    let merge_patch = warp::header::<String>("content-type")
+        .and_then(|content_type: String| async move {
+            let essence = content_type.split(';').next().unwrap_or_default().trim();
+            if essence.eq_ignore_ascii_case("application/merge-patch+json") {
+                Ok(())
+            } else {
+                Err(warp::reject())
+            }
+        })
+        .untuple_one()
+        .and(warp::body::bytes())
+        .and_then(|body: Bytes| async move {
+            serde_json::from_slice(&body)
+                .map_err(|e| warp::reject::custom(handle_errors::Error::InvalidBody(e.to_string())))
+        });
+
+    warp::body::json().or(merge_patch).unify()
+}
+
+/// `/v1`以下のルート
+///
+/// INFO: `/v2`を追加する際は同様の関数を用意し、変更のないルートはこのフィルターを`or`で繋いで再利用する。
+/// 型を消しておくことで、複数のバージョンから使っても型が深くならない
+fn v1_routes(
+    store: store::DynStore,
+    mailer: DynMailer,
+    account_deletion: AccountDeletion,
+    rate_limiter: RateLimiter,
+) -> BoxedFilter<(Box<dyn warp::Reply>,)> {
+    // INFO: storeをmapのコールバック内に所有権を移動しているので、各storeの操作が終わった後にfilter化
+    let auth = routes::authentication::auth(store.clone());
+    let admin = routes::authentication::require_role(store.clone(), Role::Admin);
+    let verified = routes::authentication::require_verified(store.clone());
+    let store_filter = warp::any().map(move || store.clone());
+    let mailer_filter = warp::any().map(move || mailer.clone());
+    // INFO: メソッドはパスが一致してから確かめる。先に確かめると、warpは存在しないパスへのリクエストにも
+    // 他のルートのメソッドの不一致(405)を返してしまう
+    // INFO: ハンドラは`map`で呼んでFutureのまま受け取り、パスの一致と認証の後でレート制限を確かめてから実行する。
+    // ルートのFutureが大きくなるので、ルートごとにBoxに入れて`or`で連結したFutureをスタックに収める
+    let limits = rate_limiter.limits;
 
     // GET /questions
-    let get_questions = warp::get()
-        .and(warp::path("questions"))
+    let get_questions = warp::path("questions")
         .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::path::full())
         .and(warp::query())
         .and(store_filter.clone())
-        .and_then(routes::question::get_questions)
+        .map(routes::question::get_questions)
+        .and(rate_limiter.by_address("get_questions", limits.read))
+        .and_then(ratelimit::apply)
         .with(warp::trace(|info| {
             tracing::info_span!(
                 "get_questions request",
@@ -36,108 +147,680 @@ async fn build_routes(store: store::Store) -> impl Filter<Extract = impl warp::R
                 path = %info.path(),
                 id = %uuid::Uuid::new_v4()
             )
-        }));
+        }))
+        .boxed();
 
     // GET /questions/:question_id
-    let get_question = warp::get()
-        .and(warp::path("questions"))
+    let get_question = warp::path("questions")
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
+        .and(warp::get())
         .and(store_filter.clone())
-        .and_then(routes::question::get_question);
+        .map(routes::question::get_question)
+        .and(rate_limiter.by_address("get_question", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
 
     // POST /questions
-    let add_question = warp::post()
-        .and(warp::path("questions"))
+    let add_question = warp::path("questions")
         .and(warp::path::end())
+        .and(warp::post())
         .and(warp::body::json())
         .and(store_filter.clone())
-        .and(routes::authentication::auth())
-        .and_then(routes::question::add_question);
+        .and(verified.clone())
+        .map(routes::question::add_question)
+        .and(rate_limiter.by_account("add_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
 
     // PUT /questions/:question_id
-    let update_question = warp::put()
-        .and(warp::path("questions"))
+    let update_question = warp::path("questions")
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
+        .and(warp::put())
         .and(warp::body::json())
         .and(store_filter.clone())
-        .and(routes::authentication::auth())
-        .and_then(routes::question::update_question);
+        .and(auth.clone())
+        .map(routes::question::update_question)
+        .and(rate_limiter.by_account("update_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PATCH /questions/:question_id
+    let patch_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::patch())
+        .and(merge_patch())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::question::patch_question)
+        .and(rate_limiter.by_account("patch_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/restore
+    let restore_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::question::restore_question)
+        .and(rate_limiter.by_account("restore_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
 
     // DELETE /questions/:question_id
-    let delete_question = warp::delete()
-        .and(warp::path("questions"))
+    let delete_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::question::delete_question)
+        .and(rate_limiter.by_account("delete_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/accept/:answer_id
+    let accept_answer = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::question::accept_answer)
+        .and(rate_limiter.by_account("accept_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // DELETE /questions/:question_id/accept/:answer_id
+    let unaccept_answer = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
+        .and(warp::delete())
         .and(store_filter.clone())
-        .and(routes::authentication::auth())
-        .and_then(routes::question::delete_question);
+        .and(auth.clone())
+        .map(routes::question::unaccept_answer)
+        .and(rate_limiter.by_account("unaccept_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
 
-    // POST /answers (x-www-form-urlencoded)
-    // INFO: /questions/:question_id/answers にルートを変更
-    let add_answer = warp::post()
+    // GET /questions/:question_id/answers
+    let get_answers = warp::path("questions")
+        .and(warp::path::param::<i32>())
         .and(warp::path("answers"))
         .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .map(routes::answer::get_answers)
+        .and(rate_limiter.by_address("get_answers", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /answers/:answer_id
+    let get_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .map(routes::answer::get_answer)
+        .and(rate_limiter.by_address("get_answer", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/answers
+    // INFO: 以前の`POST /answers`はフォームの本文だったので、JSONに加えてフォームも受け付ける
+    let add_answer = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(json_or_form())
+        .and(store_filter.clone())
+        .and(verified)
+        .map(routes::answer::add_answer)
+        .and(rate_limiter.by_account("add_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PUT /answers/:answer_id
+    let update_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::answer::update_answer)
+        .and(rate_limiter.by_account("update_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PATCH /answers/:answer_id
+    let patch_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::patch())
+        .and(merge_patch())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::answer::patch_answer)
+        .and(rate_limiter.by_account("patch_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // DELETE /answers/:answer_id
+    let delete_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::answer::delete_answer)
+        .and(rate_limiter.by_account("delete_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/vote
+    let vote_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::vote::vote_question)
+        .and(rate_limiter.by_account("vote_question", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /answers/:answer_id/vote
+    let vote_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::vote::vote_answer)
+        .and(rate_limiter.by_account("vote_answer", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /questions/:question_id/comments
+    let get_question_comments = warp::path("questions")
+        .and(warp::path::param::<i32>().map(CommentTarget::Question))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .map(routes::comment::get_comments)
+        .and(rate_limiter.by_address("get_question_comments", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/comments
+    let add_question_comment = warp::path("questions")
+        .and(warp::path::param::<i32>().map(CommentTarget::Question))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::comment::add_comment)
+        .and(rate_limiter.by_account("add_question_comment", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /answers/:answer_id/comments
+    let get_answer_comments = warp::path("answers")
+        .and(warp::path::param::<i32>().map(CommentTarget::Answer))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::get())
         .and(store_filter.clone())
-        .and(warp::body::form())
-        .and(routes::authentication::auth())
-        .and_then(routes::answer::add_answer);
+        .map(routes::comment::get_comments)
+        .and(rate_limiter.by_address("get_answer_comments", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /answers/:answer_id/comments
+    let add_answer_comment = warp::path("answers")
+        .and(warp::path::param::<i32>().map(CommentTarget::Answer))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::comment::add_comment)
+        .and(rate_limiter.by_account("add_answer_comment", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PUT /comments/:comment_id
+    let update_comment = warp::path("comments")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::comment::update_comment)
+        .and(rate_limiter.by_account("update_comment", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // DELETE /comments/:comment_id
+    let delete_comment = warp::path("comments")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::comment::delete_comment)
+        .and(rate_limiter.by_account("delete_comment", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /questions/:question_id/revisions
+    let get_revisions = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("revisions"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .map(routes::revision::get_revisions)
+        .and(rate_limiter.by_address("get_revisions", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /questions/:question_id/revisions/diff?from=1&to=2
+    let get_revision_diff = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("revisions"))
+        .and(warp::path("diff"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::revision::get_revision_diff)
+        .and(rate_limiter.by_address("get_revision_diff", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /questions/:question_id/revisions/:revision/restore
+    let restore_revision = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("revisions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::revision::restore_revision)
+        .and(rate_limiter.by_account("restore_revision", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /tags
+    let get_tags = warp::path("tags")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::tag::get_tags)
+        .and(rate_limiter.by_address("get_tags", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /tags/:name/questions
+    let get_tag_questions = warp::path("tags")
+        .and(warp::path::param::<String>())
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::path::full())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::tag::get_tag_questions)
+        .and(rate_limiter.by_address("get_tag_questions", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /search?q=...
+    let search = warp::path("search")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::search::search)
+        .and(rate_limiter.by_address("search", limits.read))
+        .and_then(ratelimit::apply)
+        .with(warp::trace(|info| {
+            tracing::info_span!(
+                "search request",
+                method = %info.method(),
+                path = %info.path(),
+                id = %uuid::Uuid::new_v4()
+            )
+        }))
+        .boxed();
 
     // POST /registration
-    let registration = warp::post()
-        .and(warp::path("registration"))
+    let registration = warp::path("registration")
         .and(warp::path::end())
+        .and(warp::post())
         .and(store_filter.clone())
+        .and(mailer_filter.clone())
         .and(warp::body::json())
-        .and_then(routes::authentication::register);
+        .map(routes::authentication::register)
+        .and(rate_limiter.by_address("registration", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
 
     // POST /login
-    let login = warp::post()
-        .and(warp::path("login"))
+    let login_attempts = Arc::new(lockout::LoginAttempts::new());
+    let login = warp::path("login")
         .and(warp::path::end())
-        .and(store_filter)
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(warp::any().map(move || login_attempts.clone()))
+        .and(warp::addr::remote())
+        .and(warp::body::json())
+        .map(routes::authentication::login)
+        .and(rate_limiter.by_address("login", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /token/refresh
+    let refresh_token = warp::path("token")
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .map(routes::authentication::refresh)
+        .and(rate_limiter.by_address("refresh_token", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /password/forgot
+    let forgot_password = warp::path("password")
+        .and(warp::path("forgot"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(warp::body::json())
+        .map(routes::password::forgot_password)
+        .and(rate_limiter.by_address("forgot_password", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /password/reset
+    let reset_password = warp::path("password")
+        .and(warp::path("reset"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .map(routes::password::reset_password)
+        .and(rate_limiter.by_address("reset_password", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /email/verify
+    let verify_email = warp::path("email")
+        .and(warp::path("verify"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::email::verify_email)
+        .and(rate_limiter.by_address("verify_email", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /email/verification
+    let resend_verification = warp::path("email")
+        .and(warp::path("verification"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(auth.clone())
+        .map(routes::email::resend_verification)
+        .and(rate_limiter.by_account("resend_verification", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /logout
+    let logout = warp::path("logout")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::authentication::logout)
+        .and(rate_limiter.by_account("logout", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PUT /account/password
+    let change_password = warp::path("account")
+        .and(warp::path("password"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(store_filter.clone())
+        .and(auth.clone())
+        .map(routes::account::change_password)
+        .and(rate_limiter.by_account("change_password", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // DELETE /account
+    let delete_account = warp::path("account")
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(store_filter.clone())
+        .and(warp::any().map(move || account_deletion))
+        .and(auth.clone())
+        .map(routes::account::delete_account)
+        .and(rate_limiter.by_account("delete_account", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // POST /account/export
+    let request_export = warp::path("account")
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(mailer_filter)
+        .and(auth.clone())
+        .map(routes::account::request_export)
+        .and(rate_limiter.by_account("request_export", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /account/export
+    let get_export = warp::path("account")
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and(auth)
+        .map(routes::account::get_export)
+        .and(rate_limiter.by_account("get_export", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /account/export/download
+    let download_export = warp::path("account")
+        .and(warp::path("export"))
+        .and(warp::path("download"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .map(routes::account::download_export)
+        .and(rate_limiter.by_address("download_export", limits.auth))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /admin/accounts
+    let get_accounts = warp::path("admin")
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and(admin.clone())
+        .map(routes::admin::get_accounts)
+        .and(rate_limiter.by_account("get_accounts", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // PUT /admin/accounts/:account_id/role
+    let update_account_role = warp::path("admin")
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(warp::put())
         .and(warp::body::json())
-        .and_then(routes::authentication::login);
+        .and(store_filter)
+        .and(admin)
+        .map(routes::admin::update_account_role)
+        .and(rate_limiter.by_account("update_account_role", limits.write))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /openapi.json
+    let get_openapi = warp::path("openapi.json")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(routes::docs::get_openapi)
+        .and(rate_limiter.by_address("get_openapi", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
+
+    // GET /docs
+    let get_docs = warp::path("docs")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(routes::docs::get_docs)
+        .and(rate_limiter.by_address("get_docs", limits.read))
+        .and_then(ratelimit::apply)
+        .boxed();
 
     get_questions
         .or(get_question)
         .or(add_question)
         .or(update_question)
+        .or(patch_question)
         .or(delete_question)
+        .or(restore_question)
+        .or(accept_answer)
+        .or(unaccept_answer)
+        .or(get_answers)
+        .or(get_answer)
         .or(add_answer)
+        .or(update_answer)
+        .or(patch_answer)
+        .or(delete_answer)
+        .or(vote_question)
+        .or(vote_answer)
+        .or(get_question_comments)
+        .or(add_question_comment)
+        .or(get_answer_comments)
+        .or(add_answer_comment)
+        .or(update_comment)
+        .or(delete_comment)
+        .or(get_revisions)
+        .or(get_revision_diff)
+        .or(restore_revision)
+        .or(get_tags)
+        .or(get_tag_questions)
+        .or(search)
         .or(registration)
         .or(login)
-        .with(cors)
-        .with(warp::trace::request())
-        .recover(handle_errors::return_error)
+        .or(refresh_token)
+        .or(logout)
+        .or(change_password)
+        .or(delete_account)
+        .or(request_export)
+        .or(get_export)
+        .or(download_export)
+        .or(forgot_password)
+        .or(reset_password)
+        .or(verify_email)
+        .or(resend_verification)
+        .or(get_accounts)
+        .or(update_account_role)
+        .or(get_openapi)
+        .or(get_docs)
+        .map(|reply| Box::new(reply) as Box<dyn warp::Reply>)
+        .boxed()
 }
 
-pub async fn setup_store(config: &config::Config) -> Result<store::Store, handle_errors::Error> {
+pub async fn setup_store(config: &config::Config) -> Result<store::DynStore, handle_errors::Error> {
     // Database
-    let store = store::Store::new(&format!(
-        "postgres://{}:{}@{}:{}/{}",
-        config.database_user,
-        config.database_password,
-        config.database_host,
-        config.database_port,
-        config.database_name
-    ))
-    .await;
+    let database_url = config.database_url.clone().unwrap_or_else(|| {
+        format!(
+            "postgres://{}:{}@{}:{}/{}",
+            config.database_user,
+            config.database_password,
+            config.database_host,
+            config.database_port,
+            config.database_name
+        )
+    });
 
-    // Migration
-    // INFO: ディレクトリを指定しないと、ALTER TABLEが効かなかったので追加
-    sqlx::migrate!("./migrations")
-        .run(&store.clone().conn)
-        .await
-        .expect("Cannnot run migration");
+    // INFO: URLのスキームでバックエンドを切り替える
+    let store: store::DynStore = if database_url.starts_with("sqlite:") {
+        let store = store::sqlite::SqliteStore::new(&database_url).await?;
+
+        // Migration
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&store.conn)
+            .await
+            .expect("Cannnot run migration");
+
+        Arc::new(store)
+    } else if database_url.starts_with("memory:") {
+        Arc::new(store::memory::MemoryStore::new())
+    } else {
+        let store = store::postgres::PostgresStore::new(&database_url).await?;
+
+        // Migration
+        // INFO: ディレクトリを指定しないと、ALTER TABLEが効かなかったので追加
+        sqlx::migrate!("./migrations")
+            .run(&store.conn)
+            .await
+            .expect("Cannnot run migration");
+
+        Arc::new(store)
+    };
 
     // Logging & Tracing
     // INFO: ログレベルを各モジュールごとにセット
     // 当アプリケーション(question_and_answer) / warp内部 / 自作モジュール(handler_errors)内部
+    // 特権操作の記録(audit)はログレベルに関わらず常に出力する
     let log_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
         format!(
-            "handle_errors={},question_and_answer={},warp={}",
+            "handle_errors={},question_and_answer={},warp={},audit=info",
             config.log_level, config.log_level, config.log_level
         )
     });
@@ -151,8 +834,62 @@ pub async fn setup_store(config: &config::Config) -> Result<store::Store, handle
     Ok(store)
 }
 
-pub async fn run(config: config::Config, store: store::Store) {
-    let routes = build_routes(store).await;
+/// URLのスキームでメールの送信先を切り替える
+pub fn setup_mailer(config: &config::Config) -> Result<DynMailer, handle_errors::Error> {
+    let url = config.mailer_url.as_str();
+    let mailer: DynMailer = if let Some(addr) = url.strip_prefix("smtp://") {
+        Arc::new(SmtpMailer::new(
+            addr.trim_end_matches('/'),
+            config.mail_from.clone(),
+        ))
+    } else if let Some(path) = url.strip_prefix("file://") {
+        Arc::new(LogMailer::to_file(path))
+    } else if url == "log:" {
+        Arc::new(LogMailer::new())
+    } else {
+        return Err(handle_errors::Error::InvalidParameter(
+            "mailer_url".to_string(),
+        ));
+    };
+
+    Ok(mailer)
+}
+
+/// URLのスキームでレート制限の状態の保存先を切り替える
+pub fn setup_rate_limiter(config: &config::Config) -> Result<RateLimiter, handle_errors::Error> {
+    let url = config.rate_limit_url.as_str();
+    let store: ratelimit::DynRateLimitStore = if let Some(addr) = url.strip_prefix("redis://") {
+        Arc::new(ratelimit::redis::RedisRateLimitStore::new(
+            addr.trim_end_matches('/'),
+        ))
+    } else if url == "memory:" {
+        Arc::new(ratelimit::memory::MemoryRateLimitStore::new())
+    } else {
+        return Err(handle_errors::Error::InvalidParameter(
+            "rate_limit_url".to_string(),
+        ));
+    };
+
+    Ok(RateLimiter::new(
+        store,
+        ratelimit::RateLimits {
+            global: config.rate_limit_global,
+            read: config.rate_limit,
+            write: config.rate_limit_write,
+            auth: config.rate_limit_auth,
+        },
+    ))
+}
+
+pub async fn run(
+    config: config::Config,
+    store: store::DynStore,
+    mailer: DynMailer,
+    rate_limiter: RateLimiter,
+) {
+    purge::spawn(store.clone(), config.deleted_retention_days);
+
+    let routes = build_routes(store, mailer, config.account_deletion, rate_limiter).await;
     warp::serve(routes).run(([0, 0, 0, 0], config.port)).await;
 }
 
@@ -161,8 +898,14 @@ pub struct OneshotHandler {
 }
 
 /// 統合テスト用に瞬間的に本番と同じ環境のサーバを立ち上げる関数
-pub async fn oneshot(store: store::Store) -> OneshotHandler {
-    let routes = build_routes(store).await;
+pub async fn oneshot(store: store::DynStore, mailer: DynMailer) -> OneshotHandler {
+    let routes = build_routes(
+        store,
+        mailer,
+        AccountDeletion::default(),
+        RateLimiter::default(),
+    )
+    .await;
     let (tx, rx) = oneshot::channel::<i32>();
 
     let socket: std::net::SocketAddr = "127.0.0.1:3030"
@@ -178,3 +921,1283 @@ pub async fn oneshot(store: store::Store) -> OneshotHandler {
 
     OneshotHandler { sender: tx }
 }
+
+#[cfg(test)]
+mod routes_tests {
+    use super::*;
+    use crate::ratelimit::{memory::MemoryRateLimitStore, RateLimits};
+    use crate::store::{memory::MemoryStore, Store};
+    use crate::types::{
+        account::{AccountId, AccountInfo, TokenPair},
+        answer::NewAnswer,
+        comment::NewComment,
+        question::{NewQuestion, Question},
+    };
+    use mock_server::CaptureMailer;
+
+    /// 送信したメールを破棄するMailer
+    fn mailer() -> DynMailer {
+        Arc::new(CaptureMailer::new())
+    }
+
+    /// 既定の設定でインメモリのストアに対するルートを組み立てる
+    async fn routes(store: Arc<MemoryStore>) -> impl Filter<Extract = impl warp::Reply> + Clone {
+        build_routes(
+            store,
+            mailer(),
+            AccountDeletion::default(),
+            RateLimiter::default(),
+        )
+        .await
+    }
+
+    /// 質問をストアに直接作る(投稿時の検査APIを呼ばないため)
+    async fn add_question(store: &MemoryStore, account_id: AccountId) -> Question {
+        store
+            .add_question(
+                NewQuestion {
+                    title: "title".to_string(),
+                    content: "content".to_string(),
+                    tags: None,
+                },
+                account_id,
+            )
+            .await
+            .unwrap()
+    }
+
+    /// アカウントを登録してログインし、アクセストークンを返す
+    async fn sign_in<F>(routes: &F, email: &str) -> String
+    where
+        F: Filter + 'static,
+        F::Extract: warp::Reply + Send,
+    {
+        sign_in_with_tokens(routes, email).await.access_token
+    }
+
+    async fn sign_in_with_tokens<F>(routes: &F, email: &str) -> TokenPair
+    where
+        F: Filter + 'static,
+        F::Extract: warp::Reply + Send,
+    {
+        std::env::set_var("TOKEN_SECRET_KEY", "7ZcbZPVuSTL4UasiGi3iwrZzWhKZadBY");
+        let account = serde_json::json!({ "email": email, "password": "secret" });
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&account)
+            .reply(routes)
+            .await;
+        serde_json::from_slice(res.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_run_against_memory_store() {
+        let store = MemoryStore::new();
+        add_question(&store, AccountId(1)).await;
+        let routes = routes(Arc::new(store)).await;
+
+        let res = warp::test::request()
+            .path("/questions/1")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .path("/questions/1/answers")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body(), "[]");
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/answers")
+            .json(&serde_json::json!({ "content": "answer" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn unversioned_paths_are_deprecated_aliases() {
+        let routes = routes(Arc::new(MemoryStore::new())).await;
+
+        let res = warp::test::request()
+            .path("/v1/questions")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(res.headers().get("deprecation").is_none());
+
+        let res = warp::test::request()
+            .path("/questions")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["deprecation"], "true");
+        assert_eq!(res.headers()["sunset"], UNVERSIONED_SUNSET);
+
+        let res = warp::test::request()
+            .path("/v1/v1/questions")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn errors_are_returned_as_problem_details() {
+        let routes = routes(Arc::new(MemoryStore::new())).await;
+
+        let res = warp::test::request()
+            .path("/questions/99")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.headers()["content-type"], "application/problem+json");
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "not_found");
+        assert_eq!(problem["status"], 404);
+        assert!(problem["request_id"].is_string());
+
+        let res = warp::test::request()
+            .path("/questions?tag_mode=some")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 400);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            problem["errors"],
+            serde_json::json!([{ "field": "tag_mode", "message": "Invalid value" }])
+        );
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1")
+            .header("Authorization", "not a token")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "invalid_token");
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens_rotate_and_logout_revokes_the_session() {
+        let routes = routes(Arc::new(MemoryStore::new())).await;
+        let tokens = sign_in_with_tokens(&routes, "refresh@example.com").await;
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let rotated: TokenPair = serde_json::from_slice(res.body()).unwrap();
+        assert_ne!(rotated.refresh_token, tokens.refresh_token);
+
+        // 置き換え済みのトークンの再利用はセッションごと失効させる
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "invalid_refresh_token");
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": rotated.refresh_token }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/logout")
+            .header("Authorization", rotated.access_token)
+            .reply(&routes)
+            .await;
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "session_revoked");
+
+        // ログアウト後は同じアクセストークンが使えない
+        let tokens = sign_in_with_tokens(&routes, "logout@example.com").await;
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/logout")
+            .header("Authorization", tokens.access_token.clone())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/v1/questions/1")
+            .header("Authorization", tokens.access_token)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "session_revoked");
+    }
+
+    #[tokio::test]
+    async fn unverified_accounts_cannot_post_until_they_follow_the_emailed_link() {
+        let mailer = CaptureMailer::new();
+        let routes = build_routes(
+            Arc::new(MemoryStore::new()),
+            Arc::new(mailer.clone()),
+            AccountDeletion::default(),
+            RateLimiter::default(),
+        )
+        .await;
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/registration")
+            .json(&serde_json::json!({ "email": "not an email", "password": "secret" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 400);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "email");
+
+        let token = sign_in(&routes, "verify@example.com").await;
+        let post_answer = || {
+            warp::test::request()
+                .method("POST")
+                .path("/v1/questions/99/answers")
+                .header("Authorization", token.clone())
+                .json(&serde_json::json!({ "content": "answer" }))
+        };
+
+        let res = post_answer().reply(&routes).await;
+        assert_eq!(res.status(), 403);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "email_not_verified");
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "verify@example.com");
+        let link = sent[0].body.lines().nth(2).unwrap();
+        let path = &link[link.find("/v1/").unwrap()..];
+
+        let res = warp::test::request().path(path).reply(&routes).await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request().path(path).reply(&routes).await;
+        assert_eq!(res.status(), 400);
+
+        // INFO: 同じアクセストークンのまま投稿できるようになる(質問がないので404まで進む)
+        let res = post_answer().reply(&routes).await;
+        assert_eq!(res.status(), 404);
+
+        // 以前の`POST /answers`と同じフォームの本文も受け付ける
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/questions/99/answers")
+            .header("Authorization", token.clone())
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("content=answer")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn passwords_can_be_reset_once_with_the_emailed_token() {
+        let mailer = CaptureMailer::new();
+        let routes = build_routes(
+            Arc::new(MemoryStore::new()),
+            Arc::new(mailer.clone()),
+            AccountDeletion::default(),
+            RateLimiter::default(),
+        )
+        .await;
+        let tokens = sign_in_with_tokens(&routes, "reset@example.com").await;
+
+        // 存在しないアカウントでも同じレスポンスを返し、メールは送らない
+        for email in ["unknown@example.com", "reset@example.com"] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/password/forgot")
+                .json(&serde_json::json!({ "email": email }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 200);
+        }
+        // 登録時の確認メールに続いて、再設定のメールが1通だけバックグラウンドで送られる
+        let mut sent = mailer.sent();
+        for _ in 0..100 {
+            if sent.len() > 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            sent = mailer.sent();
+        }
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, "reset@example.com");
+        let token = sent[1].body.lines().nth(2).unwrap();
+
+        let reset = serde_json::json!({ "token": token, "password": "new secret" });
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/password/reset")
+            .json(&reset)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/password/reset")
+            .json(&reset)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 400);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "invalid_reset_token");
+
+        // 再設定前のセッションは失効している
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        for (password, status) in [("secret", 401), ("new secret", 200)] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/login")
+                .json(&serde_json::json!({ "email": "reset@example.com", "password": password }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn changing_the_password_requires_the_current_one_and_revokes_other_sessions() {
+        let routes = routes(Arc::new(MemoryStore::new())).await;
+        let tokens = sign_in_with_tokens(&routes, "change@example.com").await;
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/v1/account/password")
+            .header("Authorization", tokens.access_token.clone())
+            .json(&serde_json::json!({ "current_password": "wrong", "new_password": "new secret" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/v1/account/password")
+            .header("Authorization", tokens.access_token.clone())
+            .json(
+                &serde_json::json!({ "current_password": "secret", "new_password": "new secret" }),
+            )
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let renewed: TokenPair = serde_json::from_slice(res.body()).unwrap();
+
+        // 変更前のセッションは失効し、レスポンスで受け取ったトークンだけが使える
+        for (token, status) in [(tokens.access_token, 401), (renewed.access_token, 200)] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/logout")
+                .header("Authorization", token)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status);
+        }
+
+        for (password, status) in [("secret", 401), ("new secret", 200)] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/login")
+                .json(&serde_json::json!({ "email": "change@example.com", "password": password }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_login_failures_lock_existing_and_unknown_emails_alike() {
+        // INFO: レート制限より先にロックされることを確かめるため、ログインの制限を緩める
+        let rate_limiter = RateLimiter::new(
+            Arc::new(MemoryRateLimitStore::new()),
+            RateLimits {
+                auth: "100/min".parse().unwrap(),
+                ..RateLimits::default()
+            },
+        );
+        let routes = build_routes(
+            Arc::new(MemoryStore::new()),
+            mailer(),
+            AccountDeletion::default(),
+            rate_limiter,
+        )
+        .await;
+        sign_in(&routes, "locked@example.com").await;
+        let remote: std::net::SocketAddr = "192.0.2.1:50000".parse().unwrap();
+
+        for email in ["locked@example.com", "unknown@example.com"] {
+            for _ in 0..6 {
+                let res = warp::test::request()
+                    .method("POST")
+                    .path("/v1/login")
+                    .remote_addr(remote)
+                    .json(&serde_json::json!({ "email": email, "password": "wrong" }))
+                    .reply(&routes)
+                    .await;
+                assert_eq!(res.status(), 401, "{}", email);
+                let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+                assert_eq!(problem["code"], "wrong_password");
+            }
+
+            // ロック中は正しいパスワードでもログインできない
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/login")
+                .remote_addr(remote)
+                .json(&serde_json::json!({ "email": email, "password": "secret" }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 429, "{}", email);
+            assert_eq!(res.headers()["Retry-After"], "30");
+            let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(problem["code"], "too_many_attempts");
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_are_rate_limited_per_route_and_client() {
+        let rate_limiter = RateLimiter::new(
+            Arc::new(MemoryRateLimitStore::new()),
+            RateLimits {
+                read: "2/min".parse().unwrap(),
+                write: "1/min".parse().unwrap(),
+                ..RateLimits::default()
+            },
+        );
+        let routes = build_routes(
+            Arc::new(MemoryStore::new()),
+            mailer(),
+            AccountDeletion::default(),
+            rate_limiter,
+        )
+        .await;
+        let remote: std::net::SocketAddr = "192.0.2.1:50000".parse().unwrap();
+
+        for remaining in ["1", "0"] {
+            let res = warp::test::request()
+                .path("/v1/questions")
+                .remote_addr(remote)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 200);
+            assert_eq!(res.headers()["X-RateLimit-Limit"], "2");
+            assert_eq!(res.headers()["X-RateLimit-Remaining"], remaining);
+        }
+
+        let res = warp::test::request()
+            .path("/v1/questions")
+            .remote_addr(remote)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers()["Retry-After"], "30");
+        assert_eq!(res.headers()["X-RateLimit-Remaining"], "0");
+        assert_eq!(res.headers()["X-RateLimit-Reset"], "60");
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "rate_limited");
+
+        // 他のルートと他のアドレスは別に数える
+        let res = warp::test::request()
+            .path("/v1/tags")
+            .remote_addr(remote)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .path("/v1/questions")
+            .remote_addr("192.0.2.2:50000".parse().unwrap())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // 認証が必要なルートはアドレスではなくアカウントごとに数える
+        let first = sign_in(&routes, "first@example.com").await;
+        let second = sign_in(&routes, "second@example.com").await;
+        for (token, status) in [(&first, 401), (&first, 429), (&second, 401)] {
+            let res = warp::test::request()
+                .method("DELETE")
+                .path("/v1/questions/1")
+                .remote_addr(remote)
+                .header("Authorization", token)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_methods_and_content_types_are_not_reported_as_missing_routes() {
+        let routes = routes(Arc::new(MemoryStore::new())).await;
+        let token = sign_in(&routes, "a@example.com").await;
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/v1/tags")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 405);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "method_not_allowed");
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/questions")
+            .header("Authorization", &token)
+            .header("Content-Type", "text/plain")
+            .body("title")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 415);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "unsupported_media_type");
+
+        for path in ["/v1/unknown", "/v1/questions/abc", "/unknown"] {
+            let res = warp::test::request().path(path).reply(&routes).await;
+            assert_eq!(res.status(), 404, "{}", path);
+            let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(problem["code"], "route_not_found");
+        }
+    }
+
+    #[tokio::test]
+    async fn all_routes_share_a_global_limit_per_client() {
+        let rate_limiter = RateLimiter::new(
+            Arc::new(MemoryRateLimitStore::new()),
+            RateLimits {
+                global: "3/min".parse().unwrap(),
+                ..RateLimits::default()
+            },
+        );
+        let routes = build_routes(
+            Arc::new(MemoryStore::new()),
+            mailer(),
+            AccountDeletion::default(),
+            rate_limiter,
+        )
+        .await;
+        let remote: std::net::SocketAddr = "192.0.2.1:50000".parse().unwrap();
+
+        for (path, status) in [
+            ("/v1/questions", 200),
+            ("/v1/tags", 200),
+            ("/v1/unknown", 404),
+            ("/v1/questions", 429),
+        ] {
+            let res = warp::test::request()
+                .path(path)
+                .remote_addr(remote)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status, "{}", path);
+        }
+
+        // ルートごとの制限の残りをヘッダーに返す
+        let res = warp::test::request()
+            .path("/v1/questions")
+            .remote_addr("192.0.2.2:50000".parse().unwrap())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["X-RateLimit-Limit"], "120");
+    }
+
+    #[tokio::test]
+    async fn deleted_accounts_leave_anonymized_posts_or_take_them_along() {
+        for (mode, status) in [
+            (AccountDeletion::Anonymize, 200),
+            (AccountDeletion::Cascade, 404),
+        ] {
+            let store = Arc::new(MemoryStore::new());
+            let routes = build_routes(store.clone(), mailer(), mode, RateLimiter::default()).await;
+            let token = sign_in(&routes, "leaving@example.com").await;
+            add_question(&store, AccountId(1)).await;
+
+            let res = warp::test::request()
+                .method("DELETE")
+                .path("/v1/account")
+                .header("Authorization", token.clone())
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 200);
+
+            let res = warp::test::request()
+                .method("DELETE")
+                .path("/v1/account")
+                .header("Authorization", token)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 401);
+            let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(problem["code"], "session_revoked");
+
+            let res = warp::test::request()
+                .path("/v1/questions/1")
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), status, "{:?}", mode);
+            if mode == AccountDeletion::Anonymize {
+                assert_eq!(
+                    store.get_question_owner(1).await.unwrap(),
+                    AccountId::DELETED
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn data_exports_are_built_in_the_background_and_emailed_as_a_link() {
+        let mailer = CaptureMailer::new();
+        let store = Arc::new(MemoryStore::new());
+        let routes = build_routes(
+            store.clone(),
+            Arc::new(mailer.clone()),
+            AccountDeletion::default(),
+            RateLimiter::default(),
+        )
+        .await;
+        let token = sign_in(&routes, "export@example.com").await;
+        add_question(&store, AccountId(1)).await;
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/account/export")
+            .header("Authorization", token.clone())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 202);
+
+        // 完成するまでは202で状態が返り、完成したらそのままダウンロードできる
+        let mut res = None;
+        for _ in 0..100 {
+            let polled = warp::test::request()
+                .path("/v1/account/export")
+                .header("Authorization", token.clone())
+                .reply(&routes)
+                .await;
+            if polled.status() != 202 {
+                res = Some(polled);
+                break;
+            }
+            let export: serde_json::Value = serde_json::from_slice(polled.body()).unwrap();
+            assert_eq!(export["status"], "pending");
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let res = res.expect("export was not ready in time");
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers()["Content-Disposition"],
+            "attachment; filename=\"account-export.json\""
+        );
+        let archive: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(archive["account"]["email"], "export@example.com");
+        assert_eq!(archive["questions"][0]["title"], "title");
+
+        // 登録時の確認メールに続いて、ダウンロード用のリンクが送られる
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 2);
+        let link = sent[1].body.lines().nth(2).unwrap();
+        let path = link.trim_start_matches("http://localhost:8080");
+
+        let res = warp::test::request().path(path).reply(&routes).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers()["Content-Disposition"],
+            "attachment; filename=\"account-export.json\""
+        );
+        let archive: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(archive["account"]["email"], "export@example.com");
+
+        let res = warp::test::request()
+            .path("/v1/account/export/download?token=invalid")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn moderators_can_delete_any_question_and_admins_manage_roles() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        sign_in(&routes, "owner@example.com").await;
+        let tokens = sign_in_with_tokens(&routes, "staff@example.com").await;
+        add_question(&store, AccountId(1)).await;
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/v1/questions/1")
+            .header("Authorization", tokens.access_token.clone())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .path("/v1/admin/accounts")
+            .header("Authorization", tokens.access_token)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 403);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "forbidden");
+
+        // INFO: ロールの変更はリフレッシュしたアクセストークンから反映される
+        store
+            .update_account_role(AccountId(2), Role::Admin)
+            .await
+            .unwrap();
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+            .reply(&routes)
+            .await;
+        let token = serde_json::from_slice::<TokenPair>(res.body())
+            .unwrap()
+            .access_token;
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/v1/questions/1")
+            .header("Authorization", token.clone())
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .path("/v1/admin/accounts")
+            .header("Authorization", token.clone())
+            .reply(&routes)
+            .await;
+        let accounts: Vec<AccountInfo> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            accounts.iter().map(|a| a.role).collect::<Vec<_>>(),
+            vec![Role::User, Role::Admin]
+        );
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/v1/admin/accounts/1/role")
+            .header("Authorization", token.clone())
+            .json(&serde_json::json!({ "role": "moderator" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let account: AccountInfo = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(account.role, Role::Moderator);
+
+        // 自分自身のロールは変更できない
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/v1/admin/accounts/2/role")
+            .header("Authorization", token)
+            .json(&serde_json::json!({ "role": "user" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn questions_can_be_filtered_by_tags() {
+        let store = MemoryStore::new();
+        for tags in [vec!["rust", "warp"], vec!["rust"], vec!["c++"]] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: "title".to_string(),
+                        content: "content".to_string(),
+                        tags: Some(tags.into_iter().map(String::from).collect()),
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+        let routes = routes(Arc::new(store)).await;
+
+        let count = |body: &[u8]| {
+            serde_json::from_slice::<Vec<serde_json::Value>>(body)
+                .unwrap()
+                .len()
+        };
+
+        let res = warp::test::request()
+            .path("/questions?tag=rust&tag=warp")
+            .reply(&routes)
+            .await;
+        assert_eq!(count(res.body()), 2);
+
+        let res = warp::test::request()
+            .path("/questions?tag=rust&tag=warp&tag_mode=all&limit=10&offset=0")
+            .reply(&routes)
+            .await;
+        assert_eq!(count(res.body()), 1);
+
+        let res = warp::test::request()
+            .path("/tags/c%2B%2B/questions")
+            .reply(&routes)
+            .await;
+        assert_eq!(count(res.body()), 1);
+
+        let res = warp::test::request().path("/tags").reply(&routes).await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap()[0],
+            serde_json::json!({ "name": "rust", "count": 2 })
+        );
+    }
+
+    #[tokio::test]
+    async fn question_listing_links_to_next_page() {
+        let store = MemoryStore::new();
+        for title in ["first", "second"] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: title.to_string(),
+                        content: "content".to_string(),
+                        tags: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+        let routes = routes(Arc::new(store)).await;
+
+        let res = warp::test::request()
+            .path("/questions?limit=1&offset=0")
+            .reply(&routes)
+            .await;
+        let link = res.headers()["link"].to_str().unwrap().to_string();
+        let next = link
+            .trim_start_matches('<')
+            .split('>')
+            .next()
+            .unwrap()
+            .to_string();
+        assert!(link.ends_with(r#"rel="next""#));
+
+        let res = warp::test::request().path(&next).reply(&routes).await;
+        let body: Vec<serde_json::Value> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body[0]["title"], "second");
+        assert!(res.headers().get("link").is_none());
+    }
+
+    #[tokio::test]
+    async fn question_can_be_patched_without_moderating_unchanged_fields() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let token = sign_in(&routes, "a@example.com").await;
+
+        // INFO: 登録したアカウントの質問をストアに直接作る(投稿時の検査APIを呼ばないため)
+        store
+            .add_question(
+                NewQuestion {
+                    title: "title".to_string(),
+                    content: "content".to_string(),
+                    tags: Some(vec!["rust".to_string()]),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        // タイトルは変わっていないので検査されず、タグだけが更新される
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/questions/1")
+            .header("Authorization", &token)
+            .json(&serde_json::json!({ "title": "title", "tags": null }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap(),
+            serde_json::json!({
+                "id": 1,
+                "title": "title",
+                "content": "content",
+                "tags": null,
+                "score": 0,
+                "accepted_answer_id": null,
+            })
+        );
+
+        // RFC 7396のメディアタイプでも受け付ける
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/v1/questions/1")
+            .header("Authorization", &token)
+            .header("Content-Type", "application/merge-patch+json")
+            .body(r#"{"tags":["rust"]}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["tags"], serde_json::json!(["rust"]));
+
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/v1/questions/1")
+            .header("Authorization", &token)
+            .header("Content-Type", "application/merge-patch+json")
+            .body(r#"{"title":1}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 422);
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(problem["code"], "invalid_body");
+    }
+
+    #[tokio::test]
+    async fn question_revisions_can_be_diffed_and_restored() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let token = sign_in(&routes, "a@example.com").await;
+        let question = store
+            .add_question(
+                NewQuestion {
+                    title: "title".to_string(),
+                    content: "first line\nsecond line".to_string(),
+                    tags: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        store
+            .update_question(
+                Question {
+                    content: "first line\nchanged line".to_string(),
+                    ..question
+                },
+                1,
+                AccountId(1),
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let res = warp::test::request()
+            .path("/questions/1/revisions/diff?from=1")
+            .reply(&routes)
+            .await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap()["content"],
+            serde_json::json!([
+                { "op": "equal", "line": "first line" },
+                { "op": "delete", "line": "second line" },
+                { "op": "insert", "line": "changed line" },
+            ])
+        );
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/revisions/1/restore")
+            .header("Authorization", &token)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            store.get_question(1).await.unwrap().content,
+            "first line\nsecond line"
+        );
+
+        // ロールバック前の内容も版として残る
+        let res = warp::test::request()
+            .path("/questions/1/revisions")
+            .reply(&routes)
+            .await;
+        let revisions: Vec<serde_json::Value> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[1]["content"], "first line\nchanged line");
+    }
+
+    #[tokio::test]
+    async fn moderator_edits_are_recorded_as_the_moderators_revisions() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        sign_in(&routes, "owner@example.com").await;
+        sign_in(&routes, "moderator@example.com").await;
+        store
+            .update_account_role(AccountId(2), Role::Moderator)
+            .await
+            .unwrap();
+        let res = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "moderator@example.com", "password": "secret" }))
+            .reply(&routes)
+            .await;
+        let token = serde_json::from_slice::<TokenPair>(res.body())
+            .unwrap()
+            .access_token;
+        store
+            .add_question(
+                NewQuestion {
+                    title: "title".to_string(),
+                    content: "content".to_string(),
+                    tags: Some(vec!["rust".to_string()]),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/questions/1")
+            .header("Authorization", &token)
+            .json(&serde_json::json!({ "tags": null }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // 質問の所有者は変わらず、版には編集したモデレーターが記録される
+        assert_eq!(store.get_question_owner(1).await.unwrap(), AccountId(1));
+        let res = warp::test::request()
+            .path("/questions/1/revisions")
+            .reply(&routes)
+            .await;
+        let revisions: Vec<serde_json::Value> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0]["account_id"], 2);
+    }
+
+    #[tokio::test]
+    async fn only_question_owner_can_accept_an_answer() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let owner = sign_in(&routes, "a@example.com").await;
+        let other = sign_in(&routes, "b@example.com").await;
+        add_question(&store, AccountId(1)).await;
+        store
+            .add_answer(
+                1,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/accept/1")
+            .header("Authorization", &other)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/accept/1")
+            .header("Authorization", &owner)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap()["accepted_answer_id"],
+            1
+        );
+
+        let res = warp::test::request()
+            .path("/questions?unaccepted=true")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.body().as_ref(), b"[]");
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1/accept/1")
+            .header("Authorization", &owner)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(store
+            .get_question(1)
+            .await
+            .unwrap()
+            .accepted_answer_id
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn answers_can_only_be_changed_by_their_author() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let author = sign_in(&routes, "a@example.com").await;
+        let other = sign_in(&routes, "b@example.com").await;
+        add_question(&store, AccountId(2)).await;
+        store
+            .add_answer(
+                1,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/2")
+            .header("Authorization", &author)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 404);
+
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/answers/1")
+            .header("Authorization", &other)
+            .json(&serde_json::json!({ "content": "changed" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("PATCH")
+            .path("/answers/1")
+            .header("Authorization", &author)
+            .header("Content-Type", "application/merge-patch+json")
+            .body(r#"{"content":"answer"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // 本文が変わらなければ検査APIを呼ばずにそのまま返す
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/answers/1")
+            .header("Authorization", &author)
+            .json(&serde_json::json!({ "content": "answer" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/1")
+            .header("Authorization", &author)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(store.get_answers(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn comments_can_only_be_changed_by_their_author() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let author = sign_in(&routes, "a@example.com").await;
+        let other = sign_in(&routes, "b@example.com").await;
+        add_question(&store, AccountId(2)).await;
+        store
+            .add_comment(
+                CommentTarget::Question(1),
+                NewComment {
+                    content: "comment".to_string(),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let res = warp::test::request()
+            .path("/questions/1/comments")
+            .reply(&routes)
+            .await;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap(),
+            serde_json::json!([
+                { "id": 1, "content": "comment", "question_id": 1, "answer_id": null }
+            ])
+        );
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/comments/1")
+            .header("Authorization", &other)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/comments/1")
+            .header("Authorization", &author)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(store
+            .get_comments(CommentTarget::Question(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn questions_can_be_voted_on_but_not_by_their_owner() {
+        let store = Arc::new(MemoryStore::new());
+        let routes = routes(store.clone()).await;
+        let owner = sign_in(&routes, "a@example.com").await;
+        let voter = sign_in(&routes, "b@example.com").await;
+        add_question(&store, AccountId(1)).await;
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/vote")
+            .header("Authorization", &owner)
+            .json(&serde_json::json!({ "vote": "up" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 403);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions/1/vote")
+            .header("Authorization", &voter)
+            .json(&serde_json::json!({ "vote": "down" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(res.body()).unwrap()["score"],
+            -1
+        );
+    }
+}
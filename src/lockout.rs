@@ -0,0 +1,242 @@
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// ロックせずに許す、1つのアカウントに対する連続した失敗の回数
+const ACCOUNT_FREE_ATTEMPTS: u32 = 5;
+/// ロックせずに許す、1つのIPアドレスからの失敗の回数。
+/// 同じアドレスを複数の利用者が共有している場合があるので、アカウントより多くする
+const ADDRESS_FREE_ATTEMPTS: u32 = 20;
+/// 最初のロックの長さ。以降は失敗するたびに倍にする
+const LOCKOUT_BASE: Duration = Duration::from_secs(30);
+const LOCKOUT_MAX: Duration = Duration::from_secs(60 * 60);
+/// 最後の失敗からこの期間が過ぎたら、失敗の回数を数え直す
+const ATTEMPT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// 保持するキーの数の上限。超える場合は最後の失敗が最も古いキーから取り除く
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+struct Entry {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_key: HashMap<String, Entry>,
+    /// `(last_failure, key)`の順に並べ、最も古いキーを走査せずに取り除けるようにする
+    by_last_failure: BTreeSet<(Instant, String)>,
+}
+
+/// キーごとに失敗を数え、`free_attempts`回を超えて失敗したキーを指数的に長くロックする
+///
+/// INFO: 上限に達したら最も長く失敗していないキーを取り除くので、大量のキーで失敗を重ねればロックを早く解除させられる。
+/// メモリを際限なく使われるよりは良いとする
+#[derive(Debug)]
+struct Lockout {
+    free_attempts: u32,
+    entries: Mutex<Entries>,
+}
+
+impl Lockout {
+    fn new(free_attempts: u32) -> Self {
+        Lockout {
+            free_attempts,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// ロック中であれば解除までの時間を返す
+    fn locked_for(&self, key: &str, now: Instant) -> Option<Duration> {
+        let entries = self.entries.lock();
+        let locked_until = entries.by_key.get(key)?.locked_until?;
+        (locked_until > now).then(|| locked_until - now)
+    }
+
+    fn record_failure(&self, key: &str, now: Instant) {
+        let mut entries = self.entries.lock();
+        let Entries {
+            by_key,
+            by_last_failure,
+        } = &mut *entries;
+
+        if !by_key.contains_key(key) && by_key.len() >= MAX_ENTRIES {
+            if let Some((_, oldest)) = by_last_failure.pop_first() {
+                by_key.remove(&oldest);
+            }
+        }
+
+        let entry = by_key.entry(key.to_string()).or_insert(Entry {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        by_last_failure.remove(&(entry.last_failure, key.to_string()));
+        by_last_failure.insert((now, key.to_string()));
+        if now.duration_since(entry.last_failure) >= ATTEMPT_WINDOW {
+            entry.failures = 0;
+        }
+        entry.failures += 1;
+        entry.last_failure = now;
+
+        if entry.failures > self.free_attempts {
+            let doublings = entry.failures - self.free_attempts - 1;
+            let duration = LOCKOUT_BASE
+                .checked_mul(2u32.saturating_pow(doublings))
+                .map_or(LOCKOUT_MAX, |duration| duration.min(LOCKOUT_MAX));
+            entry.locked_until = Some(now + duration);
+        }
+    }
+
+    fn reset(&self, key: &str) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.by_key.remove(key) {
+            entries
+                .by_last_failure
+                .remove(&(entry.last_failure, key.to_string()));
+        }
+    }
+}
+
+/// ログインの失敗をアカウントとIPアドレスごとに数え、総当たりを防ぐ
+///
+/// INFO: 存在しないアカウントのemailも同じように数えてロックし、ロックの有無からアカウントの存在が分からないようにする。
+/// カウンタはプロセスのメモリに持つので、複数のプロセスで動かす場合は上限がプロセス数倍になる
+#[derive(Debug)]
+pub struct LoginAttempts {
+    accounts: Lockout,
+    addresses: Lockout,
+}
+
+impl Default for LoginAttempts {
+    fn default() -> Self {
+        LoginAttempts::new()
+    }
+}
+
+impl LoginAttempts {
+    pub fn new() -> Self {
+        LoginAttempts {
+            accounts: Lockout::new(ACCOUNT_FREE_ATTEMPTS),
+            addresses: Lockout::new(ADDRESS_FREE_ATTEMPTS),
+        }
+    }
+
+    /// アカウントとアドレスのどちらかがロック中であれば、長い方の解除までの時間でエラーを返す
+    pub fn check(&self, email: &str, addr: Option<IpAddr>) -> Result<(), handle_errors::Error> {
+        let now = Instant::now();
+        let account = self.accounts.locked_for(&account_key(email), now);
+        let address = addr.and_then(|addr| self.addresses.locked_for(&addr.to_string(), now));
+
+        match account.max(address) {
+            // INFO: 端数を切り上げ、`Retry-After`の秒数を待てば必ず解除されているようにする
+            Some(duration) => Err(handle_errors::Error::TooManyAttempts(
+                duration.as_secs() + u64::from(duration.subsec_nanos() > 0),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, email: &str, addr: Option<IpAddr>) {
+        let now = Instant::now();
+        self.accounts.record_failure(&account_key(email), now);
+        if let Some(addr) = addr {
+            self.addresses.record_failure(&addr.to_string(), now);
+        }
+    }
+
+    /// アカウントの失敗の回数を数え直す
+    ///
+    /// INFO: アドレスの回数は戻さない。自分のアカウントに時々ログインすることで、他のアカウントへの試行を続けられてしまうため
+    pub fn record_success(&self, email: &str) {
+        self.accounts.reset(&account_key(email));
+    }
+}
+
+/// 大文字と小文字を変えて別のキーとして試せないよう、emailを正規化する
+fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod lockout_tests {
+    use super::*;
+
+    #[test]
+    fn lockout_doubles_after_the_free_attempts() {
+        let lockout = Lockout::new(2);
+        let start = Instant::now();
+
+        for _ in 0..2 {
+            lockout.record_failure("key", start);
+            assert_eq!(lockout.locked_for("key", start), None);
+        }
+
+        lockout.record_failure("key", start);
+        assert_eq!(lockout.locked_for("key", start), Some(LOCKOUT_BASE));
+        lockout.record_failure("key", start);
+        assert_eq!(lockout.locked_for("key", start), Some(LOCKOUT_BASE * 2));
+        assert_eq!(lockout.locked_for("key", start + LOCKOUT_BASE * 2), None);
+
+        for _ in 0..20 {
+            lockout.record_failure("key", start);
+        }
+        assert_eq!(lockout.locked_for("key", start), Some(LOCKOUT_MAX));
+        assert_eq!(lockout.locked_for("other", start), None);
+
+        // 期間を過ぎてからの失敗は1回目として数える
+        let later = start + ATTEMPT_WINDOW;
+        lockout.record_failure("key", later);
+        assert_eq!(lockout.locked_for("key", later), None);
+    }
+
+    #[test]
+    fn the_oldest_keys_are_evicted_beyond_max_entries() {
+        let lockout = Lockout::new(0);
+        let start = Instant::now();
+
+        for i in 0..MAX_ENTRIES + 10 {
+            let now = start + Duration::from_millis(i as u64);
+            lockout.record_failure(&format!("key{}", i), now);
+        }
+        let entries = lockout.entries.lock();
+        assert_eq!(entries.by_key.len(), MAX_ENTRIES);
+        assert_eq!(entries.by_last_failure.len(), MAX_ENTRIES);
+        assert!(!entries.by_key.contains_key("key9"));
+        assert!(entries.by_key.contains_key("key10"));
+        drop(entries);
+
+        // 既にあるキーの失敗では取り除かず、最後の失敗の順だけを更新する
+        let later = start + Duration::from_secs(60);
+        lockout.record_failure("key10", later);
+        lockout.record_failure("new", later);
+        let entries = lockout.entries.lock();
+        assert_eq!(entries.by_key.len(), MAX_ENTRIES);
+        assert_eq!(entries.by_last_failure.len(), MAX_ENTRIES);
+        assert!(entries.by_key.contains_key("key10"));
+        assert!(!entries.by_key.contains_key("key11"));
+    }
+
+    #[test]
+    fn success_resets_the_account_but_not_the_address() {
+        let attempts = LoginAttempts::new();
+        let addr: IpAddr = "192.0.2.1".parse().unwrap();
+
+        for i in 0..ADDRESS_FREE_ATTEMPTS {
+            let email = format!("user{}@example.com", i % 2);
+            attempts.record_failure(&email, Some(addr));
+            attempts.record_success(&email);
+        }
+        assert!(attempts.check("user0@example.com", None).is_ok());
+
+        attempts.record_failure("user0@example.com", Some(addr));
+        match attempts.check("someone@example.com", Some(addr)) {
+            Err(handle_errors::Error::TooManyAttempts(secs)) => {
+                assert_eq!(secs, LOCKOUT_BASE.as_secs())
+            }
+            other => panic!("expected a lockout, got {:?}", other),
+        }
+    }
+}
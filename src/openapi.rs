@@ -0,0 +1,1076 @@
+use serde_json::{json, Map, Value};
+
+/// レスポンスの本文の種類
+enum Body {
+    /// `components.schemas`のスキーマ
+    Schema(&'static str),
+    /// `components.schemas`のスキーマの配列
+    Array(&'static str),
+    /// `Content-Disposition`でファイルとしてダウンロードさせる`components.schemas`のスキーマ
+    Download(&'static str),
+    /// プレーンテキスト
+    Text,
+    /// HTML
+    Html,
+    /// OpenAPIのドキュメント自体
+    Any,
+}
+
+/// 1つのルートの定義。`build_routes`のルートと1対1に対応させる
+struct Operation {
+    method: &'static str,
+    /// パスパラメータは`{question_id}`の形式で書く
+    path: &'static str,
+    summary: &'static str,
+    /// `Authorization`ヘッダーのトークンが必要かどうか
+    auth: bool,
+    /// `components.parameters`のクエリパラメータ
+    query: &'static [&'static str],
+    request: Option<&'static str>,
+    response: Body,
+}
+
+const LISTING: &[&str] = &["sort", "order", "limit", "offset", "cursor"];
+const QUESTION_LISTING: &[&str] = &[
+    "tag",
+    "tag_mode",
+    "unanswered",
+    "unaccepted",
+    "sort",
+    "order",
+    "limit",
+    "offset",
+    "cursor",
+];
+
+/// JSONに加えてフォーム(x-www-form-urlencoded)の本文も受け付けるルート
+const FORM_BODIES: &[(&str, &str)] = &[("post", "/questions/{question_id}/answers")];
+
+/// 結果がまだ用意できていなければ、`202 Accepted`で状態を返すルートとその本文のスキーマ
+const ACCEPTED_BODIES: &[(&str, &str, &str)] = &[("get", "/account/export", "DataExport")];
+
+const OPERATIONS: &[Operation] = &[
+    Operation {
+        method: "get",
+        path: "/questions",
+        summary: "List questions",
+        auth: false,
+        query: QUESTION_LISTING,
+        request: None,
+        response: Body::Array("Question"),
+    },
+    Operation {
+        method: "get",
+        path: "/questions/{question_id}",
+        summary: "Get a question",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "post",
+        path: "/questions",
+        summary: "Ask a question (verified email required)",
+        auth: true,
+        query: &[],
+        request: Some("NewQuestion"),
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "put",
+        path: "/questions/{question_id}",
+        summary: "Replace a question",
+        auth: true,
+        query: &[],
+        request: Some("Question"),
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "patch",
+        path: "/questions/{question_id}",
+        summary: "Update a question with a JSON merge patch",
+        auth: true,
+        query: &[],
+        request: Some("QuestionPatch"),
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/restore",
+        summary: "Restore a deleted question",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "delete",
+        path: "/questions/{question_id}",
+        summary: "Delete a question",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/accept/{answer_id}",
+        summary: "Accept an answer",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "delete",
+        path: "/questions/{question_id}/accept/{answer_id}",
+        summary: "Unaccept an answer",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "get",
+        path: "/questions/{question_id}/answers",
+        summary: "List the answers to a question",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Array("Answer"),
+    },
+    Operation {
+        method: "get",
+        path: "/answers/{answer_id}",
+        summary: "Get an answer",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Schema("Answer"),
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/answers",
+        summary: "Answer a question (verified email required)",
+        auth: true,
+        query: &[],
+        request: Some("NewAnswer"),
+        response: Body::Schema("Answer"),
+    },
+    Operation {
+        method: "put",
+        path: "/answers/{answer_id}",
+        summary: "Replace an answer",
+        auth: true,
+        query: &[],
+        request: Some("NewAnswer"),
+        response: Body::Schema("Answer"),
+    },
+    Operation {
+        method: "patch",
+        path: "/answers/{answer_id}",
+        summary: "Update an answer with a JSON merge patch",
+        auth: true,
+        query: &[],
+        request: Some("AnswerPatch"),
+        response: Body::Schema("Answer"),
+    },
+    Operation {
+        method: "delete",
+        path: "/answers/{answer_id}",
+        summary: "Delete an answer",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/vote",
+        summary: "Vote on a question",
+        auth: true,
+        query: &[],
+        request: Some("NewVote"),
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "post",
+        path: "/answers/{answer_id}/vote",
+        summary: "Vote on an answer",
+        auth: true,
+        query: &[],
+        request: Some("NewVote"),
+        response: Body::Schema("Answer"),
+    },
+    Operation {
+        method: "get",
+        path: "/questions/{question_id}/comments",
+        summary: "List the comments on a question",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Array("Comment"),
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/comments",
+        summary: "Comment on a question",
+        auth: true,
+        query: &[],
+        request: Some("NewComment"),
+        response: Body::Schema("Comment"),
+    },
+    Operation {
+        method: "get",
+        path: "/answers/{answer_id}/comments",
+        summary: "List the comments on an answer",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Array("Comment"),
+    },
+    Operation {
+        method: "post",
+        path: "/answers/{answer_id}/comments",
+        summary: "Comment on an answer",
+        auth: true,
+        query: &[],
+        request: Some("NewComment"),
+        response: Body::Schema("Comment"),
+    },
+    Operation {
+        method: "put",
+        path: "/comments/{comment_id}",
+        summary: "Edit a comment",
+        auth: true,
+        query: &[],
+        request: Some("NewComment"),
+        response: Body::Schema("Comment"),
+    },
+    Operation {
+        method: "delete",
+        path: "/comments/{comment_id}",
+        summary: "Delete a comment",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "get",
+        path: "/questions/{question_id}/revisions",
+        summary: "List the past revisions of a question",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Array("QuestionRevision"),
+    },
+    Operation {
+        method: "get",
+        path: "/questions/{question_id}/revisions/diff",
+        summary: "Diff two revisions of a question",
+        auth: false,
+        query: &["from", "to"],
+        request: None,
+        response: Body::Schema("RevisionDiff"),
+    },
+    Operation {
+        method: "post",
+        path: "/questions/{question_id}/revisions/{revision}/restore",
+        summary: "Roll a question back to a revision",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Schema("Question"),
+    },
+    Operation {
+        method: "get",
+        path: "/tags",
+        summary: "List tags by popularity",
+        auth: false,
+        query: &["limit", "offset"],
+        request: None,
+        response: Body::Array("Tag"),
+    },
+    Operation {
+        method: "get",
+        path: "/tags/{name}/questions",
+        summary: "List the questions with a tag",
+        auth: false,
+        query: LISTING,
+        request: None,
+        response: Body::Array("Question"),
+    },
+    Operation {
+        method: "get",
+        path: "/search",
+        summary: "Search questions and answers",
+        auth: false,
+        query: &["q", "limit", "offset"],
+        request: None,
+        response: Body::Array("SearchResult"),
+    },
+    Operation {
+        method: "post",
+        path: "/registration",
+        summary: "Register an account and send a verification email",
+        auth: false,
+        query: &[],
+        request: Some("Account"),
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/login",
+        summary: "Log in and receive an access token and a refresh token",
+        auth: false,
+        query: &[],
+        request: Some("Account"),
+        response: Body::Schema("TokenPair"),
+    },
+    Operation {
+        method: "post",
+        path: "/token/refresh",
+        summary: "Exchange a refresh token for a new token pair",
+        auth: false,
+        query: &[],
+        request: Some("RefreshRequest"),
+        response: Body::Schema("TokenPair"),
+    },
+    Operation {
+        method: "post",
+        path: "/logout",
+        summary: "Revoke the current login session",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "put",
+        path: "/account/password",
+        summary: "Change the password, revoking every other login session",
+        auth: true,
+        query: &[],
+        request: Some("ChangePasswordRequest"),
+        response: Body::Schema("TokenPair"),
+    },
+    Operation {
+        method: "delete",
+        path: "/account",
+        summary: "Delete the account, anonymizing or deleting its posts depending on the server configuration",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/account/export",
+        summary: "Request an export of all data linked to the account. The download link is emailed when it is ready",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Schema("DataExport"),
+    },
+    Operation {
+        method: "get",
+        path: "/account/export",
+        summary: "Download the latest data export. While it is not ready (pending, failed or expired), its status is returned with 202",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Download("AccountExport"),
+    },
+    Operation {
+        method: "get",
+        path: "/account/export/download",
+        summary: "Download a data export with the link from the export email",
+        auth: false,
+        query: &["token"],
+        request: None,
+        response: Body::Download("AccountExport"),
+    },
+    Operation {
+        method: "get",
+        path: "/email/verify",
+        summary: "Verify an email address with the link from the verification email",
+        auth: false,
+        query: &["token"],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/email/verification",
+        summary: "Send the verification email again",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/password/forgot",
+        summary: "Email a single-use password reset token",
+        auth: false,
+        query: &[],
+        request: Some("ForgotPasswordRequest"),
+        response: Body::Text,
+    },
+    Operation {
+        method: "post",
+        path: "/password/reset",
+        summary: "Set a new password with a reset token and revoke every session",
+        auth: false,
+        query: &[],
+        request: Some("ResetPasswordRequest"),
+        response: Body::Text,
+    },
+    Operation {
+        method: "get",
+        path: "/admin/accounts",
+        summary: "List accounts (admin only)",
+        auth: true,
+        query: &[],
+        request: None,
+        response: Body::Array("AccountInfo"),
+    },
+    Operation {
+        method: "put",
+        path: "/admin/accounts/{account_id}/role",
+        summary: "Change the role of an account (admin only)",
+        auth: true,
+        query: &[],
+        request: Some("RoleUpdate"),
+        response: Body::Schema("AccountInfo"),
+    },
+    Operation {
+        method: "get",
+        path: "/openapi.json",
+        summary: "This document",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Any,
+    },
+    Operation {
+        method: "get",
+        path: "/docs",
+        summary: "API reference page",
+        auth: false,
+        query: &[],
+        request: None,
+        response: Body::Html,
+    },
+];
+
+/// `GET /docs`で返すAPIリファレンスのページ
+pub const REDOC_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Q&amp;A API</title>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+  </head>
+  <body>
+    <redoc spec-url="/v1/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/v2.0.0/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+/// OpenAPI 3のドキュメントを組み立てる
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for operation in OPERATIONS {
+        let path = paths
+            .entry(operation.path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("path item is an object");
+        path.insert(operation.method.to_string(), operation.to_json());
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Q&A API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/v1" }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "parameters": parameters(),
+            "responses": {
+                "Problem": {
+                    "description": "Error",
+                    "content": {
+                        "application/problem+json": {
+                            "schema": { "$ref": "#/components/schemas/Problem" }
+                        }
+                    }
+                },
+                "RateLimited": {
+                    "description": "Rate limit exceeded",
+                    "headers": {
+                        "Retry-After": {
+                            "description": "Seconds until the next request is allowed",
+                            "schema": { "type": "integer" },
+                        },
+                        "X-RateLimit-Limit": { "$ref": "#/components/headers/X-RateLimit-Limit" },
+                        "X-RateLimit-Remaining": { "$ref": "#/components/headers/X-RateLimit-Remaining" },
+                        "X-RateLimit-Reset": { "$ref": "#/components/headers/X-RateLimit-Reset" },
+                    },
+                    "content": {
+                        "application/problem+json": {
+                            "schema": { "$ref": "#/components/schemas/Problem" }
+                        }
+                    }
+                }
+            },
+            "headers": {
+                "X-RateLimit-Limit": {
+                    "description": "Requests allowed per period for this route and client",
+                    "schema": { "type": "integer" },
+                },
+                "X-RateLimit-Remaining": {
+                    "description": "Requests left before the limit is reached",
+                    "schema": { "type": "integer" },
+                },
+                "X-RateLimit-Reset": {
+                    "description": "Seconds until the limit is fully restored",
+                    "schema": { "type": "integer" },
+                },
+            },
+            "securitySchemes": {
+                "token": {
+                    "type": "apiKey",
+                    "in": "header",
+                    "name": "Authorization",
+                    "description": "Access token returned by POST /login or POST /token/refresh",
+                }
+            },
+        },
+    })
+}
+
+impl Operation {
+    fn to_json(&self) -> Value {
+        let mut parameters: Vec<Value> = path_params(self.path)
+            .map(|name| {
+                let schema = if name == "name" {
+                    json!({ "type": "string" })
+                } else {
+                    json!({ "type": "integer" })
+                };
+                json!({ "name": name, "in": "path", "required": true, "schema": schema })
+            })
+            .collect();
+        parameters.extend(
+            self.query
+                .iter()
+                .map(|name| json!({ "$ref": format!("#/components/parameters/{}", name) })),
+        );
+
+        let content = match self.response {
+            Body::Schema(name) | Body::Download(name) => {
+                json!({ "application/json": { "schema": schema_ref(name) } })
+            }
+            Body::Array(name) => json!({
+                "application/json": { "schema": { "type": "array", "items": schema_ref(name) } }
+            }),
+            Body::Text => json!({ "text/plain": { "schema": { "type": "string" } } }),
+            Body::Html => json!({ "text/html": { "schema": { "type": "string" } } }),
+            Body::Any => json!({ "application/json": { "schema": { "type": "object" } } }),
+        };
+        // INFO: 全てのルートにレート制限をかけているので、`X-RateLimit-*`ヘッダーと429を共通で載せる
+        let mut headers = rate_limit_headers();
+        if matches!(self.response, Body::Array("Question")) {
+            headers["Link"] = json!({
+                "description": "URL of the next page, when there is one",
+                "schema": { "type": "string" },
+            });
+        }
+        if matches!(self.response, Body::Download(_)) {
+            headers["Content-Disposition"] = json!({
+                "description": "Suggested file name of the download",
+                "schema": { "type": "string" },
+            });
+        }
+        let mut responses = json!({
+            "200": { "description": "OK", "headers": headers, "content": content },
+            "429": { "$ref": "#/components/responses/RateLimited" },
+            "default": { "$ref": "#/components/responses/Problem" },
+        });
+        if let Some((_, _, name)) = ACCEPTED_BODIES
+            .iter()
+            .find(|(method, path, _)| (*method, *path) == (self.method, self.path))
+        {
+            responses["202"] = json!({
+                "description": "Not ready yet",
+                "headers": rate_limit_headers(),
+                "content": { "application/json": { "schema": schema_ref(name) } },
+            });
+        }
+
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": parameters,
+            "responses": responses,
+        });
+        if let Some(name) = self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema_ref(name) } },
+            });
+            if self.method == "patch" {
+                operation["requestBody"]["content"]["application/merge-patch+json"] =
+                    json!({ "schema": schema_ref(name) });
+            }
+            if FORM_BODIES.contains(&(self.method, self.path)) {
+                operation["requestBody"]["content"]["application/x-www-form-urlencoded"] =
+                    json!({ "schema": schema_ref(name) });
+            }
+        }
+        if self.auth {
+            operation["security"] = json!([{ "token": [] }]);
+        }
+
+        operation
+    }
+}
+
+/// 制限を通過した応答に付く`X-RateLimit-*`ヘッダー
+fn rate_limit_headers() -> Value {
+    let mut headers = Map::new();
+    for name in [
+        "X-RateLimit-Limit",
+        "X-RateLimit-Remaining",
+        "X-RateLimit-Reset",
+    ] {
+        headers.insert(
+            name.to_string(),
+            json!({ "$ref": format!("#/components/headers/{}", name) }),
+        );
+    }
+    Value::Object(headers)
+}
+
+/// `/questions/{question_id}`の`question_id`のようなパスパラメータの名前
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn parameters() -> Value {
+    let query = |name: &str, schema: Value, description: &str| json!({ "name": name, "in": "query", "schema": schema, "description": description });
+
+    json!({
+        "tag": query(
+            "tag",
+            json!({ "type": "array", "items": { "type": "string" } }),
+            "Tag to filter by. Can be repeated",
+        ),
+        "tag_mode": query(
+            "tag_mode",
+            json!({ "type": "string", "enum": ["any", "all"], "default": "any" }),
+            "Whether a question needs any or all of the tags",
+        ),
+        "unanswered": query(
+            "unanswered",
+            json!({ "type": "boolean", "default": false }),
+            "Only questions without answers",
+        ),
+        "unaccepted": query(
+            "unaccepted",
+            json!({ "type": "boolean", "default": false }),
+            "Only questions without an accepted answer",
+        ),
+        "sort": query(
+            "sort",
+            json!({
+                "type": "string",
+                "enum": ["newest", "oldest", "most_answers", "recently_active", "title", "score"],
+                "default": "oldest",
+            }),
+            "Ordering of the questions",
+        ),
+        "order": query(
+            "order",
+            json!({ "type": "string", "enum": ["asc", "desc"] }),
+            "Overrides the natural direction of sort",
+        ),
+        "limit": query("limit", json!({ "type": "integer", "minimum": 0 }), "Page size"),
+        "offset": query(
+            "offset",
+            json!({ "type": "integer", "minimum": 0 }),
+            "Number of items to skip",
+        ),
+        "cursor": query(
+            "cursor",
+            json!({ "type": "string" }),
+            "Opaque cursor from a Link header. Only for the default ordering",
+        ),
+        "q": {
+            "name": "q",
+            "in": "query",
+            "required": true,
+            "schema": { "type": "string" },
+            "description": "Search terms",
+        },
+        "from": {
+            "name": "from",
+            "in": "query",
+            "required": true,
+            "schema": { "type": "integer" },
+            "description": "Revision to diff from",
+        },
+        "to": query(
+            "to",
+            json!({ "type": "integer" }),
+            "Revision to diff to. Defaults to the current question",
+        ),
+        "token": {
+            "name": "token",
+            "in": "query",
+            "required": true,
+            "schema": { "type": "string" },
+            "description": "Token from the link in the email",
+        },
+    })
+}
+
+fn schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let integer = json!({ "type": "integer" });
+    let nullable_integer = json!({ "type": "integer", "nullable": true });
+    let tags = json!({ "type": "array", "items": { "type": "string" }, "nullable": true });
+    let timestamp = json!({
+        "type": "string",
+        "description": "UTC timestamp without an offset, e.g. 2022-10-16T10:30:20.123456",
+    });
+    let diff = json!({ "type": "array", "items": schema_ref("DiffLine") });
+
+    json!({
+        "Question": {
+            "type": "object",
+            "required": ["id", "title", "content"],
+            "properties": {
+                "id": integer,
+                "title": string,
+                "content": string,
+                "tags": tags,
+                "score": { "type": "integer", "readOnly": true },
+                "accepted_answer_id": { "type": "integer", "nullable": true, "readOnly": true },
+            },
+        },
+        "NewQuestion": {
+            "type": "object",
+            "required": ["title", "content"],
+            "properties": { "title": string, "content": string, "tags": tags },
+        },
+        "QuestionPatch": {
+            "type": "object",
+            "description": "JSON merge patch. Missing fields are left unchanged",
+            "properties": { "title": string, "content": string, "tags": tags },
+        },
+        "Answer": {
+            "type": "object",
+            "required": ["id", "content", "question_id"],
+            "properties": {
+                "id": integer,
+                "content": string,
+                "question_id": integer,
+                "score": { "type": "integer", "readOnly": true },
+            },
+        },
+        "NewAnswer": {
+            "type": "object",
+            "required": ["content"],
+            "properties": { "content": string },
+        },
+        "AnswerPatch": {
+            "type": "object",
+            "description": "JSON merge patch. Missing fields are left unchanged",
+            "properties": { "content": string },
+        },
+        "Comment": {
+            "type": "object",
+            "required": ["id", "content"],
+            "properties": {
+                "id": integer,
+                "content": string,
+                "question_id": nullable_integer,
+                "answer_id": nullable_integer,
+            },
+        },
+        "NewComment": {
+            "type": "object",
+            "required": ["content"],
+            "properties": { "content": string },
+        },
+        "NewVote": {
+            "type": "object",
+            "required": ["vote"],
+            "properties": {
+                "vote": { "type": "string", "enum": ["up", "down", "none"] },
+            },
+        },
+        "QuestionRevision": {
+            "type": "object",
+            "properties": {
+                "revision": integer,
+                "question_id": integer,
+                "account_id": integer,
+                "created_on": timestamp,
+                "title": string,
+                "content": string,
+                "tags": tags,
+            },
+        },
+        "DiffLine": {
+            "type": "object",
+            "properties": {
+                "op": { "type": "string", "enum": ["equal", "delete", "insert"] },
+                "line": string,
+            },
+        },
+        "RevisionDiff": {
+            "type": "object",
+            "properties": {
+                "from": integer,
+                "to": nullable_integer,
+                "title": diff,
+                "content": diff,
+                "tags": diff,
+            },
+        },
+        "SearchResult": {
+            "allOf": [
+                schema_ref("Question"),
+                {
+                    "type": "object",
+                    "properties": {
+                        "relevance": { "type": "number" },
+                        "snippet": string,
+                    },
+                },
+            ],
+        },
+        "Tag": {
+            "type": "object",
+            "properties": { "name": string, "count": integer },
+        },
+        "Account": {
+            "type": "object",
+            "required": ["email", "password"],
+            "properties": {
+                "email": string,
+                "password": { "type": "string", "format": "password" },
+            },
+        },
+        "ForgotPasswordRequest": {
+            "type": "object",
+            "required": ["email"],
+            "properties": { "email": string },
+        },
+        "ChangePasswordRequest": {
+            "type": "object",
+            "required": ["current_password", "new_password"],
+            "properties": {
+                "current_password": { "type": "string", "format": "password" },
+                "new_password": { "type": "string", "format": "password" },
+            },
+        },
+        "ResetPasswordRequest": {
+            "type": "object",
+            "required": ["token", "password"],
+            "properties": {
+                "token": { "type": "string", "description": "Token from the reset email. Valid for 60 minutes" },
+                "password": { "type": "string", "format": "password" },
+            },
+        },
+        "Role": {
+            "type": "string",
+            "enum": ["user", "moderator", "admin"],
+            "description": "Moderators can edit and delete any question or answer. Admins can also manage accounts",
+        },
+        "AccountInfo": {
+            "type": "object",
+            "required": ["id", "email", "role"],
+            "properties": {
+                "id": integer,
+                "email": string,
+                "role": schema_ref("Role"),
+                "verified_at": { "allOf": [timestamp], "nullable": true },
+            },
+        },
+        "RoleUpdate": {
+            "type": "object",
+            "required": ["role"],
+            "properties": { "role": schema_ref("Role") },
+        },
+        "TokenPair": {
+            "type": "object",
+            "required": ["access_token", "refresh_token", "expires_in"],
+            "properties": {
+                "access_token": string,
+                "refresh_token": {
+                    "type": "string",
+                    "description": "Single use. Reusing a rotated token revokes the session",
+                },
+                "expires_in": { "type": "integer", "description": "Access token lifetime in seconds" },
+            },
+        },
+        "DataExport": {
+            "type": "object",
+            "required": ["id", "status", "created_on"],
+            "properties": {
+                "id": integer,
+                "status": { "type": "string", "enum": ["pending", "ready", "failed", "expired"] },
+                "created_on": timestamp,
+                "expires_at": { "allOf": [timestamp], "nullable": true },
+            },
+        },
+        "ExportedVote": {
+            "type": "object",
+            "required": ["value", "created_on"],
+            "properties": {
+                "question_id": nullable_integer,
+                "answer_id": nullable_integer,
+                "value": { "type": "integer", "enum": [-1, 1] },
+                "created_on": timestamp,
+            },
+        },
+        "AccountExport": {
+            "type": "object",
+            "properties": {
+                "exported_on": timestamp,
+                "account": schema_ref("AccountInfo"),
+                "questions": { "type": "array", "items": schema_ref("Question") },
+                "answers": { "type": "array", "items": schema_ref("Answer") },
+                "comments": { "type": "array", "items": schema_ref("Comment") },
+                "votes": { "type": "array", "items": schema_ref("ExportedVote") },
+                "revisions": { "type": "array", "items": schema_ref("QuestionRevision") },
+            },
+        },
+        "RefreshRequest": {
+            "type": "object",
+            "required": ["refresh_token"],
+            "properties": { "refresh_token": string },
+        },
+        "FieldError": {
+            "type": "object",
+            "properties": { "field": string, "message": string },
+        },
+        "Problem": {
+            "type": "object",
+            "description": "RFC 7807 problem details",
+            "required": ["type", "title", "status", "detail", "code", "request_id"],
+            "properties": {
+                "type": string,
+                "title": string,
+                "status": integer,
+                "detail": string,
+                "code": { "type": "string", "description": "Stable identifier of the error" },
+                "request_id": string,
+                "errors": { "type": "array", "items": schema_ref("FieldError") },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod openapi_tests {
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    use super::{path_params, spec, OPERATIONS};
+    use crate::store::memory::MemoryStore;
+    use crate::types::account::AccountDeletion;
+
+    /// `build_routes`の各ルートに付けた`// GET /questions/:question_id`のコメントを集める
+    fn commented_routes() -> BTreeSet<(String, String)> {
+        include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| {
+                let (method, path) = line.trim().strip_prefix("// ")?.split_once(' ')?;
+                if !path.starts_with('/') || method.chars().any(|c| !c.is_ascii_uppercase()) {
+                    return None;
+                }
+                let path = path.split('?').next().unwrap_or(path);
+                let path = path
+                    .split('/')
+                    .map(|segment| match segment.strip_prefix(':') {
+                        Some(name) => format!("{{{}}}", name),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((method.to_lowercase(), path))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spec_covers_every_route() {
+        let documented: BTreeSet<(String, String)> = OPERATIONS
+            .iter()
+            .map(|operation| (operation.method.to_string(), operation.path.to_string()))
+            .collect();
+
+        assert_eq!(documented, commented_routes());
+    }
+
+    #[test]
+    fn every_schema_reference_resolves() {
+        let spec = spec();
+        let text = spec.to_string();
+        for reference in text.split("\"$ref\":\"").skip(1) {
+            let reference = reference.split('"').next().unwrap();
+            let pointer = reference.trim_start_matches('#');
+            assert!(spec.pointer(pointer).is_some(), "{} is missing", reference);
+        }
+    }
+
+    #[tokio::test]
+    async fn every_documented_route_is_served() {
+        let routes = crate::build_routes(
+            Arc::new(MemoryStore::new()),
+            Arc::new(mock_server::CaptureMailer::new()),
+            AccountDeletion::default(),
+            crate::ratelimit::RateLimiter::default(),
+        )
+        .await;
+
+        for operation in OPERATIONS {
+            let mut path = format!("/v1{}", operation.path);
+            for name in path_params(operation.path) {
+                let value = if name == "name" { "rust" } else { "1" };
+                path = path.replace(&format!("{{{}}}", name), value);
+            }
+
+            // INFO: 認証や本文のエラーになっても、ルートが見つからないエラーでなければよい
+            let res = warp::test::request()
+                .method(&operation.method.to_uppercase())
+                .path(&path)
+                .reply(&routes)
+                .await;
+            if res.headers()["content-type"] == "application/problem+json" {
+                let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+                assert_ne!(
+                    problem["code"], "route_not_found",
+                    "{} {} is not routed",
+                    operation.method, operation.path
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::prelude::*;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{event, Level};
+
+use crate::store::DynStore;
+
+/// 削除済みの質問を確認する間隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 削除されてから`retention_days`日を過ぎた質問と期限を過ぎた個人データの書き出しを、
+/// 定期的に完全に削除するタスクを起動する
+pub fn spawn(store: DynStore, retention_days: u32) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let before = Utc::now().naive_utc() - chrono::Duration::days(retention_days.into());
+            match store.purge_deleted_questions(before).await {
+                Ok(purged) => event!(Level::INFO, purged, "purged deleted questions"),
+                // INFO: 失敗しても次の確認で再試行する
+                Err(e) => event!(Level::WARN, "could not purge deleted questions: {}", e),
+            }
+
+            match store
+                .purge_expired_data_exports(Utc::now().naive_utc())
+                .await
+            {
+                Ok(purged) => event!(Level::INFO, purged, "purged expired data exports"),
+                Err(e) => event!(Level::WARN, "could not purge expired data exports: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use handle_errors::Error;
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use super::{take_token, RateLimit, RateLimitStore, Taken};
+
+/// 保持するバケットの数の上限。超える場合は最後に使われたのが最も古いバケットから取り除く
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    /// `(updated, key)`の順に並べ、最も古いバケットを走査せずに取り除けるようにする
+    by_updated: BTreeSet<(Instant, String)>,
+}
+
+/// プロセスのメモリにバケットを持つストア
+///
+/// INFO: 複数のプロセスで動かす場合は上限がプロセス数倍になる。共有するには`RedisRateLimitStore`を使う。
+/// 上限に達したら使われていないバケットから取り除くので、大量のクライアントから呼ばれると回復途中のバケットも満杯に戻る
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<Buckets>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        MemoryRateLimitStore::default()
+    }
+
+    fn take_at(&self, key: &str, limit: RateLimit, now: Instant) -> Taken {
+        let mut buckets = self.buckets.lock();
+        let Buckets { by_key, by_updated } = &mut *buckets;
+
+        if !by_key.contains_key(key) && by_key.len() >= MAX_ENTRIES {
+            if let Some((_, oldest)) = by_updated.pop_first() {
+                by_key.remove(&oldest);
+            }
+        }
+
+        let taken = match by_key.get(key) {
+            Some(bucket) => {
+                by_updated.remove(&(bucket.updated, key.to_string()));
+                take_token(
+                    bucket.tokens,
+                    now.saturating_duration_since(bucket.updated),
+                    limit,
+                )
+            }
+            None => take_token(f64::from(limit.capacity), Duration::ZERO, limit),
+        };
+        by_key.insert(
+            key.to_string(),
+            Bucket {
+                tokens: taken.tokens,
+                updated: now,
+            },
+        );
+        by_updated.insert((now, key.to_string()));
+
+        taken
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn take(&self, key: &str, limit: RateLimit) -> Result<Taken, Error> {
+        Ok(self.take_at(key, limit, Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use super::*;
+
+    #[test]
+    fn the_least_recently_used_buckets_are_evicted_beyond_max_entries() {
+        let store = MemoryRateLimitStore::new();
+        let limit = RateLimit {
+            capacity: 1,
+            period: Duration::from_secs(60 * 60),
+        };
+        let start = Instant::now();
+
+        // 全てのバケットは回復途中で、満杯に戻ったものはない
+        for i in 0..MAX_ENTRIES + 10 {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(store.take_at(&format!("ip:{}", i), limit, now).allowed);
+        }
+        let buckets = store.buckets.lock();
+        assert_eq!(buckets.by_key.len(), MAX_ENTRIES);
+        assert_eq!(buckets.by_updated.len(), MAX_ENTRIES);
+        assert!(!buckets.by_key.contains_key("ip:9"));
+        assert!(buckets.by_key.contains_key("ip:10"));
+        drop(buckets);
+
+        // 使ったバケットは最後に取り除かれる順に回る
+        let later = start + Duration::from_secs(1);
+        assert!(!store.take_at("ip:10", limit, later).allowed);
+        store.take_at("new", limit, later);
+        let buckets = store.buckets.lock();
+        assert_eq!(buckets.by_key.len(), MAX_ENTRIES);
+        assert!(buckets.by_key.contains_key("ip:10"));
+        assert!(!buckets.by_key.contains_key("ip:11"));
+    }
+}
@@ -0,0 +1,324 @@
+use async_trait::async_trait;
+use handle_errors::Error;
+use std::fmt::Debug;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+use crate::routes::authentication::verify_token;
+
+pub mod memory;
+pub mod redis;
+
+/// トークンバケットの設定。`capacity`回まで続けて呼べ、`period`で空から満杯まで回復する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// 1ミリ秒あたりに補充するトークンの数
+    fn refill_per_ms(&self) -> f64 {
+        f64::from(self.capacity) / self.period.as_millis().max(1) as f64
+    }
+
+    /// `tokens`から`target`個まで回復するのにかかる秒数。端数は切り上げる
+    fn secs_until(&self, tokens: f64, target: f64) -> u64 {
+        let ms = ((target - tokens).max(0.0) / self.refill_per_ms()).ceil();
+        (ms / 1000.0).ceil() as u64
+    }
+}
+
+/// `120/min`のように回数と期間の単位(`s`、`min`、`h`)で指定する
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected `<count>/<s|min|h>`, found `{}`", value);
+        let (capacity, unit) = value.split_once('/').ok_or_else(invalid)?;
+        let capacity = capacity
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|capacity| *capacity > 0)
+            .ok_or_else(invalid)?;
+        let period = match unit.trim() {
+            "s" => Duration::from_secs(1),
+            "min" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            _ => return Err(invalid()),
+        };
+
+        Ok(RateLimit { capacity, period })
+    }
+}
+
+/// ルートの種類ごとのレート制限。`build_routes`で各ルートに割り当てる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// 全てのルートを合わせた、クライアントごとの上限
+    pub global: RateLimit,
+    /// 読み取りのルート
+    pub read: RateLimit,
+    /// 認証したアカウントによる書き込みのルート
+    pub write: RateLimit,
+    /// ログインや登録、メールを送るルート
+    pub auth: RateLimit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            global: RateLimit {
+                capacity: 600,
+                period: Duration::from_secs(60),
+            },
+            read: RateLimit {
+                capacity: 120,
+                period: Duration::from_secs(60),
+            },
+            write: RateLimit {
+                capacity: 30,
+                period: Duration::from_secs(60),
+            },
+            auth: RateLimit {
+                capacity: 10,
+                period: Duration::from_secs(60),
+            },
+        }
+    }
+}
+
+/// バケットからトークンを取り出した結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Taken {
+    pub allowed: bool,
+    /// 取り出した後に残っているトークン。回復途中の端数を含む
+    pub tokens: f64,
+}
+
+/// 経過時間の分だけ補充してから、トークンを1つ取り出す。足りなければ取り出さずに`allowed: false`を返す。
+/// 全てのバックエンドで共通の計算
+pub fn take_token(tokens: f64, elapsed: Duration, limit: RateLimit) -> Taken {
+    let capacity = f64::from(limit.capacity);
+    let tokens = (tokens + elapsed.as_millis() as f64 * limit.refill_per_ms()).min(capacity);
+
+    if tokens >= 1.0 {
+        Taken {
+            allowed: true,
+            tokens: tokens - 1.0,
+        }
+    } else {
+        Taken {
+            allowed: false,
+            tokens,
+        }
+    }
+}
+
+/// バケットを保存する先を差し替えるためのトレイト
+#[async_trait]
+pub trait RateLimitStore: Debug + Send + Sync {
+    /// `key`のバケットからトークンを1つ取り出す。バケットがなければ満杯のバケットを作る
+    async fn take(&self, key: &str, limit: RateLimit) -> Result<Taken, Error>;
+}
+
+pub type DynRateLimitStore = Arc<dyn RateLimitStore>;
+
+/// 通過したリクエストの応答に付ける`X-RateLimit-*`ヘッダーの値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// バケットが満杯に戻るまでの秒数
+    pub reset: u64,
+}
+
+/// ルートごと、クライアントごとにトークンバケットでリクエストを制限する
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    store: DynRateLimitStore,
+    pub limits: RateLimits,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(
+            Arc::new(memory::MemoryRateLimitStore::new()),
+            RateLimits::default(),
+        )
+    }
+}
+
+impl RateLimiter {
+    pub fn new(store: DynRateLimitStore, limits: RateLimits) -> Self {
+        RateLimiter { store, limits }
+    }
+
+    /// クライアントのIPアドレスごとに制限する。匿名のルートに使う
+    pub fn by_address(
+        &self,
+        route: &'static str,
+        limit: RateLimit,
+    ) -> BoxedFilter<(Option<RateLimitStatus>,)> {
+        let client = warp::addr::remote().map(|addr: Option<SocketAddr>| match addr {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        });
+        self.limit(route, limit, client.boxed())
+    }
+
+    /// 全てのルートを合わせて、クライアントのIPアドレスごとに制限する。`build_routes`でルート全体に1度だけかける
+    ///
+    /// INFO: 応答の`X-RateLimit-*`ヘッダーはルートごとの制限の値にするので、通過した場合の状態は捨てる
+    pub fn global(&self) -> BoxedFilter<()> {
+        self.by_address("global", self.limits.global)
+            .map(|_| ())
+            .untuple_one()
+            .boxed()
+    }
+
+    /// `Session.account_id`ごとに制限する。認証が必要なルートに使う
+    ///
+    /// INFO: `auth`で確かめ済みのトークンを復号し直してアカウントを得る。セッションの失効はここでは確かめない
+    pub fn by_account(
+        &self,
+        route: &'static str,
+        limit: RateLimit,
+    ) -> BoxedFilter<(Option<RateLimitStatus>,)> {
+        let client = warp::header::<String>("Authorization").and_then(|token: String| async move {
+            match verify_token(token) {
+                Ok(session) => Ok(format!("account:{}", session.account_id.0)),
+                Err(e) => Err(warp::reject::custom(e)),
+            }
+        });
+        self.limit(route, limit, client.boxed())
+    }
+
+    fn limit(
+        &self,
+        route: &'static str,
+        limit: RateLimit,
+        client: BoxedFilter<(String,)>,
+    ) -> BoxedFilter<(Option<RateLimitStatus>,)> {
+        let store = self.store.clone();
+        client
+            .and_then(move |client: String| take(store.clone(), route, client, limit))
+            .boxed()
+    }
+}
+
+async fn take(
+    store: DynRateLimitStore,
+    route: &'static str,
+    client: String,
+    limit: RateLimit,
+) -> Result<Option<RateLimitStatus>, warp::Rejection> {
+    let key = format!("{}:{}", route, client);
+    let capacity = f64::from(limit.capacity);
+    match store.take(&key, limit).await {
+        Ok(taken) if taken.allowed => Ok(Some(RateLimitStatus {
+            limit: limit.capacity,
+            remaining: taken.tokens.floor() as u32,
+            reset: limit.secs_until(taken.tokens, capacity),
+        })),
+        Ok(taken) => {
+            tracing::event!(tracing::Level::WARN, route, client = %client, "rate limit exceeded");
+            Err(warp::reject::custom(Error::RateLimited {
+                limit: limit.capacity,
+                reset: limit.secs_until(taken.tokens, capacity),
+                retry_after: limit.secs_until(taken.tokens, 1.0),
+            }))
+        }
+        // INFO: 保存先の障害でサービス全体を止めないよう、制限せずに通す
+        Err(e) => {
+            tracing::event!(tracing::Level::ERROR, "{}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// `map`でハンドラを呼んで受け取ったFutureを、制限を通過してから実行する。
+/// 応答には`X-RateLimit-*`ヘッダーを付ける
+///
+/// INFO: ハンドラの引数を変えずにパスの一致と認証の後で制限するため、ルートは
+/// `.map(handler).and(limiter.by_address(..)).and_then(ratelimit::apply)`の順に組み立てる。
+/// async fnは呼んだだけでは実行されないので、制限を超えた場合はハンドラの処理は行われない
+pub async fn apply<F, R>(
+    handler: F,
+    status: Option<RateLimitStatus>,
+) -> Result<WithRateLimit<R>, warp::Rejection>
+where
+    F: Future<Output = Result<R, warp::Rejection>>,
+    R: Reply,
+{
+    let reply = handler.await?;
+    Ok(WithRateLimit { reply, status })
+}
+
+/// `X-RateLimit-*`ヘッダーを付けた応答
+pub struct WithRateLimit<R> {
+    reply: R,
+    status: Option<RateLimitStatus>,
+}
+
+impl<R: Reply> Reply for WithRateLimit<R> {
+    fn into_response(self) -> Response {
+        let mut res = self.reply.into_response();
+        if let Some(status) = self.status {
+            for (name, value) in
+                handle_errors::rate_limit_headers(status.limit, status.remaining, status.reset)
+            {
+                res.headers_mut().insert(name, value);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod ratelimit_tests {
+    use super::*;
+
+    #[test]
+    fn rate_limits_are_parsed_from_count_and_unit() {
+        assert_eq!(
+            "10/min".parse::<RateLimit>(),
+            Ok(RateLimit {
+                capacity: 10,
+                period: Duration::from_secs(60),
+            })
+        );
+        assert!("0/min".parse::<RateLimit>().is_err());
+        assert!("10/day".parse::<RateLimit>().is_err());
+        assert!("10".parse::<RateLimit>().is_err());
+    }
+
+    #[test]
+    fn buckets_refill_over_the_period() {
+        let limit = RateLimit {
+            capacity: 2,
+            period: Duration::from_secs(2),
+        };
+
+        let taken = take_token(2.0, Duration::ZERO, limit);
+        assert!(taken.allowed);
+        let taken = take_token(taken.tokens, Duration::ZERO, limit);
+        assert!(taken.allowed);
+        let taken = take_token(taken.tokens, Duration::from_millis(500), limit);
+        assert!(!taken.allowed);
+        assert_eq!(limit.secs_until(taken.tokens, 1.0), 1);
+        assert_eq!(limit.secs_until(taken.tokens, 2.0), 2);
+
+        // 満杯より多くは貯まらない
+        let taken = take_token(taken.tokens, Duration::from_secs(60), limit);
+        assert!(taken.allowed);
+        assert_eq!(taken.tokens, 1.0);
+    }
+}
@@ -0,0 +1,320 @@
+use async_trait::async_trait;
+use handle_errors::Error;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use super::{RateLimit, RateLimitStore, Taken};
+
+/// 応答を待つ時間。超えたら接続を捨て、その回は制限せずに通す
+const TIMEOUT: Duration = Duration::from_secs(1);
+const KEY_PREFIX: &str = "ratelimit:";
+
+/// 補充と取り出しをサーバ側で1度に行い、複数のプロセスから同じバケットを同時に更新しても数え漏れないようにする。
+/// 計算は`take_token`と同じ。バケットは満杯に戻る時点で期限切れにして消す
+const TAKE_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local refill = capacity / period
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or capacity
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated) * refill)
+local allowed = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], math.max(1, math.ceil((capacity - tokens) / refill)))
+return {allowed, tostring(tokens)}
+"#;
+
+/// Redis互換のサーバにバケットを保存するストア。複数のプロセスで上限を共有できる
+///
+/// INFO: 認証とTLSには対応していないので、同じネットワーク内のサーバに限る。
+/// 経過時間は各プロセスの時計で測るので、プロセス間の時計のずれはそのまま誤差になる
+#[derive(Debug)]
+pub struct RedisRateLimitStore {
+    /// `host:port`
+    addr: String,
+    /// 使い回す接続。エラーになったら捨て、次の呼び出しで接続し直す
+    conn: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(addr: impl Into<String>) -> Self {
+        RedisRateLimitStore {
+            addr: addr.into(),
+            conn: Mutex::new(None),
+        }
+    }
+
+    async fn command(&self, args: &[&str]) -> Result<Value, Error> {
+        let mut conn = self.conn.lock().await;
+        let result = match tokio::time::timeout(TIMEOUT, send(&mut conn, &self.addr, args)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+        };
+        if result.is_err() {
+            *conn = None;
+        }
+
+        result.map_err(|e| Error::RateLimitStoreError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn take(&self, key: &str, limit: RateLimit) -> Result<Taken, Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .to_string();
+        let key = format!("{}{}", KEY_PREFIX, key);
+        let capacity = limit.capacity.to_string();
+        let period = limit.period.as_millis().max(1).to_string();
+
+        let reply = self
+            .command(&["EVAL", TAKE_SCRIPT, "1", &key, &capacity, &period, &now])
+            .await?;
+        match reply {
+            Value::Array(values) => match values.as_slice() {
+                [Value::Integer(allowed), Value::Bulk(tokens)] => {
+                    let tokens = std::str::from_utf8(tokens)
+                        .ok()
+                        .and_then(|tokens| tokens.parse::<f64>().ok());
+                    match tokens {
+                        Some(tokens) => Ok(Taken {
+                            allowed: *allowed == 1,
+                            tokens,
+                        }),
+                        None => Err(Error::RateLimitStoreError(
+                            "invalid token count".to_string(),
+                        )),
+                    }
+                }
+                _ => Err(Error::RateLimitStoreError(format!(
+                    "unexpected reply: {:?}",
+                    values
+                ))),
+            },
+            other => Err(Error::RateLimitStoreError(format!(
+                "unexpected reply: {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// 必要なら接続してからコマンドを送り、応答を読む
+async fn send(
+    conn: &mut Option<BufReader<TcpStream>>,
+    addr: &str,
+    args: &[&str],
+) -> io::Result<Value> {
+    let stream = match conn {
+        Some(stream) => stream,
+        None => conn.insert(BufReader::new(TcpStream::connect(addr).await?)),
+    };
+    stream.get_mut().write_all(&encode(args)).await?;
+    read_value(stream).await
+}
+
+/// RESPの値。使わない型(Null等)は扱わない
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Simple(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Nil,
+    Array(Vec<Value>),
+}
+
+/// コマンドをバルク文字列の配列として書き出す
+fn encode(args: &[&str]) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf
+}
+
+/// 値を1つ読む。エラーの応答(`-ERR ...`)は`Err`にする
+fn read_value<R>(stream: &mut R) -> Pin<Box<dyn Future<Output = io::Result<Value>> + Send + '_>>
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    // INFO: 配列の要素を読むために再帰するので、Futureの大きさを決めるためにBoxに入れる
+    Box::pin(async move {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end_matches("\r\n");
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid reply: {}", line),
+            )
+        };
+        let kind = line.chars().next().ok_or_else(invalid)?;
+        let rest = &line[kind.len_utf8()..];
+
+        match kind {
+            '+' => Ok(Value::Simple(rest.to_string())),
+            '-' => Err(io::Error::other(rest.to_string())),
+            ':' => rest.parse().map(Value::Integer).map_err(|_| invalid()),
+            '$' => {
+                let len: i64 = rest.parse().map_err(|_| invalid())?;
+                if len < 0 {
+                    return Ok(Value::Nil);
+                }
+                let mut buf = vec![0; len as usize + 2];
+                stream.read_exact(&mut buf).await?;
+                buf.truncate(len as usize);
+                Ok(Value::Bulk(buf))
+            }
+            '*' => {
+                let len: i64 = rest.parse().map_err(|_| invalid())?;
+                if len < 0 {
+                    return Ok(Value::Nil);
+                }
+                let mut values = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    values.push(read_value(stream).await?);
+                }
+                Ok(Value::Array(values))
+            }
+            _ => Err(invalid()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod redis_tests {
+    use super::*;
+    use crate::ratelimit::take_token;
+    use parking_lot::Mutex as SyncMutex;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    /// EVALだけに応答する最小限のRedis互換サーバ。
+    /// Luaは実行せず、スクリプトと同じ計算を`take_token`で行う
+    async fn stand_in() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let buckets = Arc::new(SyncMutex::new(HashMap::<String, (f64, u64)>::new()));
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let buckets = buckets.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    while let Ok(Value::Array(args)) = read_value(&mut stream).await {
+                        let args: Vec<String> = args
+                            .into_iter()
+                            .map(|arg| match arg {
+                                Value::Bulk(arg) => String::from_utf8(arg).unwrap(),
+                                other => panic!("unexpected argument: {:?}", other),
+                            })
+                            .collect();
+                        assert_eq!(args[0], "EVAL");
+                        assert_eq!(args[1], TAKE_SCRIPT);
+                        let limit = RateLimit {
+                            capacity: args[4].parse().unwrap(),
+                            period: Duration::from_millis(args[5].parse().unwrap()),
+                        };
+                        let now: u64 = args[6].parse().unwrap();
+
+                        let taken = {
+                            let mut buckets = buckets.lock();
+                            let (tokens, updated) = buckets
+                                .get(&args[3])
+                                .copied()
+                                .unwrap_or((f64::from(limit.capacity), now));
+                            let elapsed = Duration::from_millis(now.saturating_sub(updated));
+                            let taken = take_token(tokens, elapsed, limit);
+                            buckets.insert(args[3].clone(), (taken.tokens, now));
+                            taken
+                        };
+
+                        let tokens = taken.tokens.to_string();
+                        let reply = format!(
+                            "*2\r\n:{}\r\n${}\r\n{}\r\n",
+                            u8::from(taken.allowed),
+                            tokens.len(),
+                            tokens
+                        );
+                        stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn buckets_are_shared_between_processes_through_the_server() {
+        let addr = stand_in().await;
+        let limit = RateLimit {
+            capacity: 2,
+            period: Duration::from_secs(60),
+        };
+
+        // INFO: 別々のプロセスの代わりに、接続を共有しない2つのストアを使う
+        let first = RedisRateLimitStore::new(addr.clone());
+        let second = RedisRateLimitStore::new(addr);
+
+        let taken = first.take("login:ip:192.0.2.1", limit).await.unwrap();
+        assert!(taken.allowed);
+        assert!(
+            second
+                .take("login:ip:192.0.2.1", limit)
+                .await
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !first
+                .take("login:ip:192.0.2.1", limit)
+                .await
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            second
+                .take("login:ip:192.0.2.2", limit)
+                .await
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_servers_are_reported_as_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let limit = RateLimit {
+            capacity: 1,
+            period: Duration::from_secs(1),
+        };
+        match RedisRateLimitStore::new(addr).take("key", limit).await {
+            Err(Error::RateLimitStoreError(_)) => {}
+            other => panic!("expected a store error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::prelude::*;
+use mailer::DynMailer;
+use tracing::instrument;
+use warp::http::StatusCode;
+use warp::Reply;
+
+use crate::export::PENDING_EXPORT_TIMEOUT_MINUTES;
+use crate::routes::authentication::{hash, hash_secret, start_session, verify_password};
+use crate::store::DynStore;
+use crate::types::account::{AccountDeletion, ChangePasswordRequest, Session};
+use crate::types::export::{ExportDownloadQuery, ExportStatus};
+
+/// 現在のパスワードを確かめてからパスワードを変更する。
+/// 他の端末のセッションは全て失効させ、呼び出した端末には新しいトークンを返す
+#[instrument(skip(request))]
+pub async fn change_password(
+    request: ChangePasswordRequest,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+
+    // INFO: トークンにはemailを含めていないので、idからアカウントを引き直す
+    let account = match store.get_account_info(account_id.clone()).await {
+        Ok(info) => match store.get_account(info.email).await {
+            Ok(account) => account,
+            Err(e) => return Err(warp::reject::custom(e)),
+        },
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match verify_password(&account.password, request.current_password.as_bytes()) {
+        Ok(true) => {}
+        Ok(false) => return Err(warp::reject::custom(handle_errors::Error::WrongPassword)),
+        Err(e) => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::ArgonLibraryError(e),
+            ))
+        }
+    }
+
+    let password = hash(request.new_password.as_bytes());
+    if let Err(e) = store
+        .update_account_password(account_id.clone(), password)
+        .await
+    {
+        return Err(warp::reject::custom(e));
+    }
+
+    if let Err(e) = store.revoke_account_sessions(account_id.clone()).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    match start_session(&store, account_id, account.role).await {
+        Ok(tokens) => Ok(warp::reply::json(&tokens)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 呼び出したアカウントを削除する。投稿の扱いは設定の`account_deletion`に従う
+#[instrument]
+pub async fn delete_account(
+    store: DynStore,
+    mode: AccountDeletion,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.delete_account(session.account_id.clone(), mode).await {
+        Ok(_) => {
+            tracing::event!(
+                tracing::Level::INFO,
+                account_id = session.account_id.0,
+                mode = ?mode,
+                "account deleted"
+            );
+            Ok(warp::reply::with_status("Account deleted", StatusCode::OK))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// アカウントのデータの書き出しを依頼する。作成はバックグラウンドで行い、完成したらリンクをメールで送る
+#[instrument]
+pub async fn request_export(
+    store: DynStore,
+    mailer: DynMailer,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+
+    // INFO: 作成中の書き出しがあれば、同じものを作らずにそれを返す
+    let timeout =
+        (Utc::now() - chrono::Duration::minutes(PENDING_EXPORT_TIMEOUT_MINUTES)).naive_utc();
+    match store.get_latest_data_export(account_id.clone()).await {
+        Ok(export) if export.status == ExportStatus::Pending && export.created_on > timeout => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&export),
+                StatusCode::ACCEPTED,
+            ))
+        }
+        Ok(_) | Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {}
+        Err(e) => return Err(warp::reject::custom(e)),
+    }
+
+    let account = match store.get_account_info(account_id.clone()).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let export = match store.add_data_export(account_id).await {
+        Ok(export) => export,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    crate::export::spawn(store, mailer, export.id, account);
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&export),
+        StatusCode::ACCEPTED,
+    ))
+}
+
+/// 最も新しい書き出しが完成していれば、書き出したJSONをファイルとして返す。
+/// 完成していなければ(作成中、失敗、期限切れ)、`202 Accepted`で状態を返す
+#[instrument]
+pub async fn get_export(
+    store: DynStore,
+    session: Session,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let account_id = session.account_id;
+    match store
+        .get_latest_data_export_archive(account_id.clone())
+        .await
+    {
+        Ok(Some(archive)) => return Ok(archive_reply(archive).into_response()),
+        Ok(None) => {}
+        Err(e) => return Err(warp::reject::custom(e)),
+    }
+
+    match store.get_latest_data_export(account_id).await {
+        Ok(export) => Ok(warp::reply::with_status(
+            warp::reply::json(&export),
+            StatusCode::ACCEPTED,
+        )
+        .into_response()),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// メールのリンクから呼ばれ、書き出したJSONをファイルとして返す。
+/// リンクを開くだけでダウンロードできるよう、認証の代わりにトークンを確かめる
+#[instrument(skip(query))]
+pub async fn download_export(
+    query: ExportDownloadQuery,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let archive = match store
+        .get_data_export_archive(&hash_secret(&query.token))
+        .await
+    {
+        Ok(Some(archive)) => archive,
+        // INFO: 期限切れと不正なトークンを区別しない
+        Ok(None) => return Err(warp::reject::custom(handle_errors::Error::NotFound)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(archive_reply(archive))
+}
+
+/// 書き出したJSONをダウンロードさせる応答
+fn archive_reply(archive: String) -> impl warp::Reply {
+    let reply = warp::reply::with_header(archive, "Content-Type", "application/json");
+    warp::reply::with_header(
+        reply,
+        "Content-Disposition",
+        "attachment; filename=\"account-export.json\"",
+    )
+}
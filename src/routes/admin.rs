@@ -0,0 +1,42 @@
+use tracing::instrument;
+
+use crate::routes::authentication::log_privileged_action;
+use crate::store::DynStore;
+use crate::types::account::{AccountId, AccountInfo, RoleUpdate, Session};
+
+/// INFO: 管理者のロールはルート側の`require_role`で確認済み
+#[instrument]
+pub async fn get_accounts(
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    log_privileged_action(&session, "get_accounts", None);
+
+    let res: Vec<AccountInfo> = match store.get_accounts().await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+/// アカウントのロールを変更する。発行済みのアクセストークンには、次のリフレッシュから反映される
+#[instrument]
+pub async fn update_account_role(
+    id: i32,
+    update: RoleUpdate,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // 管理者が1人もいなくなることを避けるため、自分自身のロールは変更できない
+    if session.account_id == AccountId(id) {
+        return Err(warp::reject::custom(handle_errors::Error::Forbidden));
+    }
+
+    log_privileged_action(&session, "update_account_role", Some(id));
+
+    match store.update_account_role(AccountId(id), update.role).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
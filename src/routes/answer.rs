@@ -2,30 +2,165 @@ use tracing::instrument;
 use warp::http::StatusCode;
 
 use crate::profanity::check_profanity;
-use crate::store::Store;
-use crate::types::account::Session;
-use crate::types::answer::NewAnswer;
+use crate::routes::authentication::log_privileged_action;
+use crate::store::DynStore;
+use crate::types::account::{AccountId, Role, Session};
+use crate::types::answer::{Answer, AnswerPatch, NewAnswer};
+
+/// 回答を取得する。存在しない回答は所有者の確認より先に404として返す
+async fn find_answer(store: &DynStore, id: i32) -> Result<Answer, handle_errors::Error> {
+    match store.get_answer(id).await {
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+            Err(handle_errors::Error::NotFound)
+        }
+        res => res,
+    }
+}
+
+/// 回答を編集・削除できるかを確かめ、ストアに渡す所有者のidを返す。
+/// モデレーター以上は他人の回答も操作でき、その操作は記録される
+async fn answer_owner_for(
+    store: &DynStore,
+    id: i32,
+    session: &Session,
+    action: &str,
+) -> Result<AccountId, handle_errors::Error> {
+    if store.is_answer_owner(id, &session.account_id).await? {
+        return Ok(session.account_id.clone());
+    }
+
+    if session.role < Role::Moderator {
+        return Err(handle_errors::Error::Unauthorized);
+    }
+
+    let owner = store.get_answer_owner(id).await?;
+    log_privileged_action(session, action, Some(id));
+    Ok(owner)
+}
+
+/// 本文が現在の値から変わっていれば検査して更新する。変わっていなければ現在の回答を返す
+async fn edit_answer(
+    store: &DynStore,
+    id: i32,
+    patch: AnswerPatch,
+    session: &Session,
+    action: &str,
+) -> Result<Answer, handle_errors::Error> {
+    let current = find_answer(store, id).await?;
+    let account_id = answer_owner_for(store, id, session, action).await?;
+
+    match patch.content.filter(|content| *content != current.content) {
+        Some(content) => {
+            let content = check_profanity(content).await?;
+            store
+                .update_answer(id, NewAnswer { content }, account_id)
+                .await
+        }
+        None => Ok(current),
+    }
+}
+
+#[instrument]
+pub async fn get_answers(
+    question_id: i32,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // 存在しない質問に対しては空の配列ではなくエラーを返す
+    if let Err(e) = store.get_question(question_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let res: Vec<Answer> = match store.get_answers(question_id).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+#[instrument]
+pub async fn get_answer(id: i32, store: DynStore) -> Result<impl warp::Reply, warp::Rejection> {
+    let res: Answer = match store.get_answer(id).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
 
 #[instrument]
 pub async fn add_answer(
-    store: Store,
+    question_id: i32,
     new_answer: NewAnswer,
+    store: DynStore,
     session: Session,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let account_id = session.account_id;
 
+    if let Err(e) = store.get_question(question_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
     let content = match check_profanity(new_answer.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    let answer = NewAnswer {
-        content,
-        question_id: new_answer.question_id,
+    let answer = NewAnswer { content };
+
+    match store.add_answer(question_id, answer, account_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn update_answer(
+    id: i32,
+    new_answer: NewAnswer,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let patch = AnswerPatch {
+        content: Some(new_answer.content),
     };
 
-    match store.add_answer(answer, account_id).await {
-        Ok(_) => Ok(warp::reply::with_status("Answer added", StatusCode::OK)),
+    match edit_answer(&store, id, patch, &session, "update_answer").await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn patch_answer(
+    id: i32,
+    patch: AnswerPatch,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match edit_answer(&store, id, patch, &session, "patch_answer").await {
+        Ok(res) => Ok(warp::reply::json(&res)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+#[instrument]
+pub async fn delete_answer(
+    id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(e) = find_answer(&store, id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let account_id = answer_owner_for(&store, id, &session, "delete_answer").await?;
+    if let Err(e) = store.delete_answer(id, account_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(warp::reply::with_status(
+        format!("Answer {} deleted", id),
+        StatusCode::OK,
+    ))
+}
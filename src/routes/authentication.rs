@@ -1,46 +1,208 @@
 use argon2::Config;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::prelude::*;
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use tracing::instrument;
 use warp::http::StatusCode;
 use warp::Filter;
 
-use crate::store::Store;
-use crate::types::account::{Account, AccountId, Session};
+use crate::lockout::LoginAttempts;
+use crate::routes::email::send_verification;
+use crate::store::DynStore;
+use crate::types::account::{
+    is_valid_email, Account, AccountId, RefreshRequest, Role, Session, TokenPair,
+};
+use mailer::DynMailer;
+
+/// アクセストークンの有効期間。失効の確認は毎回行うが、漏洩時の被害を抑えるため短くする
+const ACCESS_TOKEN_LIFETIME_MINUTES: i64 = 15;
+/// リフレッシュトークンの有効期間。リフレッシュのたびに延長される
+const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+
+/// アカウントを登録し、確認メールを送る。確認するまでは質問・回答を投稿できない
+pub async fn register(
+    store: DynStore,
+    mailer: DynMailer,
+    account: Account,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !is_valid_email(&account.email) {
+        return Err(warp::reject::custom(
+            handle_errors::Error::InvalidParameter("email".to_string()),
+        ));
+    }
 
-pub async fn register(store: Store, account: Account) -> Result<impl warp::Reply, warp::Rejection> {
     let hashed_password = hash(account.password.as_bytes());
 
     let account = Account {
         id: account.id,
         email: account.email,
         password: hashed_password,
+        role: Role::User,
+        verified_at: None,
     };
+    let email = account.email.clone();
 
-    match store.add_account(account).await {
-        Ok(_) => Ok(warp::reply::with_status("Account added", StatusCode::OK)),
-        Err(e) => Err(warp::reject::custom(e)),
+    if let Err(e) = store.add_account(account).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    // INFO: 確認メールを送れなくても登録は取り消さない。`POST /email/verification`で送り直せる
+    let sent = match store.get_account(email.clone()).await {
+        Ok(account) => {
+            send_verification(&store, &mailer, account.id.expect("id not found"), email).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = sent {
+        tracing::event!(tracing::Level::ERROR, "{}", e);
     }
+
+    Ok(warp::reply::with_status("Account added", StatusCode::OK))
 }
 
-pub async fn login(store: Store, login: Account) -> Result<impl warp::Reply, warp::Rejection> {
+/// 失敗が続いたアカウントやIPアドレスからのログインは、`LoginAttempts`によって一定時間拒否する
+pub async fn login(
+    store: DynStore,
+    attempts: Arc<LoginAttempts>,
+    remote: Option<SocketAddr>,
+    login: Account,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let addr = remote.map(|remote| remote.ip());
+    if let Err(e) = attempts.check(&login.email, addr) {
+        return Err(warp::reject::custom(e));
+    }
+
     // データベースにユーザが存在するかチェック
-    match store.get_account(login.email).await {
-        // パスワードが正しいかチェック
-        Ok(account) => match verify_password(&account.password, login.password.as_bytes()) {
-            Ok(verified) => {
-                if verified {
-                    Ok(warp::reply::json(&issue_token(
-                        account.id.expect("id not found"),
-                    )))
-                } else {
-                    Err(warp::reject::custom(handle_errors::Error::WrongPassword))
-                }
-            }
-            Err(e) => Err(warp::reject::custom(
+    let account = match store.get_account(login.email.clone()).await {
+        Ok(account) => Some(account),
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => None,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    // パスワードが正しいかチェック
+    // INFO: 応答時間からアカウントの有無が分からないよう、存在しない場合もダミーのハッシュで同じ検証を行い、
+    // パスワードの誤りと同じエラーを返す
+    let hash = match &account {
+        Some(account) => account.password.as_str(),
+        None => dummy_hash(),
+    };
+    let verified = match verify_password(hash, login.password.as_bytes()) {
+        Ok(verified) => verified,
+        Err(e) => {
+            return Err(warp::reject::custom(
                 handle_errors::Error::ArgonLibraryError(e),
-            )),
-        },
+            ))
+        }
+    };
+
+    let account = match account {
+        Some(account) if verified => account,
+        _ => {
+            attempts.record_failure(&login.email, addr);
+            return Err(warp::reject::custom(handle_errors::Error::WrongPassword));
+        }
+    };
+    attempts.record_success(&login.email);
+
+    let account_id = account.id.expect("id not found");
+    match start_session(&store, account_id, account.role).await {
+        Ok(tokens) => Ok(warp::reply::json(&tokens)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// リフレッシュトークンを新しいものに置き換え、アクセストークンと一緒に返す
+#[instrument(skip(request))]
+pub async fn refresh(
+    store: DynStore,
+    request: RefreshRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (session_id, secret) = match parse_refresh_token(&request.refresh_token) {
+        Some(parts) => parts,
+        None => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::InvalidRefreshToken,
+            ))
+        }
+    };
+
+    let session = match store.get_login_session(session_id).await {
+        Ok(session) => session,
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::InvalidRefreshToken,
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    if session.revoked_at.is_some() || session.expires_at <= Utc::now().naive_utc() {
+        return Err(warp::reject::custom(
+            handle_errors::Error::InvalidRefreshToken,
+        ));
+    }
+
+    // INFO: 置き換え済みの古いトークンが使われた場合は盗まれた可能性があるので、
+    // 正規の利用者のトークンも含めてセッションごと失効させる
+    if hash_secret(secret) != session.refresh_token_hash {
+        tracing::event!(
+            tracing::Level::WARN,
+            session_id,
+            "refresh token reuse detected, revoking session"
+        );
+        if let Err(e) = store.revoke_login_session(session_id).await {
+            return Err(warp::reject::custom(e));
+        }
+        return Err(warp::reject::custom(
+            handle_errors::Error::InvalidRefreshToken,
+        ));
+    }
+
+    // INFO: ロールの変更はここで反映されるので、降格されたアカウントも最長でアクセストークンの有効期間で権限を失う
+    let account = match store.get_account_info(session.account_id.clone()).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let (refresh_token, new_hash) = generate_refresh_token(session_id);
+    match store
+        .rotate_refresh_token(
+            session_id,
+            &session.refresh_token_hash,
+            new_hash,
+            refresh_token_expiry(),
+        )
+        .await
+    {
+        Ok(true) => {}
+        // 同じトークンによる別のリフレッシュが先に置き換えた
+        Ok(false) => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::InvalidRefreshToken,
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    }
+
+    Ok(warp::reply::json(&TokenPair {
+        access_token: issue_token(account.id, session_id, account.role),
+        refresh_token,
+        expires_in: ACCESS_TOKEN_LIFETIME_MINUTES * 60,
+    }))
+}
+
+/// 現在のログインセッションを失効させる。発行済みのアクセストークンとリフレッシュトークンは共に使えなくなる
+#[instrument]
+pub async fn logout(
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.revoke_login_session(session.session_id).await {
+        Ok(_) => Ok(warp::reply::with_status("Logged out", StatusCode::OK)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
@@ -59,33 +221,145 @@ pub fn verify_token(token: String) -> Result<Session, handle_errors::Error> {
 }
 
 /// 戻り値の型はwarp::Filter::andメソッドに併せてセット
-pub fn auth() -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+pub fn auth(store: DynStore) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
     // warp::Filter::and_thenメソッドにセットする関数は非同期である必要があるのでasyncを付与
     // @ref https://docs.rs/warp/0.3.1/warp/trait.Filter.html#method.and_then
-    warp::header::<String>("Authorization").and_then(|token: String| async move {
-        let token = match verify_token(token) {
-            Ok(t) => t,
-            Err(_) => return Err(warp::reject::reject()),
-        };
+    warp::header::<String>("Authorization")
+        .and(warp::any().map(move || store.clone()))
+        .and_then(|token: String, store: DynStore| async move {
+            let token = match verify_token(token) {
+                Ok(t) => t,
+                Err(e) => return Err(warp::reject::custom(e)),
+            };
 
-        Ok(token)
+            // INFO: 有効期限内のトークンでも、ログアウト等で失効したセッションのものは受け付けない
+            match store.get_login_session(token.session_id).await {
+                Ok(session) if session.revoked_at.is_none() => Ok(token),
+                Ok(_) | Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+                    Err(warp::reject::custom(handle_errors::Error::SessionRevoked))
+                }
+                Err(e) => Err(warp::reject::custom(e)),
+            }
+        })
+}
+
+/// `auth`に加えて、`role`以上のロールを持つことを要求する
+pub fn require_role(
+    store: DynStore,
+    role: Role,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store).and_then(move |session: Session| async move {
+        if session.role >= role {
+            Ok(session)
+        } else {
+            Err(warp::reject::custom(handle_errors::Error::Forbidden))
+        }
+    })
+}
+
+/// `auth`に加えて、メールアドレスを確認済みであることを要求する
+///
+/// INFO: 確認した直後から投稿できるよう、トークンではなくストアの値を確かめる
+pub fn require_verified(
+    store: DynStore,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store.clone()).and_then(move |session: Session| {
+        let store = store.clone();
+        async move {
+            match store.get_account_info(session.account_id.clone()).await {
+                Ok(account) if account.verified_at.is_some() => Ok(session),
+                Ok(_) => Err(warp::reject::custom(handle_errors::Error::EmailNotVerified)),
+                Err(e) => Err(warp::reject::custom(e)),
+            }
+        }
     })
 }
 
+/// ロールによって許可された操作を記録する。`target: "audit"`で絞り込める
+pub fn log_privileged_action(session: &Session, action: &str, target_id: Option<i32>) {
+    tracing::event!(
+        target: "audit",
+        tracing::Level::INFO,
+        account_id = session.account_id.0,
+        role = session.role.as_str(),
+        action,
+        target_id = ?target_id,
+        "privileged action"
+    );
+}
+
 pub fn hash(password: &[u8]) -> String {
     let salt = rand::thread_rng().gen::<[u8; 32]>();
     let config = Config::default();
     argon2::hash_encoded(password, &salt, &config).unwrap()
 }
 
-fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
+pub(crate) fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
-fn issue_token(account_id: AccountId) -> String {
-    // 有効期限を1日にセット
+/// 存在しないアカウントのログインで検証に使うハッシュ。実在するアカウントと同じ設定で作る
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash(b"dummy password"))
+}
+
+/// ログインセッションを作成し、最初のアクセストークンとリフレッシュトークンを発行する
+pub(crate) async fn start_session(
+    store: &DynStore,
+    account_id: AccountId,
+    role: Role,
+) -> Result<TokenPair, handle_errors::Error> {
+    // INFO: トークンにセッションidを含めるため、ハッシュは仮の値で作成してから置き換える
+    let session = store
+        .add_login_session(account_id.clone(), String::new(), refresh_token_expiry())
+        .await?;
+
+    let (refresh_token, hash) = generate_refresh_token(session.id);
+    store
+        .rotate_refresh_token(session.id, "", hash, session.expires_at)
+        .await?;
+
+    Ok(TokenPair {
+        access_token: issue_token(account_id, session.id, role),
+        refresh_token,
+        expires_in: ACCESS_TOKEN_LIFETIME_MINUTES * 60,
+    })
+}
+
+fn refresh_token_expiry() -> NaiveDateTime {
+    (Utc::now() + chrono::Duration::days(REFRESH_TOKEN_LIFETIME_DAYS)).naive_utc()
+}
+
+/// `{セッションid}.{ランダムな秘密}`の形式のリフレッシュトークンと、保存用の秘密のハッシュを返す
+fn generate_refresh_token(session_id: i32) -> (String, String) {
+    let secret = random_secret();
+    let hash = hash_secret(&secret);
+    (format!("{}.{}", session_id, secret), hash)
+}
+
+fn parse_refresh_token(token: &str) -> Option<(i32, &str)> {
+    let (id, secret) = token.split_once('.')?;
+    if secret.is_empty() {
+        return None;
+    }
+    Some((id.parse().ok()?, secret))
+}
+
+/// トークンに使う推測できないランダムな文字列(URLに含められる形式)
+pub(crate) fn random_secret() -> String {
+    URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// INFO: 秘密は十分にランダムなので、パスワードと違いargon2ではなくSHA-256で足りる
+pub(crate) fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+fn issue_token(account_id: AccountId, session_id: i32, role: Role) -> String {
+    // 有効期限を15分にセット
     let current_date_time = Utc::now();
-    let dt = current_date_time + chrono::Duration::days(1);
+    let dt = current_date_time + chrono::Duration::minutes(ACCESS_TOKEN_LIFETIME_MINUTES);
 
     // トークン生成時の秘密鍵を.envから取得
     let secret_key = env::var("TOKEN_SECRET_KEY").unwrap();
@@ -95,25 +369,76 @@ fn issue_token(account_id: AccountId) -> String {
         .set_expiration(&dt)
         .set_not_before(&Utc::now())
         .set_claim("account_id", serde_json::json!(account_id))
+        .set_claim("session_id", serde_json::json!(session_id))
+        .set_claim("role", serde_json::json!(role))
         .build()
         .expect("Failed to construct paseto token w/ builder!")
 }
 
 #[cfg(test)]
 mod authentication_tests {
-    use super::{auth, env, issue_token, AccountId};
+    use super::{
+        auth, env, issue_token, parse_refresh_token, refresh_token_expiry, require_role, AccountId,
+        Role,
+    };
+    use crate::store::{memory::MemoryStore, DynStore};
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn post_questions_auth() {
         env::set_var("TOKEN_SECRET_KEY", "7ZcbZPVuSTL4UasiGi3iwrZzWhKZadBY");
-        let token = issue_token(AccountId(3));
+        let store: DynStore = Arc::new(MemoryStore::new());
+        let session = store
+            .add_login_session(AccountId(3), String::new(), refresh_token_expiry())
+            .await
+            .unwrap();
+        let token = issue_token(AccountId(3), session.id, Role::User);
 
-        let filter = auth();
+        let filter = auth(store.clone());
 
         let res = warp::test::request()
-            .header("Authorization", token)
+            .header("Authorization", token.clone())
             .filter(&filter);
 
         assert_eq!(res.await.unwrap().account_id, AccountId(3));
+
+        // 失効したセッションのトークンは有効期限内でも拒否される
+        store.revoke_login_session(session.id).await.unwrap();
+        let res = warp::test::request()
+            .header("Authorization", token)
+            .filter(&filter);
+
+        assert!(res.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn require_role_accepts_the_role_and_above() {
+        env::set_var("TOKEN_SECRET_KEY", "7ZcbZPVuSTL4UasiGi3iwrZzWhKZadBY");
+        let store: DynStore = Arc::new(MemoryStore::new());
+        let session = store
+            .add_login_session(AccountId(3), String::new(), refresh_token_expiry())
+            .await
+            .unwrap();
+        let token = issue_token(AccountId(3), session.id, Role::Moderator);
+
+        for (role, allowed) in [
+            (Role::User, true),
+            (Role::Moderator, true),
+            (Role::Admin, false),
+        ] {
+            let res = warp::test::request()
+                .header("Authorization", token.clone())
+                .filter(&require_role(store.clone(), role))
+                .await;
+            assert_eq!(res.is_ok(), allowed, "{:?}", role);
+        }
+    }
+
+    #[test]
+    fn refresh_tokens_must_have_a_session_id_and_a_secret() {
+        assert_eq!(parse_refresh_token("12.abc"), Some((12, "abc")));
+        assert_eq!(parse_refresh_token("12."), None);
+        assert_eq!(parse_refresh_token("abc"), None);
+        assert_eq!(parse_refresh_token("x.abc"), None);
     }
 }
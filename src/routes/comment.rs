@@ -0,0 +1,106 @@
+use tracing::instrument;
+use warp::http::StatusCode;
+
+use crate::profanity::check_profanity;
+use crate::store::DynStore;
+use crate::types::account::Session;
+use crate::types::comment::{Comment, CommentTarget, NewComment};
+
+/// コメントの対象の投稿が存在するかを確かめる。削除された質問は存在しないものとして扱う
+async fn find_target(store: &DynStore, target: CommentTarget) -> Result<(), handle_errors::Error> {
+    match target {
+        CommentTarget::Question(id) => store.get_question(id).await.map(|_| ()),
+        CommentTarget::Answer(id) => store.get_answer(id).await.map(|_| ()),
+    }
+}
+
+/// INFO: `target`はルート側でパスの質問idまたは回答idから作る
+#[instrument]
+pub async fn get_comments(
+    target: CommentTarget,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // 存在しない投稿に対しては空の配列ではなくエラーを返す
+    if let Err(e) = find_target(&store, target).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let res: Vec<Comment> = match store.get_comments(target).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+#[instrument]
+pub async fn add_comment(
+    target: CommentTarget,
+    new_comment: NewComment,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+
+    if let Err(e) = find_target(&store, target).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let content = match check_profanity(new_comment.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let comment = NewComment { content };
+
+    match store.add_comment(target, comment, account_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn update_comment(
+    id: i32,
+    new_comment: NewComment,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if store.is_comment_owner(id, &account_id).await? {
+        let content = match check_profanity(new_comment.content).await {
+            Ok(res) => res,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+
+        let comment = NewComment { content };
+
+        match store.update_comment(id, comment, account_id).await {
+            Ok(res) => Ok(warp::reply::json(&res)),
+            Err(e) => Err(warp::reject::custom(e)),
+        }
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
+
+#[instrument]
+pub async fn delete_comment(
+    id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if store.is_comment_owner(id, &account_id).await? {
+        if let Err(e) = store.delete_comment(id, account_id).await {
+            return Err(warp::reject::custom(e));
+        }
+
+        Ok(warp::reply::with_status(
+            format!("Comment {} deleted", id),
+            StatusCode::OK,
+        ))
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
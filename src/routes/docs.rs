@@ -0,0 +1,11 @@
+use crate::openapi;
+
+/// ルートの定義から組み立てたOpenAPI 3のドキュメントを返す
+pub async fn get_openapi() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&openapi::spec()))
+}
+
+/// `/openapi.json`を読み込んで表示するAPIリファレンスのページを返す
+pub async fn get_docs() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::html(openapi::REDOC_PAGE))
+}
@@ -0,0 +1,101 @@
+use chrono::prelude::*;
+use mailer::{DynMailer, Mail};
+use std::env;
+use tracing::instrument;
+use warp::http::StatusCode;
+
+use crate::routes::authentication::{hash_secret, random_secret};
+use crate::store::DynStore;
+use crate::types::account::{AccountId, Session, VerifyEmailQuery};
+
+/// 確認用のトークンの有効期間
+const VERIFICATION_TOKEN_LIFETIME_HOURS: i64 = 24;
+
+/// 確認用のリンクを組み立てる際のURL。`PUBLIC_URL`が未設定の場合に使う
+const DEFAULT_PUBLIC_URL: &str = "http://localhost:8080";
+
+/// メールで送るリンクの先頭に付けるURL。末尾の`/`は取り除く
+///
+/// INFO: リクエストのHostヘッダーを使うと、偽のホストへのリンクを送らせることができてしまうので設定値を使う
+pub(crate) fn public_url() -> String {
+    env::var("PUBLIC_URL")
+        .unwrap_or_else(|_| DEFAULT_PUBLIC_URL.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// 確認用のトークンを発行し、リンクをメールで送る
+pub(crate) async fn send_verification(
+    store: &DynStore,
+    mailer: &DynMailer,
+    account_id: AccountId,
+    email: String,
+) -> Result<(), handle_errors::Error> {
+    let token = random_secret();
+    let expires_at =
+        (Utc::now() + chrono::Duration::hours(VERIFICATION_TOKEN_LIFETIME_HOURS)).naive_utc();
+    store
+        .add_email_verification_token(account_id, hash_secret(&token), expires_at)
+        .await?;
+
+    let mail = Mail {
+        to: email,
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Open the following link within {} hours to verify your email address.\n\n\
+            {}/v1/email/verify?token={}\n\n\
+            You can log in before verifying, but cannot post questions or answers.",
+            VERIFICATION_TOKEN_LIFETIME_HOURS,
+            public_url(),
+            token
+        ),
+    };
+
+    mailer
+        .send(mail)
+        .await
+        .map_err(|e| handle_errors::Error::MailError(e.0))
+}
+
+/// メールのリンクから呼ばれ、アカウントを確認済みにする
+#[instrument(skip(query))]
+pub async fn verify_email(
+    query: VerifyEmailQuery,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.verify_email(&hash_secret(&query.token)).await {
+        Ok(Some(_)) => Ok(warp::reply::with_status("Email verified", StatusCode::OK)),
+        Ok(None) => Err(warp::reject::custom(
+            handle_errors::Error::InvalidVerificationToken,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 確認メールを送り直す。以前に送ったリンクも有効期限までは使える
+#[instrument]
+pub async fn resend_verification(
+    store: DynStore,
+    mailer: DynMailer,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_info(session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    if account.verified_at.is_some() {
+        return Ok(warp::reply::with_status(
+            "Email already verified",
+            StatusCode::OK,
+        ));
+    }
+
+    match send_verification(&store, &mailer, account.id, account.email).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            "Verification email sent",
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
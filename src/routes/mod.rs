@@ -1,3 +1,13 @@
+pub mod account;
+pub mod admin;
 pub mod answer;
 pub mod authentication;
+pub mod comment;
+pub mod docs;
+pub mod email;
+pub mod password;
 pub mod question;
+pub mod revision;
+pub mod search;
+pub mod tag;
+pub mod vote;
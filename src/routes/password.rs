@@ -0,0 +1,115 @@
+use chrono::prelude::*;
+use mailer::{DynMailer, Mail};
+use tracing::instrument;
+use warp::http::StatusCode;
+
+use crate::routes::authentication::{hash, hash_secret, random_secret};
+use crate::store::DynStore;
+use crate::types::account::{Account, ForgotPasswordRequest, ResetPasswordRequest};
+
+/// 再設定用のトークンの有効期間
+const RESET_TOKEN_LIFETIME_MINUTES: i64 = 60;
+
+/// 再設定用のトークンをメールで送る。
+/// アカウントの有無を推測されないよう、存在しないemailでも同じレスポンスを返す
+///
+/// INFO: トークンの保存とメールの送信は応答時間の差でアカウントの有無が分からないよう、
+/// レスポンスを返した後にバックグラウンドで行う
+#[instrument]
+pub async fn forgot_password(
+    store: DynStore,
+    mailer: DynMailer,
+    request: ForgotPasswordRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reply = warp::reply::with_status(
+        "If the account exists, a password reset email has been sent",
+        StatusCode::OK,
+    );
+
+    let account = match store.get_account(request.email).await {
+        Ok(account) => account,
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+            return Ok(reply)
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    tokio::spawn(async move {
+        // INFO: 失敗を返すとアカウントが存在することが分かってしまうので、ログに残すだけにする
+        if let Err(e) = send_reset_token(&store, &mailer, account).await {
+            tracing::event!(
+                tracing::Level::ERROR,
+                "could not send password reset email: {}",
+                e
+            );
+        }
+    });
+
+    Ok(reply)
+}
+
+async fn send_reset_token(
+    store: &DynStore,
+    mailer: &DynMailer,
+    account: Account,
+) -> Result<(), handle_errors::Error> {
+    let token = random_secret();
+    let expires_at =
+        (Utc::now() + chrono::Duration::minutes(RESET_TOKEN_LIFETIME_MINUTES)).naive_utc();
+    store
+        .add_password_reset_token(
+            account.id.expect("id not found"),
+            hash_secret(&token),
+            expires_at,
+        )
+        .await?;
+
+    let mail = Mail {
+        to: account.email,
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Send the following token to POST /v1/password/reset with your new password \
+            within {} minutes.\n\n{}\n\nIf you did not request a password reset, ignore this email.",
+            RESET_TOKEN_LIFETIME_MINUTES, token
+        ),
+    };
+    mailer
+        .send(mail)
+        .await
+        .map_err(|e| handle_errors::Error::MailError(e.0))
+}
+
+/// トークンを使用済みにしてパスワードを更新する。
+/// 漏洩したパスワードで作られたセッションが残らないよう、既存のセッションは全て失効させる
+#[instrument(skip(request))]
+pub async fn reset_password(
+    store: DynStore,
+    request: ResetPasswordRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = match store
+        .use_password_reset_token(&hash_secret(&request.token))
+        .await
+    {
+        Ok(Some(account_id)) => account_id,
+        Ok(None) => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::InvalidResetToken,
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let password = hash(request.password.as_bytes());
+    if let Err(e) = store
+        .update_account_password(account_id.clone(), password)
+        .await
+    {
+        return Err(warp::reject::custom(e));
+    }
+
+    if let Err(e) = store.revoke_account_sessions(account_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(warp::reply::with_status("Password updated", StatusCode::OK))
+}
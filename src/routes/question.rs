@@ -1,39 +1,114 @@
 use std::collections::HashMap;
 use tracing::{event, instrument, Level};
-use warp::http::StatusCode;
+use warp::http::{header, HeaderValue, StatusCode};
+use warp::path::FullPath;
+use warp::Reply;
 
 use crate::profanity::check_profanity;
-use crate::store::Store;
-use crate::types::account::Session;
-use crate::types::pagination::{extract_pagination, Pagination};
-use crate::types::question::{NewQuestion, Question};
+use crate::routes::authentication::log_privileged_action;
+use crate::store::DynStore;
+use crate::types::account::{AccountId, Role, Session};
+use crate::types::pagination::{
+    extract_pagination, next_offset_page_link, next_page_link, Page, Pagination,
+};
+use crate::types::question::{
+    extract_question_filter, extract_question_sort, NewQuestion, Question, QuestionPatch,
+    QuestionSort,
+};
 
-#[instrument]
-pub async fn get_questions(
-    params: HashMap<String, String>,
-    store: Store,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    event!(target: "question_and_answer", Level::INFO, "querying questions");
+/// 質問を編集・削除できるかを確かめ、ストアに渡す所有者のidを返す。
+/// モデレーター以上は他人の質問も操作でき、その操作は記録される
+pub(crate) async fn question_owner_for(
+    store: &DynStore,
+    id: i32,
+    session: &Session,
+    action: &str,
+) -> Result<AccountId, handle_errors::Error> {
+    if store.is_question_owner(id, &session.account_id).await? {
+        return Ok(session.account_id.clone());
+    }
+
+    if session.role < Role::Moderator {
+        return Err(handle_errors::Error::Unauthorized);
+    }
+
+    let owner = store.get_question_owner(id).await?;
+    log_privileged_action(session, action, Some(id));
+    Ok(owner)
+}
+
+/// 質問一覧の並び順とページ指定をクエリパラメータから取り出す
+pub fn extract_listing(
+    params: &[(String, String)],
+) -> Result<(QuestionSort, Pagination), handle_errors::Error> {
+    let sort = extract_question_sort(params)?;
+    let query: HashMap<String, String> = params.iter().cloned().collect();
     let mut pagination = Pagination::default();
 
-    if !params.is_empty() {
+    if !query.is_empty() {
         event!(Level::INFO, pagination = true);
-        pagination = extract_pagination(params)?;
+        pagination = extract_pagination(query)?;
     }
 
-    let res: Vec<Question> = match store
-        .get_questions(pagination.limit, pagination.offset)
-        .await
-    {
+    // カーソルは(created_on, id)の位置を表すので、それ以外の並び順では使えない
+    if pagination.cursor.is_some() && !sort.supports_cursor() {
+        return Err(handle_errors::Error::InvalidParameter("cursor".to_string()));
+    }
+
+    Ok((sort, pagination))
+}
+
+/// 質問一覧のレスポンス。続きがある場合は次のページを指す`Link`ヘッダーを付ける
+pub fn questions_page_reply(
+    path: &FullPath,
+    params: &[(String, String)],
+    sort: &QuestionSort,
+    pagination: &Pagination,
+    page: Page<Question>,
+) -> warp::reply::Response {
+    let mut res = warp::reply::json(&page.items).into_response();
+
+    if let (Some(cursor), Some(limit)) = (page.next_cursor, pagination.limit) {
+        let link = if sort.supports_cursor() {
+            next_page_link(path.as_str(), params, &cursor, limit)
+        } else {
+            next_offset_page_link(path.as_str(), params, pagination.offset, limit)
+        };
+        if let Ok(value) = HeaderValue::from_str(&link) {
+            res.headers_mut().insert(header::LINK, value);
+        }
+    }
+
+    res
+}
+
+/// INFO: `tag`を複数指定できるように、クエリパラメータをHashMapではなくVecで受け取る
+#[instrument]
+pub async fn get_questions(
+    path: FullPath,
+    params: Vec<(String, String)>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "question_and_answer", Level::INFO, "querying questions");
+    let filter = extract_question_filter(&params)?;
+    let (sort, pagination) = extract_listing(&params)?;
+
+    let res: Page<Question> = match store.get_questions(&filter, &sort, &pagination).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    Ok(warp::reply::json(&res))
+    Ok(questions_page_reply(
+        &path,
+        &params,
+        &sort,
+        &pagination,
+        res,
+    ))
 }
 
 #[instrument]
-pub async fn get_question(id: i32, store: Store) -> Result<impl warp::Reply, warp::Rejection> {
+pub async fn get_question(id: i32, store: DynStore) -> Result<impl warp::Reply, warp::Rejection> {
     let res: Question = match store.get_question(id).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
@@ -45,7 +120,7 @@ pub async fn get_question(id: i32, store: Store) -> Result<impl warp::Reply, war
 #[instrument]
 pub async fn add_question(
     new_question: NewQuestion,
-    store: Store,
+    store: DynStore,
     session: Session,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let account_id = session.account_id;
@@ -76,55 +151,156 @@ pub async fn add_question(
 pub async fn update_question(
     id: i32,
     question: Question,
-    store: Store,
+    store: DynStore,
     session: Session,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let account_id = session.account_id;
-    if store.is_question_owner(id, &account_id).await? {
-        let title = check_profanity(question.title);
-        let content = check_profanity(question.content);
-
-        let res = tokio::join!(title, content);
-
-        if let (Ok(title), Ok(content)) = res {
-            let question = Question {
-                id: question.id,
-                title,
-                content,
-                tags: question.tags,
-            };
-
-            match store.update_question(question, id, account_id).await {
-                Ok(res) => Ok(warp::reply::json(&res)),
-                Err(e) => Err(warp::reject::custom(e)),
-            }
-        } else {
-            Err(warp::reject::custom(
-                res.0.expect_err("Expected API call to have failed here"),
-            ))
+    let account_id = question_owner_for(&store, id, &session, "update_question").await?;
+
+    let title = check_profanity(question.title);
+    let content = check_profanity(question.content);
+
+    let res = tokio::join!(title, content);
+
+    if let (Ok(title), Ok(content)) = res {
+        let question = Question {
+            id: question.id,
+            title,
+            content,
+            tags: question.tags,
+            score: question.score,
+            accepted_answer_id: question.accepted_answer_id,
+        };
+
+        match store
+            .update_question(question, id, account_id, session.account_id)
+            .await
+        {
+            Ok(res) => Ok(warp::reply::json(&res)),
+            Err(e) => Err(warp::reject::custom(e)),
         }
     } else {
-        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+        Err(warp::reject::custom(
+            res.0.expect_err("Expected API call to have failed here"),
+        ))
     }
 }
 
+/// 送られてきたフィールドのうち、現在の値から変わったものだけを検査して更新する
 #[instrument]
-pub async fn delete_question(
+pub async fn patch_question(
     id: i32,
-    store: Store,
+    patch: QuestionPatch,
+    store: DynStore,
     session: Session,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let account_id = session.account_id;
-    if store.is_question_owner(id, &account_id).await? {
-        if let Err(e) = store.delete_question(id, account_id).await {
-            return Err(warp::reject::custom(e));
+    let account_id = question_owner_for(&store, id, &session, "patch_question").await?;
+
+    let current = match store.get_question(id).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let title = patch.title.filter(|title| *title != current.title);
+    let content = patch.content.filter(|content| *content != current.content);
+    let tags = patch.tags.filter(|tags| *tags != current.tags);
+
+    // INFO: 変更のないフィールドはAPIを呼ばずにそのまま通す
+    let (title, content) = tokio::join!(
+        async {
+            match title {
+                Some(title) => check_profanity(title).await.map(Some),
+                None => Ok(None),
+            }
+        },
+        async {
+            match content {
+                Some(content) => check_profanity(content).await.map(Some),
+                None => Ok(None),
+            }
         }
+    );
 
-        Ok(warp::reply::with_status(
-            format!("Question {} deleted", id),
-            StatusCode::OK,
-        ))
-    } else {
-        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    let patch = QuestionPatch {
+        title: title?,
+        content: content?,
+        tags,
+    };
+
+    match store
+        .patch_question(id, patch, account_id, session.account_id)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 削除された質問を元に戻す。保持期間を過ぎて完全に削除された後は戻せない
+#[instrument]
+pub async fn restore_question(
+    id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = question_owner_for(&store, id, &session, "restore_question").await?;
+
+    match store.restore_question(id, account_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 回答を採用する。採用できるのは質問者だけで、採用済みの回答があれば置き換える
+#[instrument]
+pub async fn accept_answer(
+    id: i32,
+    answer_id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if !store.is_question_owner(id, &account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    match store.accept_answer(id, answer_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+#[instrument]
+pub async fn unaccept_answer(
+    id: i32,
+    answer_id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if !store.is_question_owner(id, &account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::Unauthorized));
+    }
+
+    match store.unaccept_answer(id, answer_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn delete_question(
+    id: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = question_owner_for(&store, id, &session, "delete_question").await?;
+
+    if let Err(e) = store.delete_question(id, account_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(warp::reply::with_status(
+        format!("Question {} deleted", id),
+        StatusCode::OK,
+    ))
+}
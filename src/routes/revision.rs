@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::routes::question::question_owner_for;
+use crate::store::DynStore;
+use crate::types::account::Session;
+use crate::types::revision::{extract_revision_range, QuestionRevision, RevisionDiff};
+
+#[instrument]
+pub async fn get_revisions(
+    question_id: i32,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // 存在しない質問に対しては空の配列ではなくエラーを返す
+    if let Err(e) = store.get_question(question_id).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let res: Vec<QuestionRevision> = match store.get_question_revisions(question_id).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+/// `from`の版と`to`の版(省略時は現在の質問)の差分を返す
+#[instrument]
+pub async fn get_revision_diff(
+    question_id: i32,
+    params: HashMap<String, String>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (from, to) = extract_revision_range(params)?;
+
+    let old = match store.get_question_revision(question_id, from).await {
+        Ok(res) => res.to_question(),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let new = match to {
+        Some(to) => store
+            .get_question_revision(question_id, to)
+            .await
+            .map(|revision| revision.to_question()),
+        None => store.get_question(question_id).await,
+    };
+    let new = match new {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&RevisionDiff::new(from, &old, to, &new)))
+}
+
+/// 過去の版の内容で質問を更新する。ロールバック前の内容も新しい版として記録される
+#[instrument]
+pub async fn restore_revision(
+    question_id: i32,
+    revision: i32,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = question_owner_for(&store, question_id, &session, "restore_revision").await?;
+
+    // INFO: 過去の版は投稿時に検査済みなので、ここでは検査APIを呼ばない
+    let question = match store.get_question_revision(question_id, revision).await {
+        Ok(res) => res.to_question(),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match store
+        .update_question(question, question_id, account_id, session.account_id)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use tracing::{event, instrument, Level};
+
+use crate::store::DynStore;
+use crate::types::pagination::{extract_pagination, Pagination};
+use crate::types::search::SearchResult;
+
+#[instrument]
+pub async fn search(
+    mut params: HashMap<String, String>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let query = match params.remove("q") {
+        Some(q) if !q.trim().is_empty() => q,
+        _ => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::MissingParameters,
+            ))
+        }
+    };
+    event!(target: "question_and_answer", Level::INFO, query = %query, "searching questions");
+
+    let mut pagination = Pagination::default();
+
+    if !params.is_empty() {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    // 検索結果は関連度順に並ぶので、キーセットによるページングには対応しない
+    if pagination.cursor.is_some() {
+        return Err(warp::reject::custom(
+            handle_errors::Error::InvalidParameter("cursor".to_string()),
+        ));
+    }
+
+    let res: Vec<SearchResult> = match store
+        .search_questions(&query, pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
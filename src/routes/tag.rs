@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use tracing::{event, instrument, Level};
+use warp::path::FullPath;
+
+use crate::routes::question::{extract_listing, questions_page_reply};
+use crate::store::DynStore;
+use crate::types::pagination::{extract_pagination, Page, Pagination};
+use crate::types::question::{Question, QuestionFilter};
+use crate::types::tag::Tag;
+
+#[instrument]
+pub async fn get_tags(
+    params: HashMap<String, String>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "question_and_answer", Level::INFO, "querying tags");
+    let mut pagination = Pagination::default();
+
+    if !params.is_empty() {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    // タグは件数順に並ぶので、キーセットによるページングには対応しない
+    if pagination.cursor.is_some() {
+        return Err(warp::reject::custom(
+            handle_errors::Error::InvalidParameter("cursor".to_string()),
+        ));
+    }
+
+    let res: Vec<Tag> = match store.get_tags(pagination.limit, pagination.offset).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+#[instrument]
+pub async fn get_tag_questions(
+    name: String,
+    path: FullPath,
+    params: Vec<(String, String)>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // INFO: パスパラメータはパーセントエンコードされたまま渡ってくるのでデコードする (例: c%2B%2B)
+    let name = percent_encoding::percent_decode_str(&name)
+        .decode_utf8()
+        .map_err(|_| {
+            warp::reject::custom(handle_errors::Error::InvalidParameter("name".to_string()))
+        })?
+        .into_owned();
+    let filter = QuestionFilter {
+        tags: vec![name],
+        ..QuestionFilter::default()
+    };
+    let (sort, pagination) = extract_listing(&params)?;
+
+    let res: Page<Question> = match store.get_questions(&filter, &sort, &pagination).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(questions_page_reply(
+        &path,
+        &params,
+        &sort,
+        &pagination,
+        res,
+    ))
+}
@@ -0,0 +1,56 @@
+use tracing::instrument;
+
+use crate::store::DynStore;
+use crate::types::account::Session;
+use crate::types::vote::NewVote;
+
+#[instrument]
+pub async fn vote_question(
+    question_id: i32,
+    new_vote: NewVote,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+
+    // 削除された質問には投票できない
+    if let Err(e) = store.get_question(question_id).await {
+        return Err(warp::reject::custom(e));
+    }
+    if store.is_question_owner(question_id, &account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::SelfVote));
+    }
+
+    match store
+        .vote_question(question_id, account_id, new_vote.vote)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn vote_answer(
+    answer_id: i32,
+    new_vote: NewVote,
+    store: DynStore,
+    session: Session,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+
+    if let Err(e) = store.get_answer(answer_id).await {
+        return Err(warp::reject::custom(e));
+    }
+    if store.is_answer_owner(answer_id, &account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::SelfVote));
+    }
+
+    match store
+        .vote_answer(answer_id, account_id, new_vote.vote)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
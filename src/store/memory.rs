@@ -0,0 +1,1640 @@
+use async_trait::async_trait;
+use chrono::prelude::*;
+use handle_errors::Error;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::store::Store;
+use crate::types::{
+    account::{Account, AccountDeletion, AccountId, AccountInfo, LoginSession, Role},
+    answer::{Answer, AnswerId, NewAnswer},
+    comment::{Comment, CommentId, CommentTarget, NewComment},
+    export::{AccountExport, DataExport, ExportStatus, ExportedVote},
+    pagination::{Cursor, Page, Pagination},
+    question::{
+        NewQuestion, Question, QuestionFilter, QuestionId, QuestionPatch, QuestionSort,
+        SortDirection, SortField, TagMode,
+    },
+    revision::QuestionRevision,
+    search::{search_terms, SearchResult},
+    tag::Tag,
+    vote::VoteDirection,
+};
+
+/// `questions`テーブルの1行に相当
+#[derive(Debug, Clone)]
+struct QuestionRow {
+    id: i32,
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+    account_id: AccountId,
+    created_on: NaiveDateTime,
+    /// 並べ替え用に非正規化した値。データベース側ではトリガーで更新している
+    answer_count: i32,
+    last_activity_on: NaiveDateTime,
+    deleted_at: Option<NaiveDateTime>,
+    score: i32,
+    accepted_answer_id: Option<i32>,
+}
+
+impl QuestionRow {
+    fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    fn matches(&self, filter: &QuestionFilter) -> bool {
+        if self.is_deleted() {
+            return false;
+        }
+        if filter.unanswered && self.answer_count > 0 {
+            return false;
+        }
+        if filter.unaccepted && self.accepted_answer_id.is_some() {
+            return false;
+        }
+        if filter.tags.is_empty() {
+            return true;
+        }
+        let tags = self.tags.as_deref().unwrap_or_default();
+
+        match filter.tag_mode {
+            TagMode::Any => filter.tags.iter().any(|tag| tags.contains(tag)),
+            TagMode::All => filter.tags.iter().all(|tag| tags.contains(tag)),
+        }
+    }
+
+    /// `sort`の並び順で比較する。値が同じならidで比較する
+    fn cmp_by(&self, other: &QuestionRow, sort: &QuestionSort) -> std::cmp::Ordering {
+        let ordering = match sort.field {
+            SortField::CreatedOn => self.created_on.cmp(&other.created_on),
+            SortField::AnswerCount => self.answer_count.cmp(&other.answer_count),
+            SortField::LastActivity => self.last_activity_on.cmp(&other.last_activity_on),
+            SortField::Title => self.title.to_lowercase().cmp(&other.title.to_lowercase()),
+            SortField::Score => self.score.cmp(&other.score),
+        }
+        .then_with(|| self.id.cmp(&other.id));
+
+        match sort.direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            created_on: self.created_on,
+            id: self.id,
+        }
+    }
+
+    fn to_question(&self) -> Question {
+        Question {
+            id: QuestionId(self.id),
+            title: self.title.clone(),
+            content: self.content.clone(),
+            tags: self.tags.clone(),
+            score: self.score,
+            accepted_answer_id: self.accepted_answer_id.map(AnswerId),
+        }
+    }
+}
+
+/// `answers`テーブルの1行に相当
+#[derive(Debug, Clone)]
+struct AnswerRow {
+    id: i32,
+    content: String,
+    question_id: i32,
+    account_id: AccountId,
+    score: i32,
+}
+
+impl AnswerRow {
+    fn to_answer(&self) -> Answer {
+        Answer {
+            id: AnswerId(self.id),
+            content: self.content.clone(),
+            question_id: QuestionId(self.question_id),
+            score: self.score,
+        }
+    }
+}
+
+/// `comments`テーブルの1行に相当
+#[derive(Debug, Clone)]
+struct CommentRow {
+    id: i32,
+    content: String,
+    target: CommentTarget,
+    account_id: AccountId,
+}
+
+impl CommentRow {
+    fn to_comment(&self) -> Comment {
+        Comment {
+            id: CommentId(self.id),
+            content: self.content.clone(),
+            question_id: self.target.question_id().map(QuestionId),
+            answer_id: self.target.answer_id().map(AnswerId),
+        }
+    }
+}
+
+/// `password_reset_tokens`・`email_verification_tokens`テーブルの1行に相当
+#[derive(Debug, Clone)]
+struct TokenRow {
+    account_id: AccountId,
+    expires_at: NaiveDateTime,
+    used_at: Option<NaiveDateTime>,
+}
+
+/// `data_exports`テーブルの1行に相当
+#[derive(Debug, Clone)]
+struct DataExportRow {
+    id: i32,
+    account_id: AccountId,
+    /// `pending`・`ready`・`failed`のいずれか
+    status: &'static str,
+    archive: Option<String>,
+    token_hash: Option<String>,
+    created_on: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+}
+
+impl DataExportRow {
+    fn to_data_export(&self) -> DataExport {
+        DataExport {
+            id: self.id,
+            status: ExportStatus::from_db(self.status, self.expires_at),
+            created_on: self.created_on,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tables {
+    questions: BTreeMap<i32, QuestionRow>,
+    answers: BTreeMap<i32, AnswerRow>,
+    /// `(question_id, revision)`の順に並ぶ
+    revisions: BTreeMap<(i32, i32), QuestionRevision>,
+    /// `(投稿のid, 投票したアカウントのid)`ごとの票と投票した日時
+    question_votes: HashMap<(i32, i32), (i16, NaiveDateTime)>,
+    answer_votes: HashMap<(i32, i32), (i16, NaiveDateTime)>,
+    comments: BTreeMap<i32, CommentRow>,
+    /// PostgreSQL側と同じくemailを主キーとして扱う
+    accounts: BTreeMap<String, Account>,
+    login_sessions: BTreeMap<i32, LoginSession>,
+    /// トークンのハッシュをキーにする
+    password_reset_tokens: HashMap<String, TokenRow>,
+    email_verification_tokens: HashMap<String, TokenRow>,
+    data_exports: BTreeMap<i32, DataExportRow>,
+    question_seq: i32,
+    answer_seq: i32,
+    comment_seq: i32,
+    login_session_seq: i32,
+    account_seq: i32,
+    data_export_seq: i32,
+}
+
+impl Tables {
+    /// 所有者の質問であれば、現在の内容を`editor`が書き換えた版として記録してから書き換えられるように返す
+    fn edit_question(
+        &mut self,
+        id: i32,
+        account_id: &AccountId,
+        editor: &AccountId,
+    ) -> Option<&mut QuestionRow> {
+        let row = self.questions.get(&id)?;
+        if &row.account_id != account_id || row.is_deleted() {
+            return None;
+        }
+
+        let revision = self
+            .revisions
+            .range((id, i32::MIN)..=(id, i32::MAX))
+            .next_back()
+            .map_or(1, |((_, revision), _)| revision + 1);
+        let previous = QuestionRevision {
+            revision,
+            question_id: QuestionId(id),
+            account_id: editor.clone(),
+            created_on: now(),
+            title: row.title.clone(),
+            content: row.content.clone(),
+            tags: row.tags.clone(),
+        };
+        self.revisions.insert((id, revision), previous);
+
+        self.questions.get_mut(&id)
+    }
+
+    /// 回答を削除し、トリガーと外部キーの制約の代わりに関連する値を更新する
+    fn remove_answer(&mut self, id: i32) {
+        let question_id = match self.answers.remove(&id) {
+            Some(row) => row.question_id,
+            None => return,
+        };
+
+        if let Some(question) = self.questions.get_mut(&question_id) {
+            question.answer_count -= 1;
+            if question.accepted_answer_id == Some(id) {
+                question.accepted_answer_id = None;
+            }
+        }
+        self.answer_votes
+            .retain(|(answer_id, _), _| *answer_id != id);
+        self.comments
+            .retain(|_, comment| comment.target != CommentTarget::Answer(id));
+    }
+}
+
+/// テストやローカル開発用に、データをプロセス内のメモリに保持するストア
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: RwLock<Tables>,
+}
+
+/// INFO: ハンドラの`#[instrument]`がストアを記録するので、パスワードやトークンのハッシュ、
+/// エクスポートの内容がログに出ないようにテーブルは表示しない
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore").finish_non_exhaustive()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+/// INFO: カーソルはマイクロ秒単位で表現するので、PostgreSQLのtimestampと同じ精度に揃える
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc().trunc_subsecs(6)
+}
+
+/// 票を登録・変更・取り消しして、合計に加える差分を返す
+fn apply_vote(
+    votes: &mut HashMap<(i32, i32), (i16, NaiveDateTime)>,
+    key: (i32, i32),
+    vote: VoteDirection,
+) -> i32 {
+    let previous = match vote {
+        VoteDirection::None => votes.remove(&key),
+        _ => votes.insert(key, (vote.value(), now())),
+    };
+
+    i32::from(vote.value()) - i32::from(previous.map_or(0, |(value, _)| value))
+}
+
+fn account_info(account: &Account) -> AccountInfo {
+    AccountInfo {
+        id: account
+            .id
+            .clone()
+            .expect("stored accounts always have an id"),
+        email: account.email.clone(),
+        role: account.role,
+        verified_at: account.verified_at,
+    }
+}
+
+/// SERIAL型と同じく1から採番する
+fn next_id(seq: &mut i32) -> i32 {
+    *seq += 1;
+    *seq
+}
+
+/// 抜粋に含める一致箇所の前後の単語数
+const SNIPPET_WORDS_BEFORE: usize = 8;
+const SNIPPET_WORDS_AFTER: usize = 24;
+
+/// 1つのテキストに対する検索語の一致状況
+struct TextMatch {
+    /// 一致した単語の数
+    hits: usize,
+    /// 一致した検索語のインデックス
+    terms: HashSet<usize>,
+    /// 最初の一致箇所の周辺を`<b>`で強調した抜粋
+    snippet: String,
+}
+
+/// 検索語のいずれかで始まる単語を探す。全文検索インデックスの前方一致に相当
+fn match_text(text: &str, terms: &[String]) -> Option<TextMatch> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut matched = HashSet::new();
+    let mut hit_positions = Vec::new();
+
+    for (pos, word) in words.iter().enumerate() {
+        let normalized: String = word
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        let mut hit = false;
+        for (i, term) in terms.iter().enumerate() {
+            if normalized.starts_with(term.as_str()) {
+                matched.insert(i);
+                hit = true;
+            }
+        }
+        if hit {
+            hit_positions.push(pos);
+        }
+    }
+
+    let first = *hit_positions.first()?;
+    let start = first.saturating_sub(SNIPPET_WORDS_BEFORE);
+    let end = (first + SNIPPET_WORDS_AFTER).min(words.len());
+    let mut snippet = words[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if hit_positions.contains(&(start + i)) {
+                format!("<b>{}</b>", word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        snippet = format!("...{}", snippet);
+    }
+    if end < words.len() {
+        snippet = format!("{}...", snippet);
+    }
+
+    Some(TextMatch {
+        hits: hit_positions.len(),
+        terms: matched,
+        snippet,
+    })
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        sort: &QuestionSort,
+        pagination: &Pagination,
+    ) -> Result<Page<Question>, Error> {
+        let tables = self.tables.read();
+        let limit = pagination
+            .limit
+            .map(|l| l as usize + 1)
+            .unwrap_or(usize::MAX);
+
+        let mut rows: Vec<&QuestionRow> = tables
+            .questions
+            .values()
+            .filter(|row| row.matches(filter))
+            .filter(|row| match (pagination.cursor, sort.direction) {
+                (Some(cursor), SortDirection::Asc) => {
+                    (row.created_on, row.id) > (cursor.created_on, cursor.id)
+                }
+                (Some(cursor), SortDirection::Desc) => {
+                    (row.created_on, row.id) < (cursor.created_on, cursor.id)
+                }
+                (None, _) => true,
+            })
+            .collect();
+        rows.sort_by(|a, b| a.cmp_by(b, sort));
+
+        let rows = rows
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(limit)
+            .map(|row| (row.to_question(), row.cursor()))
+            .collect();
+
+        Ok(Page::from_rows(rows, pagination.limit))
+    }
+
+    async fn get_question(&self, id: i32) -> Result<Question, Error> {
+        self.tables
+            .read()
+            .questions
+            .get(&id)
+            .filter(|row| !row.is_deleted())
+            .map(QuestionRow::to_question)
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        let id = next_id(&mut tables.question_seq);
+        let created_on = now();
+        let row = QuestionRow {
+            id,
+            title: new_question.title,
+            content: new_question.content,
+            tags: new_question.tags,
+            account_id,
+            created_on,
+            answer_count: 0,
+            last_activity_on: created_on,
+            deleted_at: None,
+            score: 0,
+            accepted_answer_id: None,
+        };
+        let question = row.to_question();
+        tables.questions.insert(id, row);
+
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        match tables.edit_question(id, &account_id, &editor) {
+            Some(row) => {
+                row.title = question.title;
+                row.content = question.content;
+                row.tags = question.tags;
+                row.last_activity_on = now();
+                Ok(row.to_question())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn patch_question(
+        &self,
+        id: i32,
+        patch: QuestionPatch,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        if patch.is_empty() {
+            return tables
+                .questions
+                .get(&id)
+                .filter(|row| !row.is_deleted())
+                .map(QuestionRow::to_question)
+                .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound));
+        }
+
+        match tables.edit_question(id, &account_id, &editor) {
+            Some(row) => {
+                if let Some(title) = patch.title {
+                    row.title = title;
+                }
+                if let Some(content) = patch.content {
+                    row.content = content;
+                }
+                if let Some(tags) = patch.tags {
+                    row.tags = tags;
+                }
+                row.last_activity_on = now();
+                Ok(row.to_question())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error> {
+        Ok(self
+            .tables
+            .read()
+            .revisions
+            .range((question_id, i32::MIN)..=(question_id, i32::MAX))
+            .map(|(_, revision)| revision.clone())
+            .collect())
+    }
+
+    async fn get_question_revision(
+        &self,
+        question_id: i32,
+        revision: i32,
+    ) -> Result<QuestionRevision, Error> {
+        self.tables
+            .read()
+            .revisions
+            .get(&(question_id, revision))
+            .cloned()
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn delete_question(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        match tables.questions.get_mut(&id) {
+            Some(row) if row.account_id == account_id && !row.is_deleted() => {
+                row.deleted_at = Some(now());
+            }
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    async fn restore_question(&self, id: i32, account_id: AccountId) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        match tables.questions.get_mut(&id) {
+            Some(row) if row.account_id == account_id && row.is_deleted() => {
+                row.deleted_at = None;
+                Ok(row.to_question())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn purge_deleted_questions(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        let mut tables = self.tables.write();
+        let purged: HashSet<i32> = tables
+            .questions
+            .values()
+            .filter(|row| row.deleted_at.is_some_and(|deleted_at| deleted_at < before))
+            .map(|row| row.id)
+            .collect();
+
+        let purged_answers: HashSet<i32> = tables
+            .answers
+            .values()
+            .filter(|answer| purged.contains(&answer.question_id))
+            .map(|answer| answer.id)
+            .collect();
+
+        tables.questions.retain(|id, _| !purged.contains(id));
+        tables.answers.retain(|id, _| !purged_answers.contains(id));
+        tables
+            .question_votes
+            .retain(|(question_id, _), _| !purged.contains(question_id));
+        tables
+            .answer_votes
+            .retain(|(answer_id, _), _| !purged_answers.contains(answer_id));
+        tables
+            .revisions
+            .retain(|(question_id, _), _| !purged.contains(question_id));
+        tables.comments.retain(|_, comment| match comment.target {
+            CommentTarget::Question(id) => !purged.contains(&id),
+            CommentTarget::Answer(id) => !purged_answers.contains(&id),
+        });
+
+        Ok(purged.len() as u64)
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(matches!(
+            self.tables.read().questions.get(&question_id),
+            Some(row) if &row.account_id == account_id
+        ))
+    }
+
+    async fn get_question_owner(&self, question_id: i32) -> Result<AccountId, Error> {
+        self.tables
+            .read()
+            .questions
+            .get(&question_id)
+            .map(|row| row.account_id.clone())
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        let belongs = matches!(
+            tables.answers.get(&answer_id),
+            Some(answer) if answer.question_id == question_id
+        );
+
+        match tables.questions.get_mut(&question_id) {
+            Some(row) if belongs && !row.is_deleted() => {
+                row.accepted_answer_id = Some(answer_id);
+                Ok(row.to_question())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        match tables.questions.get_mut(&question_id) {
+            Some(row) if row.accepted_answer_id == Some(answer_id) && !row.is_deleted() => {
+                row.accepted_answer_id = None;
+                Ok(row.to_question())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tables = self.tables.read();
+        let mut results = Vec::new();
+
+        for question in tables.questions.values().filter(|row| !row.is_deleted()) {
+            // 重みはPostgreSQL側と同じくタイトル > 本文 > 回答の順
+            let mut texts = vec![
+                (question.title.as_str(), 2.0),
+                (question.content.as_str(), 1.0),
+            ];
+            texts.extend(
+                tables
+                    .answers
+                    .values()
+                    .filter(|answer| answer.question_id == question.id)
+                    .map(|answer| (answer.content.as_str(), 0.5)),
+            );
+
+            let mut matched_terms = HashSet::new();
+            let mut relevance = 0.0;
+            let mut best: Option<(f32, String)> = None;
+            for (text, weight) in texts {
+                if let Some(m) = match_text(text, &terms) {
+                    let rank =
+                        weight * m.hits as f32 / (text.split_whitespace().count() as f32).sqrt();
+                    relevance += rank;
+                    matched_terms.extend(m.terms);
+                    if best.as_ref().is_none_or(|(best_rank, _)| rank > *best_rank) {
+                        best = Some((rank, m.snippet));
+                    }
+                }
+            }
+
+            // 全ての検索語が質問か回答のどこかに含まれているものだけを返す
+            if let (true, Some((_, snippet))) = (matched_terms.len() == terms.len(), best) {
+                results.push(SearchResult {
+                    question: question.to_question(),
+                    relevance,
+                    snippet,
+                });
+            }
+        }
+
+        results.sort_by(|a, b| {
+            b.relevance
+                .total_cmp(&a.relevance)
+                .then_with(|| a.question.id.0.cmp(&b.question.id.0))
+        });
+
+        Ok(results
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn get_tags(&self, limit: Option<u32>, offset: u32) -> Result<Vec<Tag>, Error> {
+        let tables = self.tables.read();
+        let mut counts: HashMap<&str, i64> = HashMap::new();
+        for row in tables.questions.values().filter(|row| !row.is_deleted()) {
+            let tags: HashSet<&String> = row.tags.iter().flatten().collect();
+            for tag in tags {
+                *counts.entry(tag.as_str()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<Tag> = counts
+            .into_iter()
+            .map(|(name, count)| Tag {
+                name: name.to_string(),
+                count,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(tags
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn get_answers(&self, question_id: i32) -> Result<Vec<Answer>, Error> {
+        Ok(self
+            .tables
+            .read()
+            .answers
+            .values()
+            .filter(|row| row.question_id == question_id)
+            .map(AnswerRow::to_answer)
+            .collect())
+    }
+
+    async fn get_answer(&self, id: i32) -> Result<Answer, Error> {
+        self.tables
+            .read()
+            .answers
+            .get(&id)
+            .map(AnswerRow::to_answer)
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn add_answer(
+        &self,
+        question_id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        let mut tables = self.tables.write();
+        // 外部キー制約の代わり
+        match tables.questions.get_mut(&question_id) {
+            Some(question) => {
+                question.answer_count += 1;
+                question.last_activity_on = now();
+            }
+            None => return Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+
+        let id = next_id(&mut tables.answer_seq);
+        let row = AnswerRow {
+            id,
+            content: new_answer.content,
+            question_id,
+            account_id,
+            score: 0,
+        };
+        let answer = row.to_answer();
+        tables.answers.insert(id, row);
+
+        Ok(answer)
+    }
+
+    async fn update_answer(
+        &self,
+        id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        let mut tables = self.tables.write();
+        match tables.answers.get_mut(&id) {
+            Some(row) if row.account_id == account_id => {
+                row.content = new_answer.content;
+                Ok(row.to_answer())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn delete_answer(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        if matches!(tables.answers.get(&id), Some(row) if row.account_id == account_id) {
+            tables.remove_answer(id);
+        }
+
+        Ok(true)
+    }
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error> {
+        Ok(matches!(
+            self.tables.read().answers.get(&answer_id),
+            Some(row) if &row.account_id == account_id
+        ))
+    }
+
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<AccountId, Error> {
+        self.tables
+            .read()
+            .answers
+            .get(&answer_id)
+            .map(|row| row.account_id.clone())
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Question, Error> {
+        let mut tables = self.tables.write();
+        if !tables.questions.contains_key(&question_id) {
+            return Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound));
+        }
+
+        let delta = apply_vote(
+            &mut tables.question_votes,
+            (question_id, account_id.0),
+            vote,
+        );
+        let row = tables
+            .questions
+            .get_mut(&question_id)
+            .expect("question exists");
+        row.score += delta;
+
+        Ok(row.to_question())
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Answer, Error> {
+        let mut tables = self.tables.write();
+        if !tables.answers.contains_key(&answer_id) {
+            return Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound));
+        }
+
+        let delta = apply_vote(&mut tables.answer_votes, (answer_id, account_id.0), vote);
+        let row = tables.answers.get_mut(&answer_id).expect("answer exists");
+        row.score += delta;
+
+        Ok(row.to_answer())
+    }
+
+    async fn get_comments(&self, target: CommentTarget) -> Result<Vec<Comment>, Error> {
+        Ok(self
+            .tables
+            .read()
+            .comments
+            .values()
+            .filter(|row| row.target == target)
+            .map(CommentRow::to_comment)
+            .collect())
+    }
+
+    async fn add_comment(
+        &self,
+        target: CommentTarget,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        let mut tables = self.tables.write();
+        // 外部キー制約の代わり
+        let exists = match target {
+            CommentTarget::Question(id) => tables.questions.contains_key(&id),
+            CommentTarget::Answer(id) => tables.answers.contains_key(&id),
+        };
+        if !exists {
+            return Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound));
+        }
+
+        let id = next_id(&mut tables.comment_seq);
+        let row = CommentRow {
+            id,
+            content: new_comment.content,
+            target,
+            account_id,
+        };
+        let comment = row.to_comment();
+        tables.comments.insert(id, row);
+
+        Ok(comment)
+    }
+
+    async fn update_comment(
+        &self,
+        id: i32,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        let mut tables = self.tables.write();
+        match tables.comments.get_mut(&id) {
+            Some(row) if row.account_id == account_id => {
+                row.content = new_comment.content;
+                Ok(row.to_comment())
+            }
+            _ => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn delete_comment(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        if matches!(tables.comments.get(&id), Some(row) if row.account_id == account_id) {
+            tables.comments.remove(&id);
+        }
+
+        Ok(true)
+    }
+
+    async fn is_comment_owner(
+        &self,
+        comment_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(matches!(
+            self.tables.read().comments.get(&comment_id),
+            Some(row) if &row.account_id == account_id
+        ))
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        if tables.accounts.contains_key(&account.email) {
+            return Err(Error::AccountAlreadyExists);
+        }
+
+        let id = next_id(&mut tables.account_seq);
+        tables.accounts.insert(
+            account.email.clone(),
+            Account {
+                id: Some(AccountId(id)),
+                email: account.email,
+                password: account.password,
+                role: Role::User,
+                verified_at: None,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        self.tables
+            .read()
+            .accounts
+            .get(&email)
+            .cloned()
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn get_account_info(&self, id: AccountId) -> Result<AccountInfo, Error> {
+        self.tables
+            .read()
+            .accounts
+            .values()
+            .find(|account| account.id.as_ref() == Some(&id))
+            .map(account_info)
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountInfo>, Error> {
+        let mut accounts: Vec<AccountInfo> = self
+            .tables
+            .read()
+            .accounts
+            .values()
+            .map(account_info)
+            .collect();
+        // INFO: emailをキーにしているので、PostgreSQL側に合わせてid順に並べ直す
+        accounts.sort_by_key(|account| account.id.0);
+
+        Ok(accounts)
+    }
+
+    async fn update_account_role(&self, id: AccountId, role: Role) -> Result<AccountInfo, Error> {
+        let mut tables = self.tables.write();
+        match tables
+            .accounts
+            .values_mut()
+            .find(|account| account.id.as_ref() == Some(&id))
+        {
+            Some(account) => {
+                account.role = role;
+                Ok(account_info(account))
+            }
+            None => Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+        }
+    }
+
+    async fn update_account_password(
+        &self,
+        id: AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        match tables
+            .accounts
+            .values_mut()
+            .find(|account| account.id.as_ref() == Some(&id))
+        {
+            Some(account) => {
+                account.password = password;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_account(&self, id: AccountId, mode: AccountDeletion) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        let tables = &mut *tables;
+        let now = now();
+
+        match mode {
+            AccountDeletion::Anonymize => {
+                for row in tables.questions.values_mut() {
+                    if row.account_id == id {
+                        row.account_id = AccountId::DELETED;
+                    }
+                }
+                for row in tables.answers.values_mut() {
+                    if row.account_id == id {
+                        row.account_id = AccountId::DELETED;
+                    }
+                }
+                for row in tables.comments.values_mut() {
+                    if row.account_id == id {
+                        row.account_id = AccountId::DELETED;
+                    }
+                }
+                for revision in tables.revisions.values_mut() {
+                    if revision.account_id == id {
+                        revision.account_id = AccountId::DELETED;
+                    }
+                }
+            }
+            AccountDeletion::Cascade => {
+                for row in tables.questions.values_mut() {
+                    if row.account_id == id && !row.is_deleted() {
+                        row.deleted_at = Some(now);
+                    }
+                }
+                tables
+                    .comments
+                    .retain(|_, comment| comment.account_id != id);
+                let answers: Vec<i32> = tables
+                    .answers
+                    .values()
+                    .filter(|row| row.account_id == id)
+                    .map(|row| row.id)
+                    .collect();
+                for answer_id in answers {
+                    tables.remove_answer(answer_id);
+                }
+            }
+        }
+
+        // トリガーの代わりに票の合計から差し引く
+        let questions = &mut tables.questions;
+        tables
+            .question_votes
+            .retain(|(question_id, account_id), (value, _)| {
+                if *account_id != id.0 {
+                    return true;
+                }
+                if let Some(row) = questions.get_mut(question_id) {
+                    row.score -= i32::from(*value);
+                }
+                false
+            });
+        let answers = &mut tables.answers;
+        tables
+            .answer_votes
+            .retain(|(answer_id, account_id), (value, _)| {
+                if *account_id != id.0 {
+                    return true;
+                }
+                if let Some(row) = answers.get_mut(answer_id) {
+                    row.score -= i32::from(*value);
+                }
+                false
+            });
+
+        tables
+            .password_reset_tokens
+            .retain(|_, token| token.account_id != id);
+        tables
+            .email_verification_tokens
+            .retain(|_, token| token.account_id != id);
+        tables.data_exports.retain(|_, row| row.account_id != id);
+        for session in tables.login_sessions.values_mut() {
+            if session.account_id == id && session.revoked_at.is_none() {
+                session.revoked_at = Some(now);
+            }
+        }
+
+        let before = tables.accounts.len();
+        tables
+            .accounts
+            .retain(|_, account| account.id.as_ref() != Some(&id));
+        Ok(tables.accounts.len() < before)
+    }
+
+    async fn add_password_reset_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        self.tables.write().password_reset_tokens.insert(
+            token_hash,
+            TokenRow {
+                account_id,
+                expires_at,
+                used_at: None,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn use_password_reset_token(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        let mut tables = self.tables.write();
+        let now = now();
+        match tables.password_reset_tokens.get_mut(token_hash) {
+            Some(row) if row.used_at.is_none() && row.expires_at > now => {
+                row.used_at = Some(now);
+                Ok(Some(row.account_id.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn add_email_verification_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        self.tables.write().email_verification_tokens.insert(
+            token_hash,
+            TokenRow {
+                account_id,
+                expires_at,
+                used_at: None,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn verify_email(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        let mut tables = self.tables.write();
+        let now = now();
+        let account_id = match tables.email_verification_tokens.get_mut(token_hash) {
+            Some(row) if row.used_at.is_none() && row.expires_at > now => {
+                row.used_at = Some(now);
+                row.account_id.clone()
+            }
+            _ => return Ok(None),
+        };
+
+        if let Some(account) = tables
+            .accounts
+            .values_mut()
+            .find(|account| account.id.as_ref() == Some(&account_id))
+        {
+            account.verified_at.get_or_insert(now);
+        }
+
+        Ok(Some(account_id))
+    }
+
+    async fn add_login_session(
+        &self,
+        account_id: AccountId,
+        refresh_token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<LoginSession, Error> {
+        let mut tables = self.tables.write();
+        let id = next_id(&mut tables.login_session_seq);
+        let session = LoginSession {
+            id,
+            account_id,
+            refresh_token_hash,
+            expires_at,
+            revoked_at: None,
+        };
+        tables.login_sessions.insert(id, session.clone());
+
+        Ok(session)
+    }
+
+    async fn get_login_session(&self, id: i32) -> Result<LoginSession, Error> {
+        self.tables
+            .read()
+            .login_sessions
+            .get(&id)
+            .cloned()
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        match tables.login_sessions.get_mut(&id) {
+            Some(session)
+                if session.refresh_token_hash == old_hash && session.revoked_at.is_none() =>
+            {
+                session.refresh_token_hash = new_hash;
+                session.expires_at = expires_at;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn revoke_login_session(&self, id: i32) -> Result<bool, Error> {
+        let mut tables = self.tables.write();
+        match tables.login_sessions.get_mut(&id) {
+            Some(session) if session.revoked_at.is_none() => {
+                session.revoked_at = Some(now());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn revoke_account_sessions(&self, account_id: AccountId) -> Result<u64, Error> {
+        let mut tables = self.tables.write();
+        let now = now();
+        let mut revoked = 0;
+        for session in tables.login_sessions.values_mut() {
+            if session.account_id == account_id && session.revoked_at.is_none() {
+                session.revoked_at = Some(now);
+                revoked += 1;
+            }
+        }
+
+        Ok(revoked)
+    }
+
+    async fn get_account_export(&self, account_id: AccountId) -> Result<AccountExport, Error> {
+        let tables = self.tables.read();
+        let account = tables
+            .accounts
+            .values()
+            .find(|account| account.id.as_ref() == Some(&account_id))
+            .map(account_info)
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))?;
+
+        let mut votes: Vec<ExportedVote> = tables
+            .question_votes
+            .iter()
+            .filter(|((_, voter), _)| *voter == account_id.0)
+            .map(|((question_id, _), (value, created_on))| ExportedVote {
+                question_id: Some(QuestionId(*question_id)),
+                answer_id: None,
+                value: *value,
+                created_on: *created_on,
+            })
+            .chain(
+                tables
+                    .answer_votes
+                    .iter()
+                    .filter(|((_, voter), _)| *voter == account_id.0)
+                    .map(|((answer_id, _), (value, created_on))| ExportedVote {
+                        question_id: None,
+                        answer_id: Some(AnswerId(*answer_id)),
+                        value: *value,
+                        created_on: *created_on,
+                    }),
+            )
+            .collect();
+        votes.sort_by_key(|vote| vote.created_on);
+
+        Ok(AccountExport {
+            exported_on: now(),
+            account,
+            questions: tables
+                .questions
+                .values()
+                .filter(|row| row.account_id == account_id)
+                .map(QuestionRow::to_question)
+                .collect(),
+            answers: tables
+                .answers
+                .values()
+                .filter(|row| row.account_id == account_id)
+                .map(AnswerRow::to_answer)
+                .collect(),
+            comments: tables
+                .comments
+                .values()
+                .filter(|row| row.account_id == account_id)
+                .map(CommentRow::to_comment)
+                .collect(),
+            votes,
+            revisions: tables
+                .revisions
+                .values()
+                .filter(|revision| revision.account_id == account_id)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn add_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        let mut tables = self.tables.write();
+        let id = next_id(&mut tables.data_export_seq);
+        let row = DataExportRow {
+            id,
+            account_id,
+            status: "pending",
+            archive: None,
+            token_hash: None,
+            created_on: now(),
+            expires_at: None,
+        };
+        let export = row.to_data_export();
+        tables.data_exports.insert(id, row);
+
+        Ok(export)
+    }
+
+    async fn get_latest_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        self.tables
+            .read()
+            .data_exports
+            .values()
+            .rev()
+            .find(|row| row.account_id == account_id)
+            .map(DataExportRow::to_data_export)
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn complete_data_export(
+        &self,
+        id: i32,
+        archive: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match self.tables.write().data_exports.get_mut(&id) {
+            Some(row) if row.status == "pending" => {
+                row.status = "ready";
+                row.archive = Some(archive);
+                row.token_hash = Some(token_hash);
+                row.expires_at = Some(expires_at);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn fail_data_export(&self, id: i32) -> Result<bool, Error> {
+        match self.tables.write().data_exports.get_mut(&id) {
+            Some(row) if row.status == "pending" => {
+                row.status = "failed";
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn get_data_export_archive(&self, token_hash: &str) -> Result<Option<String>, Error> {
+        let now = now();
+        Ok(self
+            .tables
+            .read()
+            .data_exports
+            .values()
+            .find(|row| {
+                row.status == "ready"
+                    && row.token_hash.as_deref() == Some(token_hash)
+                    && row.expires_at.is_some_and(|expires_at| expires_at > now)
+            })
+            .and_then(|row| row.archive.clone()))
+    }
+
+    async fn get_latest_data_export_archive(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Option<String>, Error> {
+        let now = now();
+        Ok(self
+            .tables
+            .read()
+            .data_exports
+            .values()
+            .rev()
+            .find(|row| row.account_id == account_id)
+            .filter(|row| {
+                row.status == "ready" && row.expires_at.is_some_and(|expires_at| expires_at > now)
+            })
+            .and_then(|row| row.archive.clone()))
+    }
+
+    async fn purge_expired_data_exports(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        let mut tables = self.tables.write();
+        let count = tables.data_exports.len();
+        tables.data_exports.retain(|_, row| {
+            let expired = row.expires_at.is_some_and(|expires_at| expires_at < before);
+            let failed = row.status == "failed" && row.created_on < before;
+            !(expired || failed)
+        });
+
+        Ok((count - tables.data_exports.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod memory_store_tests {
+    use super::*;
+
+    async fn add_question(store: &MemoryStore, title: &str) -> Question {
+        store
+            .add_question(
+                NewQuestion {
+                    title: title.to_string(),
+                    content: "content".to_string(),
+                    tags: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn questions_are_paginated_in_insertion_order() {
+        let store = MemoryStore::new();
+        for title in ["first", "second", "third"] {
+            add_question(&store, title).await;
+        }
+
+        let filter = QuestionFilter::default();
+        let pagination = Pagination {
+            limit: Some(1),
+            offset: 1,
+            cursor: None,
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &pagination)
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].title, "second");
+
+        let pagination = Pagination {
+            limit: Some(1),
+            offset: 0,
+            cursor: page.next_cursor,
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &pagination)
+            .await
+            .unwrap();
+        assert_eq!(page.items[0].title, "third");
+        assert_eq!(page.next_cursor, None);
+
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn questions_are_sorted_by_recent_activity() {
+        let store = MemoryStore::new();
+        for title in ["first", "second", "third"] {
+            add_question(&store, title).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        store
+            .add_answer(
+                1,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+
+        let sort = QuestionSort {
+            field: SortField::LastActivity,
+            direction: SortDirection::Desc,
+        };
+        let page = store
+            .get_questions(&QuestionFilter::default(), &sort, &Pagination::default())
+            .await
+            .unwrap();
+        let titles: Vec<_> = page.items.iter().map(|q| q.title.as_str()).collect();
+        assert_eq!(titles, vec!["first", "third", "second"]);
+    }
+
+    #[tokio::test]
+    async fn only_owner_can_change_question() {
+        let store = MemoryStore::new();
+        let question = add_question(&store, "title").await;
+
+        assert!(store
+            .is_question_owner(question.id.0, &AccountId(1))
+            .await
+            .unwrap());
+        assert!(!store
+            .is_question_owner(question.id.0, &AccountId(2))
+            .await
+            .unwrap());
+        assert!(store
+            .update_question(question.clone(), question.id.0, AccountId(2), AccountId(2))
+            .await
+            .is_err());
+
+        store
+            .delete_question(question.id.0, AccountId(1))
+            .await
+            .unwrap();
+        assert!(store.get_question(question.id.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn answers_belong_to_existing_question() {
+        let store = MemoryStore::new();
+        let question = add_question(&store, "title").await;
+        let answer = NewAnswer {
+            content: "answer".to_string(),
+        };
+
+        let added = store
+            .add_answer(question.id.0, answer.clone(), AccountId(2))
+            .await
+            .unwrap();
+        assert_eq!(added.question_id, question.id);
+        assert_eq!(store.get_answers(question.id.0).await.unwrap().len(), 1);
+        assert!(store.add_answer(42, answer, AccountId(2)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn search_requires_every_term_and_highlights_matches() {
+        let store = MemoryStore::new();
+        let question = add_question(&store, "How to use warp filters").await;
+        add_question(&store, "Database pooling").await;
+        store
+            .add_answer(
+                question.id.0,
+                NewAnswer {
+                    content: "Use and_then for async handlers".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+
+        let results = store.search_questions("warp async", None, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].question.id, question.id);
+        assert_eq!(results[0].snippet, "How to use <b>warp</b> filters");
+
+        assert!(store
+            .search_questions("warp missing", None, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicate_email_is_rejected() {
+        let store = MemoryStore::new();
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "hash".to_string(),
+            role: Role::User,
+            verified_at: None,
+        };
+
+        store.add_account(account.clone()).await.unwrap();
+        assert!(matches!(
+            store.add_account(account).await,
+            Err(Error::AccountAlreadyExists)
+        ));
+        assert_eq!(
+            store
+                .get_account("test@example.com".to_string())
+                .await
+                .unwrap()
+                .id,
+            Some(AccountId(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn debug_output_does_not_contain_stored_secrets() {
+        let store = MemoryStore::new();
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@example.com".to_string(),
+                password: "$argon2id$secret-hash".to_string(),
+                role: Role::User,
+                verified_at: None,
+            })
+            .await
+            .unwrap();
+
+        let debug = format!("{:?}", store);
+        assert!(!debug.contains("secret-hash"));
+        assert!(!debug.contains("test@example.com"));
+    }
+}
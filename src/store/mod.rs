@@ -0,0 +1,265 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use handle_errors::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::types::{
+    account::{Account, AccountDeletion, AccountId, AccountInfo, LoginSession, Role},
+    answer::{Answer, NewAnswer},
+    comment::{Comment, CommentTarget, NewComment},
+    export::{AccountExport, DataExport},
+    pagination::{Page, Pagination},
+    question::{
+        NewQuestion, Question, QuestionFilter, QuestionPatch, QuestionSort, SortDirection,
+        SortField,
+    },
+    revision::QuestionRevision,
+    search::SearchResult,
+    tag::Tag,
+    vote::VoteDirection,
+};
+
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+/// ルートハンドラに渡すストア。バックエンドの違いはトレイトオブジェクトで吸収する
+pub type DynStore = Arc<dyn Store>;
+
+/// 並び順に対応するORDER BY句と、カーソルの位置と比較する演算子。PostgreSQLとSQLiteで共通
+/// INFO: 列名は固定の候補から選ぶので、クエリに直接埋め込んでも問題ない
+fn question_order(sort: &QuestionSort) -> (String, &'static str) {
+    let column = match sort.field {
+        SortField::CreatedOn => "created_on",
+        SortField::AnswerCount => "answer_count",
+        SortField::LastActivity => "last_activity_on",
+        SortField::Title => "lower(title)",
+        SortField::Score => "score",
+    };
+    let (direction, operator) = match sort.direction {
+        SortDirection::Asc => ("ASC", ">"),
+        SortDirection::Desc => ("DESC", "<"),
+    };
+
+    (format!("{column} {direction}, id {direction}"), operator)
+}
+
+/// 質問・回答・アカウントの永続化を担うストレージ層のインターフェース
+///
+/// 本番ではPostgreSQL(`postgres::PostgresStore`)、小規模な環境ではSQLite(`sqlite::SqliteStore`)、
+/// テストやローカル開発ではインメモリ(`memory::MemoryStore`)の実装を利用する
+#[async_trait]
+pub trait Store: Debug + Send + Sync {
+    // Questions
+    /// 質問を`sort`の順に返す。`pagination.cursor`があればその位置の次から返す
+    /// (カーソルは`sort.supports_cursor()`の並び順でのみ指定される)
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        sort: &QuestionSort,
+        pagination: &Pagination,
+    ) -> Result<Page<Question>, Error>;
+    async fn get_question(&self, id: i32) -> Result<Question, Error>;
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error>;
+    /// 更新前の内容を`question_revisions`に記録してから更新する(`patch_question`も同じ)。
+    /// `account_id`は所有者で、所有者の質問でなければ更新しない。版には編集した`editor`を記録する
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error>;
+    /// `patch`に含まれる列だけを更新する。空のパッチでは何も更新せずに現在の質問を返す
+    async fn patch_question(
+        &self,
+        id: i32,
+        patch: QuestionPatch,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error>;
+    /// 質問に削除日時を記録する。削除された質問は一覧や取得の対象にならない
+    async fn delete_question(&self, id: i32, account_id: AccountId) -> Result<bool, Error>;
+    /// 削除された質問を元に戻す。削除されていない質問に対してはエラーを返す
+    async fn restore_question(&self, id: i32, account_id: AccountId) -> Result<Question, Error>;
+    /// `before`より前に削除された質問を、回答や過去の版とともに完全に削除して件数を返す
+    async fn purge_deleted_questions(&self, before: NaiveDateTime) -> Result<u64, Error>;
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+    /// 削除済みの質問も含めて所有者を返す。モデレーターが他人の質問を操作する際に使う
+    async fn get_question_owner(&self, question_id: i32) -> Result<AccountId, Error>;
+    /// 回答を採用する。別の回答が採用されていれば置き換える。
+    /// 回答が質問に属していない場合はエラーを返す
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error>;
+    /// 回答の採用を取り消す。その回答が採用されていない場合はエラーを返す
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error>;
+
+    /// 質問の過去の版を古い順に返す
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error>;
+    async fn get_question_revision(
+        &self,
+        question_id: i32,
+        revision: i32,
+    ) -> Result<QuestionRevision, Error>;
+
+    /// 質問と回答の本文を全文検索し、関連度の高い順に質問を返す
+    async fn search_questions(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<SearchResult>, Error>;
+
+    // Tags
+    /// 使われているタグを、付いている質問の数が多い順に返す
+    async fn get_tags(&self, limit: Option<u32>, offset: u32) -> Result<Vec<Tag>, Error>;
+
+    // Answers
+    async fn get_answers(&self, question_id: i32) -> Result<Vec<Answer>, Error>;
+    async fn get_answer(&self, id: i32) -> Result<Answer, Error>;
+    async fn add_answer(
+        &self,
+        question_id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error>;
+
+    async fn update_answer(
+        &self,
+        id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error>;
+    /// 回答を削除する。採用されていた回答なら質問の採用も取り消される
+    async fn delete_answer(&self, id: i32, account_id: AccountId) -> Result<bool, Error>;
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error>;
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<AccountId, Error>;
+
+    // Votes
+    /// 投票を登録・変更して、`score`を更新した質問を返す。`VoteDirection::None`は投票を取り消す
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Question, Error>;
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Answer, Error>;
+
+    // Comments
+    /// 投稿に付いたコメントを古い順に返す
+    async fn get_comments(&self, target: CommentTarget) -> Result<Vec<Comment>, Error>;
+    async fn add_comment(
+        &self,
+        target: CommentTarget,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error>;
+    async fn update_comment(
+        &self,
+        id: i32,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error>;
+    async fn delete_comment(&self, id: i32, account_id: AccountId) -> Result<bool, Error>;
+    async fn is_comment_owner(
+        &self,
+        comment_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+
+    // Accounts
+    async fn add_account(&self, account: Account) -> Result<bool, Error>;
+    async fn get_account(&self, email: String) -> Result<Account, Error>;
+    async fn get_account_info(&self, id: AccountId) -> Result<AccountInfo, Error>;
+    /// id順に全てのアカウントを返す
+    async fn get_accounts(&self) -> Result<Vec<AccountInfo>, Error>;
+    async fn update_account_role(&self, id: AccountId, role: Role) -> Result<AccountInfo, Error>;
+    async fn update_account_password(&self, id: AccountId, password: String)
+        -> Result<bool, Error>;
+    /// アカウントを削除し、投稿を`mode`に従って匿名化または削除する。
+    /// 票とトークンは削除し、ログインセッションは全て失効させる。アカウントが存在しなければ`false`を返す
+    async fn delete_account(&self, id: AccountId, mode: AccountDeletion) -> Result<bool, Error>;
+
+    // Password reset tokens
+    async fn add_password_reset_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error>;
+    /// 未使用かつ有効期限内のトークンであれば使用済みにして、アカウントのidを返す。
+    /// 同じトークンで同時に呼ばれても、idを返すのは1回だけになる
+    async fn use_password_reset_token(&self, token_hash: &str) -> Result<Option<AccountId>, Error>;
+
+    // Email verification tokens
+    async fn add_email_verification_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error>;
+    /// 未使用かつ有効期限内のトークンであれば使用済みにし、アカウントを確認済みにしてidを返す
+    async fn verify_email(&self, token_hash: &str) -> Result<Option<AccountId>, Error>;
+
+    // Login sessions
+    async fn add_login_session(
+        &self,
+        account_id: AccountId,
+        refresh_token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<LoginSession, Error>;
+    async fn get_login_session(&self, id: i32) -> Result<LoginSession, Error>;
+    /// 失効しておらず、現在のハッシュが`old_hash`の場合だけリフレッシュトークンを置き換える。
+    /// 同じトークンで同時にリフレッシュされても、置き換えられるのは1回だけになる
+    async fn rotate_refresh_token(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error>;
+    async fn revoke_login_session(&self, id: i32) -> Result<bool, Error>;
+    /// アカウントの全てのログインセッションを失効させ、失効させた数を返す
+    async fn revoke_account_sessions(&self, account_id: AccountId) -> Result<u64, Error>;
+
+    // Data exports
+    /// アカウントに紐づく全てのデータを集める
+    async fn get_account_export(&self, account_id: AccountId) -> Result<AccountExport, Error>;
+    async fn add_data_export(&self, account_id: AccountId) -> Result<DataExport, Error>;
+    /// アカウントの最も新しい書き出しを返す
+    async fn get_latest_data_export(&self, account_id: AccountId) -> Result<DataExport, Error>;
+    /// 作成中の書き出しに内容を保存し、`token_hash`のトークンで`expires_at`までダウンロードできるようにする
+    async fn complete_data_export(
+        &self,
+        id: i32,
+        archive: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error>;
+    async fn fail_data_export(&self, id: i32) -> Result<bool, Error>;
+    /// 期限内の書き出しであれば内容を返す
+    async fn get_data_export_archive(&self, token_hash: &str) -> Result<Option<String>, Error>;
+    /// アカウントの最も新しい書き出しが完成していて期限内であれば内容を返す
+    async fn get_latest_data_export_archive(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Option<String>, Error>;
+    /// `before`より前に期限を過ぎた書き出しと失敗した書き出しを削除し、削除した数を返す
+    async fn purge_expired_data_exports(&self, before: NaiveDateTime) -> Result<u64, Error>;
+}
@@ -0,0 +1,1483 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use handle_errors::Error;
+use sqlx::{
+    postgres::{PgPool, PgPoolOptions, PgRow, Postgres},
+    QueryBuilder, Row, Transaction,
+};
+
+use crate::store::{question_order, Store};
+use crate::types::{
+    account::{Account, AccountDeletion, AccountId, AccountInfo, LoginSession, Role},
+    answer::{Answer, AnswerId, NewAnswer},
+    comment::{Comment, CommentId, CommentTarget, NewComment},
+    export::{AccountExport, DataExport, ExportStatus, ExportedVote},
+    pagination::{Cursor, Page, Pagination},
+    question::{
+        NewQuestion, Question, QuestionFilter, QuestionId, QuestionPatch, QuestionSort, TagMode,
+    },
+    revision::QuestionRevision,
+    search::SearchResult,
+    tag::Tag,
+    vote::VoteDirection,
+};
+
+/// PostgreSQLをバックエンドとするストア
+#[derive(Clone, Debug)]
+pub struct PostgresStore {
+    pub conn: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn new(db_url: &str) -> Result<Self, Error> {
+        let db_pool = match PgPoolOptions::new()
+            .max_connections(5)
+            .connect(db_url)
+            .await
+        {
+            Ok(pool) => pool,
+            Err(e) => {
+                tracing::event!(
+                    tracing::Level::ERROR,
+                    "Couldn't establish DB connection!: {}",
+                    e
+                );
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+
+        Ok(PostgresStore { conn: db_pool })
+    }
+}
+
+fn question_from_row(row: PgRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+        score: row.get("score"),
+        accepted_answer_id: row
+            .get::<Option<i32>, _>("accepted_answer_id")
+            .map(AnswerId),
+    }
+}
+
+fn answer_from_row(row: PgRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        score: row.get("score"),
+    }
+}
+
+/// 質問の現在の内容を次の版番号で`question_revisions`に記録する。版を書き換えたアカウントは`editor`とする。
+/// `account_id`が所有者でなければ何も記録されず、続く更新も失敗する
+async fn record_revision(
+    tx: &mut Transaction<'_, Postgres>,
+    question_id: i32,
+    account_id: &AccountId,
+    editor: &AccountId,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO question_revisions (question_id, revision, account_id, title, content, tags)
+        SELECT id,
+            coalesce((SELECT max(revision) FROM question_revisions WHERE question_id = $1), 0) + 1,
+            $3, title, content, tags
+        FROM questions WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL",
+    )
+    .bind(question_id)
+    .bind(account_id.0)
+    .bind(editor.0)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+fn comment_from_row(row: PgRow) -> Comment {
+    Comment {
+        id: CommentId(row.get("id")),
+        content: row.get("content"),
+        question_id: row.get::<Option<i32>, _>("question_id").map(QuestionId),
+        answer_id: row.get::<Option<i32>, _>("answer_id").map(AnswerId),
+    }
+}
+
+fn account_info_from_row(row: PgRow) -> AccountInfo {
+    AccountInfo {
+        id: AccountId(row.get("id")),
+        email: row.get("email"),
+        role: Role::from_db(row.get("role")),
+        verified_at: row.get("verified_at"),
+    }
+}
+
+fn login_session_from_row(row: PgRow) -> LoginSession {
+    LoginSession {
+        id: row.get("id"),
+        account_id: AccountId(row.get("account_id")),
+        refresh_token_hash: row.get("refresh_token_hash"),
+        expires_at: row.get("expires_at"),
+        revoked_at: row.get("revoked_at"),
+    }
+}
+
+fn data_export_from_row(row: PgRow) -> DataExport {
+    let expires_at = row.get("expires_at");
+    DataExport {
+        id: row.get("id"),
+        status: ExportStatus::from_db(row.get("status"), expires_at),
+        created_on: row.get("created_on"),
+        expires_at,
+    }
+}
+
+fn exported_vote_from_row(row: PgRow) -> ExportedVote {
+    ExportedVote {
+        question_id: row.get::<Option<i32>, _>("question_id").map(QuestionId),
+        answer_id: row.get::<Option<i32>, _>("answer_id").map(AnswerId),
+        value: row.get("value"),
+        created_on: row.get("created_on"),
+    }
+}
+
+fn revision_from_row(row: PgRow) -> QuestionRevision {
+    QuestionRevision {
+        revision: row.get("revision"),
+        question_id: QuestionId(row.get("question_id")),
+        account_id: AccountId(row.get("account_id")),
+        created_on: row.get("created_on"),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        sort: &QuestionSort,
+        pagination: &Pagination,
+    ) -> Result<Page<Question>, Error> {
+        // INFO: タグ未指定の場合は絞り込まない。any/allはそれぞれ && / @> 演算子に対応。
+        // 次のページの有無を判定するために1件多く取得する
+        let (order_by, operator) = question_order(sort);
+        let query = format!(
+            "SELECT * FROM questions
+            WHERE deleted_at IS NULL
+                AND (cardinality($1::text[]) = 0
+                OR ($2 AND tags @> $1::text[])
+                OR (NOT $2 AND tags && $1::text[]))
+                AND ($3::timestamp IS NULL OR (created_on, id) {operator} ($3, $4))
+                AND (NOT $7 OR answer_count = 0)
+                AND (NOT $8 OR accepted_answer_id IS NULL)
+            ORDER BY {order_by}
+            LIMIT $5 OFFSET $6;"
+        );
+        match sqlx::query(&query)
+            .bind(&filter.tags)
+            .bind(filter.tag_mode == TagMode::All)
+            .bind(pagination.cursor.map(|c| c.created_on))
+            .bind(pagination.cursor.map(|c| c.id))
+            .bind(pagination.limit.map(|i| i as i64 + 1))
+            .bind(pagination.offset as i32)
+            .bind(filter.unanswered)
+            .bind(filter.unaccepted)
+            .map(|row: PgRow| {
+                let cursor = Cursor {
+                    created_on: row.get("created_on"),
+                    id: row.get("id"),
+                };
+                (question_from_row(row), cursor)
+            })
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(rows) => Ok(Page::from_rows(rows, pagination.limit)),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question(&self, id: i32) -> Result<Question, Error> {
+        match sqlx::query("SELECT * FROM questions WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .map(question_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "INSERT INTO questions (title, content, tags, account_id) VALUES ($1, $2, $3, $4) 
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(new_question.title)
+        .bind(new_question.content)
+        .bind(new_question.tags)
+        .bind(account_id.0)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        let res: Result<Question, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            record_revision(&mut tx, id, &account_id, &editor).await?;
+            let question = sqlx::query(
+                "UPDATE questions SET title = $1, content = $2, tags = $3
+                WHERE id = $4 AND account_id = $5 AND deleted_at IS NULL
+                RETURNING id, title, content, tags, score, accepted_answer_id",
+            )
+            .bind(question.title)
+            .bind(question.content)
+            .bind(question.tags)
+            .bind(id)
+            .bind(account_id.0)
+            .map(question_from_row)
+            .fetch_one(&mut tx)
+            .await?;
+            tx.commit().await?;
+            Ok(question)
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn patch_question(
+        &self,
+        id: i32,
+        patch: QuestionPatch,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        if patch.is_empty() {
+            return self.get_question(id).await;
+        }
+
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE questions SET ");
+        {
+            let mut columns = query.separated(", ");
+            if let Some(title) = patch.title {
+                columns.push("title = ").push_bind_unseparated(title);
+            }
+            if let Some(content) = patch.content {
+                columns.push("content = ").push_bind_unseparated(content);
+            }
+            if let Some(tags) = patch.tags {
+                columns.push("tags = ").push_bind_unseparated(tags);
+            }
+        }
+        query
+            .push(" WHERE id = ")
+            .push_bind(id)
+            .push(" AND account_id = ")
+            .push_bind(account_id.0)
+            .push(" AND deleted_at IS NULL")
+            .push(" RETURNING id, title, content, tags, score, accepted_answer_id");
+
+        let res: Result<Question, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            record_revision(&mut tx, id, &account_id, &editor).await?;
+            let question = query
+                .build()
+                .map(question_from_row)
+                .fetch_one(&mut tx)
+                .await?;
+            tx.commit().await?;
+            Ok(question)
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn restore_question(&self, id: i32, account_id: AccountId) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions SET deleted_at = NULL
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NOT NULL
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(id)
+        .bind(account_id.0)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn purge_deleted_questions(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        // INFO: 回答は外部キーで質問を参照しているので先に削除する。過去の版とコメントはCASCADEで削除される
+        let res: Result<u64, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            sqlx::query(
+                "DELETE FROM answers WHERE question_id IN
+                (SELECT id FROM questions WHERE deleted_at < $1)",
+            )
+            .bind(before)
+            .execute(&mut tx)
+            .await?;
+            let purged = sqlx::query("DELETE FROM questions WHERE deleted_at < $1")
+                .bind(before)
+                .execute(&mut tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+            Ok(purged)
+        }
+        .await;
+
+        match res {
+            Ok(purged) => Ok(purged),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error> {
+        match sqlx::query(
+            "SELECT * FROM question_revisions WHERE question_id = $1 ORDER BY revision",
+        )
+        .bind(question_id)
+        .map(revision_from_row)
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(revisions) => Ok(revisions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_revision(
+        &self,
+        question_id: i32,
+        revision: i32,
+    ) -> Result<QuestionRevision, Error> {
+        match sqlx::query(
+            "SELECT * FROM question_revisions WHERE question_id = $1 AND revision = $2",
+        )
+        .bind(question_id)
+        .bind(revision)
+        .map(revision_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(revision) => Ok(revision),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_question(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        // INFO: 回答が外部キーで参照しているので行は消さず、保持期間を過ぎてから完全に削除する
+        match sqlx::query(
+            "UPDATE questions SET deleted_at = NOW()
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(account_id.0)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        // INFO: 質問と回答それぞれの一致を集め、質問ごとにスコアを合算する。
+        // 抜粋は最も関連度の高い一致から作る
+        match sqlx::query(
+            "WITH query AS (SELECT websearch_to_tsquery('english', $1) AS tsq),
+            matches AS (
+                SELECT q.id AS question_id,
+                    ts_rank(q.search_vector, query.tsq) AS rank,
+                    ts_headline('english', q.content, query.tsq,
+                        'StartSel=<b>, StopSel=</b>, MaxWords=35, MinWords=15') AS snippet
+                FROM questions q, query
+                WHERE q.search_vector @@ query.tsq AND q.deleted_at IS NULL
+                UNION ALL
+                SELECT a.question_id,
+                    ts_rank(a.search_vector, query.tsq) * 0.5 AS rank,
+                    ts_headline('english', a.content, query.tsq,
+                        'StartSel=<b>, StopSel=</b>, MaxWords=35, MinWords=15') AS snippet
+                FROM answers a, query
+                WHERE a.search_vector @@ query.tsq
+            ),
+            ranked AS (
+                SELECT DISTINCT ON (question_id) question_id, snippet,
+                    SUM(rank) OVER (PARTITION BY question_id) AS relevance
+                FROM matches
+                ORDER BY question_id, rank DESC
+            )
+            SELECT q.id, q.title, q.content, q.tags, q.score, q.accepted_answer_id,
+                ranked.relevance, ranked.snippet
+            FROM ranked JOIN questions q ON q.id = ranked.question_id
+            WHERE q.deleted_at IS NULL
+            ORDER BY ranked.relevance DESC, q.id
+            LIMIT $2 OFFSET $3",
+        )
+        .bind(query)
+        .bind(limit.map(|i| i as i32))
+        .bind(offset as i32)
+        .map(|row: PgRow| SearchResult {
+            relevance: row.get("relevance"),
+            snippet: row.get("snippet"),
+            question: question_from_row(row),
+        })
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(results) => Ok(results),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_tags(&self, limit: Option<u32>, offset: u32) -> Result<Vec<Tag>, Error> {
+        match sqlx::query(
+            "SELECT tag AS name, COUNT(DISTINCT questions.id) AS count
+            FROM questions, unnest(tags) AS tag
+            WHERE deleted_at IS NULL
+            GROUP BY tag
+            ORDER BY count DESC, name
+            LIMIT $1 OFFSET $2",
+        )
+        .bind(limit.map(|i| i as i32))
+        .bind(offset as i32)
+        .map(|row: PgRow| Tag {
+            name: row.get("name"),
+            count: row.get("count"),
+        })
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(tags) => Ok(tags),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answers(&self, question_id: i32) -> Result<Vec<Answer>, Error> {
+        match sqlx::query("SELECT * FROM answers WHERE question_id = $1 ORDER BY id")
+            .bind(question_id)
+            .map(answer_from_row)
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answer(&self, id: i32) -> Result<Answer, Error> {
+        match sqlx::query("SELECT * FROM answers WHERE id = $1")
+            .bind(id)
+            .map(answer_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        question_id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers (content, question_id, account_id) VALUES ($1, $2, $3)
+            RETURNING id, content, question_id, score",
+        )
+        .bind(new_answer.content)
+        .bind(question_id)
+        .bind(account_id.0)
+        .map(answer_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers SET content = $1 WHERE id = $2 AND account_id = $3
+            RETURNING id, content, question_id, score",
+        )
+        .bind(new_answer.content)
+        .bind(id)
+        .bind(account_id.0)
+        .map(answer_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_answer(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        // INFO: 回答数はトリガーで、採用・票・コメントは外部キーの制約で更新される
+        match sqlx::query("DELETE FROM answers WHERE id = $1 AND account_id = $2")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_comments(&self, target: CommentTarget) -> Result<Vec<Comment>, Error> {
+        // INFO: 対象でない側の列にはNULLを渡すので、どちらか一方の条件だけが一致する
+        match sqlx::query(
+            "SELECT * FROM comments WHERE question_id = $1 OR answer_id = $2 ORDER BY id",
+        )
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .map(comment_from_row)
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(comments) => Ok(comments),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_comment(
+        &self,
+        target: CommentTarget,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        match sqlx::query(
+            "INSERT INTO comments (content, question_id, answer_id, account_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, content, question_id, answer_id",
+        )
+        .bind(new_comment.content)
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(account_id.0)
+        .map(comment_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(comment) => Ok(comment),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_comment(
+        &self,
+        id: i32,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        match sqlx::query(
+            "UPDATE comments SET content = $1 WHERE id = $2 AND account_id = $3
+            RETURNING id, content, question_id, answer_id",
+        )
+        .bind(new_comment.content)
+        .bind(id)
+        .bind(account_id.0)
+        .map(comment_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(comment) => Ok(comment),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_comment(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM comments WHERE id = $1 AND account_id = $2")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_comment_owner(
+        &self,
+        comment_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query("SELECT id FROM comments WHERE id = $1 AND account_id = $2")
+            .bind(comment_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(comment) => Ok(comment.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query("INSERT INTO accounts (email, password) VALUES ($1, $2)")
+            .bind(account.email)
+            .bind(account.password)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(
+                    tracing::Level::ERROR,
+                    code = e
+                        .as_database_error()
+                        .unwrap()
+                        .code()
+                        .unwrap()
+                        .parse::<i32>()
+                        .unwrap(),
+                    db_message = e.as_database_error().unwrap().message(),
+                    constraint = e.as_database_error().unwrap().constraint().unwrap(),
+                );
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE email = $1")
+            .bind(email)
+            .map(|row: PgRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: Role::from_db(row.get("role")),
+                verified_at: row.get("verified_at"),
+            })
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account_info(&self, id: AccountId) -> Result<AccountInfo, Error> {
+        match sqlx::query("SELECT id, email, role, verified_at FROM accounts WHERE id = $1")
+            .bind(id.0)
+            .map(account_info_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountInfo>, Error> {
+        match sqlx::query("SELECT id, email, role, verified_at FROM accounts ORDER BY id")
+            .map(account_info_from_row)
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_account_role(&self, id: AccountId, role: Role) -> Result<AccountInfo, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET role = $1 WHERE id = $2 RETURNING id, email, role, verified_at",
+        )
+        .bind(role.as_str())
+        .bind(id.0)
+        .map(account_info_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_account_password(
+        &self,
+        id: AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        match sqlx::query("UPDATE accounts SET password = $1 WHERE id = $2")
+            .bind(password)
+            .bind(id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_account(&self, id: AccountId, mode: AccountDeletion) -> Result<bool, Error> {
+        let res: Result<bool, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            match mode {
+                AccountDeletion::Anonymize => {
+                    // INFO: テーブル名は固定の候補から選ぶので、クエリに直接埋め込んでも問題ない
+                    for table in ["questions", "answers", "comments", "question_revisions"] {
+                        sqlx::query(&format!(
+                            "UPDATE {} SET account_id = $1 WHERE account_id = $2",
+                            table
+                        ))
+                        .bind(AccountId::DELETED.0)
+                        .bind(id.0)
+                        .execute(&mut tx)
+                        .await?;
+                    }
+                }
+                AccountDeletion::Cascade => {
+                    // INFO: 質問は他の回答が参照しているので、`delete_question`と同じく削除済みにするだけにする
+                    for statement in [
+                        "UPDATE questions SET deleted_at = NOW()
+                        WHERE account_id = $1 AND deleted_at IS NULL",
+                        "DELETE FROM comments WHERE account_id = $1",
+                        "DELETE FROM answers WHERE account_id = $1",
+                    ] {
+                        sqlx::query(statement).bind(id.0).execute(&mut tx).await?;
+                    }
+                }
+            }
+
+            // INFO: 票の合計はトリガーで差し引かれる
+            for statement in [
+                "DELETE FROM question_votes WHERE account_id = $1",
+                "DELETE FROM answer_votes WHERE account_id = $1",
+                "DELETE FROM password_reset_tokens WHERE account_id = $1",
+                "DELETE FROM email_verification_tokens WHERE account_id = $1",
+                "DELETE FROM data_exports WHERE account_id = $1",
+                "UPDATE login_sessions SET revoked_at = NOW()
+                WHERE account_id = $1 AND revoked_at IS NULL",
+            ] {
+                sqlx::query(statement).bind(id.0).execute(&mut tx).await?;
+            }
+
+            let deleted = sqlx::query("DELETE FROM accounts WHERE id = $1")
+                .bind(id.0)
+                .execute(&mut tx)
+                .await?
+                .rows_affected()
+                == 1;
+            tx.commit().await?;
+            Ok(deleted)
+        }
+        .await;
+
+        match res {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_password_reset_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO password_reset_tokens (account_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        )
+        .bind(account_id.0)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn use_password_reset_token(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        match sqlx::query(
+            "UPDATE password_reset_tokens SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+            RETURNING account_id",
+        )
+        .bind(token_hash)
+        // INFO: expires_atはUTCで保存しているので、サーバのタイムゾーンに依らないようUTCの現在時刻を渡す
+        .bind(Utc::now().naive_utc())
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| AccountId(row.get("account_id")))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_email_verification_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO email_verification_tokens (account_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)",
+        )
+        .bind(account_id.0)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn verify_email(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        let res: Result<Option<AccountId>, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            let account_id = sqlx::query(
+                "UPDATE email_verification_tokens SET used_at = $2
+                WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+                RETURNING account_id",
+            )
+            .bind(token_hash)
+            .bind(Utc::now().naive_utc())
+            .map(|row: PgRow| AccountId(row.get("account_id")))
+            .fetch_optional(&mut tx)
+            .await?;
+
+            if let Some(account_id) = &account_id {
+                sqlx::query(
+                    "UPDATE accounts SET verified_at = $2 WHERE id = $1 AND verified_at IS NULL",
+                )
+                .bind(account_id.0)
+                .bind(Utc::now().naive_utc())
+                .execute(&mut tx)
+                .await?;
+            }
+            tx.commit().await?;
+            Ok(account_id)
+        }
+        .await;
+
+        match res {
+            Ok(account_id) => Ok(account_id),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_login_session(
+        &self,
+        account_id: AccountId,
+        refresh_token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<LoginSession, Error> {
+        match sqlx::query(
+            "INSERT INTO login_sessions (account_id, refresh_token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id, account_id, refresh_token_hash, expires_at, revoked_at",
+        )
+        .bind(account_id.0)
+        .bind(refresh_token_hash)
+        .bind(expires_at)
+        .map(login_session_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(session) => Ok(session),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_login_session(&self, id: i32) -> Result<LoginSession, Error> {
+        match sqlx::query("SELECT * FROM login_sessions WHERE id = $1")
+            .bind(id)
+            .map(login_session_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(session) => Ok(session),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET refresh_token_hash = $1, expires_at = $2
+            WHERE id = $3 AND refresh_token_hash = $4 AND revoked_at IS NULL",
+        )
+        .bind(new_hash)
+        .bind(expires_at)
+        .bind(id)
+        .bind(old_hash)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_login_session(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_account_sessions(&self, account_id: AccountId) -> Result<u64, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET revoked_at = NOW() WHERE account_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(account_id.0)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account_export(&self, account_id: AccountId) -> Result<AccountExport, Error> {
+        let res: Result<AccountExport, sqlx::Error> = async {
+            let account =
+                sqlx::query("SELECT id, email, role, verified_at FROM accounts WHERE id = $1")
+                    .bind(account_id.0)
+                    .map(account_info_from_row)
+                    .fetch_one(&self.conn)
+                    .await?;
+            let questions =
+                sqlx::query("SELECT * FROM questions WHERE account_id = $1 ORDER BY id")
+                    .bind(account_id.0)
+                    .map(question_from_row)
+                    .fetch_all(&self.conn)
+                    .await?;
+            let answers = sqlx::query("SELECT * FROM answers WHERE account_id = $1 ORDER BY id")
+                .bind(account_id.0)
+                .map(answer_from_row)
+                .fetch_all(&self.conn)
+                .await?;
+            let comments = sqlx::query("SELECT * FROM comments WHERE account_id = $1 ORDER BY id")
+                .bind(account_id.0)
+                .map(comment_from_row)
+                .fetch_all(&self.conn)
+                .await?;
+            let votes = sqlx::query(
+                "SELECT question_id, NULL::integer AS answer_id, value, created_on
+                FROM question_votes WHERE account_id = $1
+                UNION ALL
+                SELECT NULL::integer, answer_id, value, created_on
+                FROM answer_votes WHERE account_id = $1
+                ORDER BY created_on",
+            )
+            .bind(account_id.0)
+            .map(exported_vote_from_row)
+            .fetch_all(&self.conn)
+            .await?;
+            let revisions = sqlx::query(
+                "SELECT * FROM question_revisions WHERE account_id = $1
+                ORDER BY question_id, revision",
+            )
+            .bind(account_id.0)
+            .map(revision_from_row)
+            .fetch_all(&self.conn)
+            .await?;
+
+            Ok(AccountExport {
+                exported_on: Utc::now().naive_utc(),
+                account,
+                questions,
+                answers,
+                comments,
+                votes,
+                revisions,
+            })
+        }
+        .await;
+
+        match res {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        match sqlx::query(
+            "INSERT INTO data_exports (account_id) VALUES ($1)
+            RETURNING id, status, created_on, expires_at",
+        )
+        .bind(account_id.0)
+        .map(data_export_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_latest_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        match sqlx::query(
+            "SELECT id, status, created_on, expires_at FROM data_exports
+            WHERE account_id = $1 ORDER BY id DESC LIMIT 1",
+        )
+        .bind(account_id.0)
+        .map(data_export_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn complete_data_export(
+        &self,
+        id: i32,
+        archive: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE data_exports SET status = 'ready', archive = $2, token_hash = $3, expires_at = $4
+            WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(id)
+        .bind(archive)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn fail_data_export(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE data_exports SET status = 'failed' WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(id)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_data_export_archive(&self, token_hash: &str) -> Result<Option<String>, Error> {
+        match sqlx::query(
+            "SELECT archive FROM data_exports
+            WHERE token_hash = $1 AND status = 'ready' AND expires_at > $2",
+        )
+        .bind(token_hash)
+        .bind(Utc::now().naive_utc())
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| row.get("archive"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_latest_data_export_archive(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Option<String>, Error> {
+        match sqlx::query(
+            "SELECT archive FROM data_exports
+            WHERE id = (SELECT MAX(id) FROM data_exports WHERE account_id = $1)
+            AND status = 'ready' AND expires_at > $2",
+        )
+        .bind(account_id.0)
+        .bind(Utc::now().naive_utc())
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| row.get("archive"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn purge_expired_data_exports(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        match sqlx::query(
+            "DELETE FROM data_exports
+            WHERE expires_at < $1 OR (status = 'failed' AND created_on < $1)",
+        )
+        .bind(before)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query("SELECT * FROM questions WHERE id = $1 AND account_id = $2")
+            .bind(question_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(question) => Ok(question.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_owner(&self, question_id: i32) -> Result<AccountId, Error> {
+        match sqlx::query("SELECT account_id FROM questions WHERE id = $1")
+            .bind(question_id)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(row) => Ok(AccountId(row.get("account_id"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        // INFO: 回答が質問に属していなければ更新されず、RowNotFoundになる
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = $2
+            WHERE id = $1 AND deleted_at IS NULL
+                AND EXISTS (SELECT 1 FROM answers WHERE id = $2 AND question_id = $1)
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = NULL
+            WHERE id = $1 AND accepted_answer_id = $2 AND deleted_at IS NULL
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error> {
+        match sqlx::query("SELECT id FROM answers WHERE id = $1 AND account_id = $2")
+            .bind(answer_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<AccountId, Error> {
+        match sqlx::query("SELECT account_id FROM answers WHERE id = $1")
+            .bind(answer_id)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(row) => Ok(AccountId(row.get("account_id"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Question, Error> {
+        // INFO: scoreの更新はトリガーで行う
+        let res = match vote {
+            VoteDirection::None => {
+                sqlx::query("DELETE FROM question_votes WHERE question_id = $1 AND account_id = $2")
+                    .bind(question_id)
+                    .bind(account_id.0)
+                    .execute(&self.conn)
+                    .await
+            }
+            _ => sqlx::query(
+                "INSERT INTO question_votes (question_id, account_id, value) VALUES ($1, $2, $3)
+                ON CONFLICT (question_id, account_id) DO UPDATE SET value = excluded.value",
+            )
+            .bind(question_id)
+            .bind(account_id.0)
+            .bind(vote.value())
+            .execute(&self.conn)
+            .await,
+        };
+        if let Err(e) = res {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        self.get_question(question_id).await
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Answer, Error> {
+        let res =
+            match vote {
+                VoteDirection::None => {
+                    sqlx::query("DELETE FROM answer_votes WHERE answer_id = $1 AND account_id = $2")
+                        .bind(answer_id)
+                        .bind(account_id.0)
+                        .execute(&self.conn)
+                        .await
+                }
+                _ => sqlx::query(
+                    "INSERT INTO answer_votes (answer_id, account_id, value) VALUES ($1, $2, $3)
+                ON CONFLICT (answer_id, account_id) DO UPDATE SET value = excluded.value",
+                )
+                .bind(answer_id)
+                .bind(account_id.0)
+                .bind(vote.value())
+                .execute(&self.conn)
+                .await,
+            };
+        if let Err(e) = res {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        self.get_answer(answer_id).await
+    }
+}
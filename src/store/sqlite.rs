@@ -0,0 +1,2428 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use handle_errors::Error;
+use sqlx::{
+    sqlite::{Sqlite, SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow},
+    QueryBuilder, Row, Transaction,
+};
+use std::str::FromStr;
+
+use crate::store::{question_order, Store};
+use crate::types::{
+    account::{Account, AccountDeletion, AccountId, AccountInfo, LoginSession, Role},
+    answer::{Answer, AnswerId, NewAnswer},
+    comment::{Comment, CommentId, CommentTarget, NewComment},
+    export::{AccountExport, DataExport, ExportStatus, ExportedVote},
+    pagination::{Cursor, Page, Pagination},
+    question::{
+        NewQuestion, Question, QuestionFilter, QuestionId, QuestionPatch, QuestionSort, TagMode,
+    },
+    revision::QuestionRevision,
+    search::{search_terms, SearchResult},
+    tag::Tag,
+    vote::VoteDirection,
+};
+
+/// SQLITE_CONSTRAINT_UNIQUE / SQLITE_CONSTRAINT_PRIMARYKEY
+const UNIQUE_VIOLATIONS: [&str; 2] = ["2067", "1555"];
+
+/// SQLiteをバックエンドとするストア。Postgresコンテナを立てない小規模な環境向け
+#[derive(Clone, Debug)]
+pub struct SqliteStore {
+    pub conn: SqlitePool,
+}
+
+impl SqliteStore {
+    pub async fn new(db_url: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(db_url)
+            .map_err(Error::DatabaseQueryError)?
+            .create_if_missing(true);
+
+        // INFO: インメモリDBは接続ごとに別のDBになるので、接続を1本に固定して使い回す
+        let pool_options = if db_url.contains(":memory:") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(5)
+        };
+
+        let db_pool = match pool_options.connect_with(options).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                tracing::event!(
+                    tracing::Level::ERROR,
+                    "Couldn't establish DB connection!: {}",
+                    e
+                );
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+
+        Ok(SqliteStore { conn: db_pool })
+    }
+}
+
+/// PostgreSQLの`TEXT[]`の代わりにJSON配列の文字列としてタグを保存する
+fn encode_tags(tags: Option<Vec<String>>) -> Option<String> {
+    tags.map(|tags| serde_json::to_string(&tags).expect("tags are always serializable"))
+}
+
+fn decode_tags(tags: Option<String>) -> Option<Vec<String>> {
+    tags.and_then(|tags| serde_json::from_str(&tags).ok())
+}
+
+/// 日時を比較の引数にする場合は、CURRENT_TIMESTAMPで保存された文字列と同じ書式にする
+fn encode_timestamp(timestamp: NaiveDateTime) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S%.f").to_string()
+}
+
+fn question_from_row(row: SqliteRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: decode_tags(row.get("tags")),
+        score: row.get("score"),
+        accepted_answer_id: row
+            .get::<Option<i32>, _>("accepted_answer_id")
+            .map(AnswerId),
+    }
+}
+
+fn comment_from_row(row: SqliteRow) -> Comment {
+    Comment {
+        id: CommentId(row.get("id")),
+        content: row.get("content"),
+        question_id: row.get::<Option<i32>, _>("question_id").map(QuestionId),
+        answer_id: row.get::<Option<i32>, _>("answer_id").map(AnswerId),
+    }
+}
+
+fn account_info_from_row(row: SqliteRow) -> AccountInfo {
+    AccountInfo {
+        id: AccountId(row.get("id")),
+        email: row.get("email"),
+        role: Role::from_db(row.get("role")),
+        verified_at: row.get("verified_at"),
+    }
+}
+
+fn login_session_from_row(row: SqliteRow) -> LoginSession {
+    LoginSession {
+        id: row.get("id"),
+        account_id: AccountId(row.get("account_id")),
+        refresh_token_hash: row.get("refresh_token_hash"),
+        expires_at: row.get("expires_at"),
+        revoked_at: row.get("revoked_at"),
+    }
+}
+
+fn data_export_from_row(row: SqliteRow) -> DataExport {
+    let expires_at = row.get("expires_at");
+    DataExport {
+        id: row.get("id"),
+        status: ExportStatus::from_db(row.get("status"), expires_at),
+        created_on: row.get("created_on"),
+        expires_at,
+    }
+}
+
+fn exported_vote_from_row(row: SqliteRow) -> ExportedVote {
+    ExportedVote {
+        question_id: row.get::<Option<i32>, _>("question_id").map(QuestionId),
+        answer_id: row.get::<Option<i32>, _>("answer_id").map(AnswerId),
+        value: row.get("value"),
+        created_on: row.get("created_on"),
+    }
+}
+
+fn revision_from_row(row: SqliteRow) -> QuestionRevision {
+    QuestionRevision {
+        revision: row.get("revision"),
+        question_id: QuestionId(row.get("question_id")),
+        account_id: AccountId(row.get("account_id")),
+        created_on: row.get("created_on"),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: decode_tags(row.get("tags")),
+    }
+}
+
+/// 質問の現在の内容を次の版番号で`question_revisions`に記録する。版を書き換えたアカウントは`editor`とする。
+/// `account_id`が所有者でなければ何も記録されず、続く更新も失敗する
+async fn record_revision(
+    tx: &mut Transaction<'_, Sqlite>,
+    question_id: i32,
+    account_id: &AccountId,
+    editor: &AccountId,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO question_revisions (question_id, revision, account_id, title, content, tags)
+        SELECT id,
+            coalesce((SELECT max(revision) FROM question_revisions WHERE question_id = ?1), 0) + 1,
+            ?3, title, content, tags
+        FROM questions WHERE id = ?1 AND account_id = ?2 AND deleted_at IS NULL",
+    )
+    .bind(question_id)
+    .bind(account_id.0)
+    .bind(editor.0)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+fn answer_from_row(row: SqliteRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        score: row.get("score"),
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        sort: &QuestionSort,
+        pagination: &Pagination,
+    ) -> Result<Page<Question>, Error> {
+        // INFO: タグはJSON配列なのでjson_each()で展開して比較する。
+        // SQLiteではLIMIT -1 が無制限を表す
+        let (order_by, operator) = question_order(sort);
+        let query = format!(
+            "SELECT * FROM questions
+            WHERE deleted_at IS NULL
+                AND (?1 = 0
+                OR (?2 AND NOT EXISTS (
+                    SELECT 1 FROM json_each(?3) AS wanted
+                    WHERE wanted.value NOT IN (SELECT value FROM json_each(questions.tags))
+                ))
+                OR (NOT ?2 AND EXISTS (
+                    SELECT 1 FROM json_each(questions.tags) AS tag
+                    JOIN json_each(?3) AS wanted ON wanted.value = tag.value
+                )))
+                AND (?4 IS NULL OR (created_on, id) {operator} (?4, ?5))
+                AND (NOT ?8 OR answer_count = 0)
+                AND (NOT ?9 OR accepted_answer_id IS NULL)
+            ORDER BY {order_by} LIMIT ?6 OFFSET ?7"
+        );
+        match sqlx::query(&query)
+            .bind(filter.tags.len() as i64)
+            .bind(filter.tag_mode == TagMode::All)
+            .bind(encode_tags(Some(filter.tags.clone())))
+            .bind(pagination.cursor.map(|c| encode_timestamp(c.created_on)))
+            .bind(pagination.cursor.map(|c| c.id))
+            .bind(pagination.limit.map(|i| i64::from(i) + 1).unwrap_or(-1))
+            .bind(pagination.offset as i64)
+            .bind(filter.unanswered)
+            .bind(filter.unaccepted)
+            .map(|row: SqliteRow| {
+                let cursor = Cursor {
+                    created_on: row.get("created_on"),
+                    id: row.get("id"),
+                };
+                (question_from_row(row), cursor)
+            })
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(rows) => Ok(Page::from_rows(rows, pagination.limit)),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question(&self, id: i32) -> Result<Question, Error> {
+        match sqlx::query("SELECT * FROM questions WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .map(question_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "INSERT INTO questions (title, content, tags, account_id) VALUES (?, ?, ?, ?)
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(new_question.title)
+        .bind(new_question.content)
+        .bind(encode_tags(new_question.tags))
+        .bind(account_id.0)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        let res: Result<Question, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            record_revision(&mut tx, id, &account_id, &editor).await?;
+            let question = sqlx::query(
+                "UPDATE questions SET title = ?, content = ?, tags = ?
+                WHERE id = ? AND account_id = ? AND deleted_at IS NULL
+                RETURNING id, title, content, tags, score, accepted_answer_id",
+            )
+            .bind(question.title)
+            .bind(question.content)
+            .bind(encode_tags(question.tags))
+            .bind(id)
+            .bind(account_id.0)
+            .map(question_from_row)
+            .fetch_one(&mut tx)
+            .await?;
+            tx.commit().await?;
+            Ok(question)
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn patch_question(
+        &self,
+        id: i32,
+        patch: QuestionPatch,
+        account_id: AccountId,
+        editor: AccountId,
+    ) -> Result<Question, Error> {
+        if patch.is_empty() {
+            return self.get_question(id).await;
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("UPDATE questions SET ");
+        {
+            let mut columns = query.separated(", ");
+            if let Some(title) = patch.title {
+                columns.push("title = ").push_bind_unseparated(title);
+            }
+            if let Some(content) = patch.content {
+                columns.push("content = ").push_bind_unseparated(content);
+            }
+            if let Some(tags) = patch.tags {
+                columns
+                    .push("tags = ")
+                    .push_bind_unseparated(encode_tags(tags));
+            }
+        }
+        query
+            .push(" WHERE id = ")
+            .push_bind(id)
+            .push(" AND account_id = ")
+            .push_bind(account_id.0)
+            .push(" AND deleted_at IS NULL")
+            .push(" RETURNING id, title, content, tags, score, accepted_answer_id");
+
+        let res: Result<Question, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            record_revision(&mut tx, id, &account_id, &editor).await?;
+            let question = query
+                .build()
+                .map(question_from_row)
+                .fetch_one(&mut tx)
+                .await?;
+            tx.commit().await?;
+            Ok(question)
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn restore_question(&self, id: i32, account_id: AccountId) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions SET deleted_at = NULL
+            WHERE id = ? AND account_id = ? AND deleted_at IS NOT NULL
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(id)
+        .bind(account_id.0)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn purge_deleted_questions(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        let before = encode_timestamp(before);
+        // INFO: 回答は外部キーで質問を参照しているので先に削除する。過去の版とコメントはCASCADEで削除される
+        let res: Result<u64, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            sqlx::query(
+                "DELETE FROM answers WHERE question_id IN
+                (SELECT id FROM questions WHERE deleted_at < ?)",
+            )
+            .bind(&before)
+            .execute(&mut tx)
+            .await?;
+            let purged = sqlx::query("DELETE FROM questions WHERE deleted_at < ?")
+                .bind(&before)
+                .execute(&mut tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+            Ok(purged)
+        }
+        .await;
+
+        match res {
+            Ok(purged) => Ok(purged),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error> {
+        match sqlx::query(
+            "SELECT * FROM question_revisions WHERE question_id = ? ORDER BY revision",
+        )
+        .bind(question_id)
+        .map(revision_from_row)
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(revisions) => Ok(revisions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_revision(
+        &self,
+        question_id: i32,
+        revision: i32,
+    ) -> Result<QuestionRevision, Error> {
+        match sqlx::query("SELECT * FROM question_revisions WHERE question_id = ? AND revision = ?")
+            .bind(question_id)
+            .bind(revision)
+            .map(revision_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(revision) => Ok(revision),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_question(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        // INFO: 回答が外部キーで参照しているので行は消さず、保持期間を過ぎてから完全に削除する
+        match sqlx::query(
+            "UPDATE questions SET deleted_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND account_id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(account_id.0)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query("SELECT id FROM questions WHERE id = ? AND account_id = ?")
+            .bind(question_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(question) => Ok(question.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_owner(&self, question_id: i32) -> Result<AccountId, Error> {
+        match sqlx::query("SELECT account_id FROM questions WHERE id = ?")
+            .bind(question_id)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(row) => Ok(AccountId(row.get("account_id"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        // INFO: 回答が質問に属していなければ更新されず、RowNotFoundになる
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = ?2
+            WHERE id = ?1 AND deleted_at IS NULL
+                AND EXISTS (SELECT 1 FROM answers WHERE id = ?2 AND question_id = ?1)
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = NULL
+            WHERE id = ?1 AND accepted_answer_id = ?2 AND deleted_at IS NULL
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .map(question_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error> {
+        match sqlx::query("SELECT id FROM answers WHERE id = ? AND account_id = ?")
+            .bind(answer_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<AccountId, Error> {
+        match sqlx::query("SELECT account_id FROM answers WHERE id = ?")
+            .bind(answer_id)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(row) => Ok(AccountId(row.get("account_id"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Question, Error> {
+        // INFO: scoreの更新はトリガーで行う
+        let res = match vote {
+            VoteDirection::None => {
+                sqlx::query("DELETE FROM question_votes WHERE question_id = ? AND account_id = ?")
+                    .bind(question_id)
+                    .bind(account_id.0)
+                    .execute(&self.conn)
+                    .await
+            }
+            _ => {
+                sqlx::query(
+                    "INSERT INTO question_votes (question_id, account_id, value) VALUES (?, ?, ?)
+                ON CONFLICT (question_id, account_id) DO UPDATE SET value = excluded.value",
+                )
+                .bind(question_id)
+                .bind(account_id.0)
+                .bind(vote.value())
+                .execute(&self.conn)
+                .await
+            }
+        };
+        if let Err(e) = res {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        self.get_question(question_id).await
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: VoteDirection,
+    ) -> Result<Answer, Error> {
+        let res = match vote {
+            VoteDirection::None => {
+                sqlx::query("DELETE FROM answer_votes WHERE answer_id = ? AND account_id = ?")
+                    .bind(answer_id)
+                    .bind(account_id.0)
+                    .execute(&self.conn)
+                    .await
+            }
+            _ => {
+                sqlx::query(
+                    "INSERT INTO answer_votes (answer_id, account_id, value) VALUES (?, ?, ?)
+                ON CONFLICT (answer_id, account_id) DO UPDATE SET value = excluded.value",
+                )
+                .bind(answer_id)
+                .bind(account_id.0)
+                .bind(vote.value())
+                .execute(&self.conn)
+                .await
+            }
+        };
+        if let Err(e) = res {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        self.get_answer(answer_id).await
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        // INFO: FTS5のクエリ構文として解釈されないよう、各単語をクォートして前方一致のAND検索にする
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let fts_query = terms
+            .iter()
+            .map(|term| format!("\"{}\"*", term))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // INFO: bm25()は値が小さいほど関連度が高いので符号を反転する。
+        // MAX()と同じ行のsnippetが選ばれるのはSQLiteの仕様
+        match sqlx::query(
+            "WITH matches AS (
+                SELECT questions_fts.rowid AS question_id,
+                    -bm25(questions_fts, 2.0, 1.0) AS rank,
+                    snippet(questions_fts, 1, '<b>', '</b>', '...', 32) AS snippet
+                FROM questions_fts
+                WHERE questions_fts MATCH ?1
+                UNION ALL
+                SELECT a.question_id,
+                    -bm25(answers_fts) * 0.5 AS rank,
+                    snippet(answers_fts, 0, '<b>', '</b>', '...', 32) AS snippet
+                FROM answers_fts JOIN answers a ON a.id = answers_fts.rowid
+                WHERE answers_fts MATCH ?1
+            )
+            SELECT q.id, q.title, q.content, q.tags, q.score, q.accepted_answer_id,
+                SUM(matches.rank) AS relevance, MAX(matches.rank) AS best_rank, matches.snippet
+            FROM matches JOIN questions q ON q.id = matches.question_id
+            WHERE q.deleted_at IS NULL
+            GROUP BY q.id
+            ORDER BY relevance DESC, q.id
+            LIMIT ?2 OFFSET ?3",
+        )
+        .bind(fts_query)
+        .bind(limit.map(i64::from).unwrap_or(-1))
+        .bind(offset as i64)
+        .map(|row: SqliteRow| SearchResult {
+            relevance: row.get::<f64, _>("relevance") as f32,
+            snippet: row.get("snippet"),
+            question: question_from_row(row),
+        })
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(results) => Ok(results),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_tags(&self, limit: Option<u32>, offset: u32) -> Result<Vec<Tag>, Error> {
+        match sqlx::query(
+            "SELECT tag.value AS name, COUNT(DISTINCT questions.id) AS count
+            FROM questions, json_each(questions.tags) AS tag
+            WHERE questions.deleted_at IS NULL
+            GROUP BY tag.value
+            ORDER BY count DESC, name
+            LIMIT ? OFFSET ?",
+        )
+        .bind(limit.map(i64::from).unwrap_or(-1))
+        .bind(offset as i64)
+        .map(|row: SqliteRow| Tag {
+            name: row.get("name"),
+            count: row.get("count"),
+        })
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(tags) => Ok(tags),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answers(&self, question_id: i32) -> Result<Vec<Answer>, Error> {
+        match sqlx::query("SELECT * FROM answers WHERE question_id = ? ORDER BY id")
+            .bind(question_id)
+            .map(answer_from_row)
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answer(&self, id: i32) -> Result<Answer, Error> {
+        match sqlx::query("SELECT * FROM answers WHERE id = ?")
+            .bind(id)
+            .map(answer_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        question_id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers (content, question_id, account_id) VALUES (?, ?, ?)
+            RETURNING id, content, question_id, score",
+        )
+        .bind(new_answer.content)
+        .bind(question_id)
+        .bind(account_id.0)
+        .map(answer_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        id: i32,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers SET content = ? WHERE id = ? AND account_id = ?
+            RETURNING id, content, question_id, score",
+        )
+        .bind(new_answer.content)
+        .bind(id)
+        .bind(account_id.0)
+        .map(answer_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_answer(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        // INFO: 回答数はトリガーで、採用・票・コメントは外部キーの制約で更新される
+        match sqlx::query("DELETE FROM answers WHERE id = ? AND account_id = ?")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_comments(&self, target: CommentTarget) -> Result<Vec<Comment>, Error> {
+        // INFO: 対象でない側の列にはNULLを渡すので、どちらか一方の条件だけが一致する
+        match sqlx::query(
+            "SELECT * FROM comments WHERE question_id = ? OR answer_id = ? ORDER BY id",
+        )
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .map(comment_from_row)
+        .fetch_all(&self.conn)
+        .await
+        {
+            Ok(comments) => Ok(comments),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_comment(
+        &self,
+        target: CommentTarget,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        match sqlx::query(
+            "INSERT INTO comments (content, question_id, answer_id, account_id)
+            VALUES (?, ?, ?, ?)
+            RETURNING id, content, question_id, answer_id",
+        )
+        .bind(new_comment.content)
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(account_id.0)
+        .map(comment_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(comment) => Ok(comment),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_comment(
+        &self,
+        id: i32,
+        new_comment: NewComment,
+        account_id: AccountId,
+    ) -> Result<Comment, Error> {
+        match sqlx::query(
+            "UPDATE comments SET content = ? WHERE id = ? AND account_id = ?
+            RETURNING id, content, question_id, answer_id",
+        )
+        .bind(new_comment.content)
+        .bind(id)
+        .bind(account_id.0)
+        .map(comment_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(comment) => Ok(comment),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_comment(&self, id: i32, account_id: AccountId) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM comments WHERE id = ? AND account_id = ?")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_comment_owner(
+        &self,
+        comment_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query("SELECT id FROM comments WHERE id = ? AND account_id = ?")
+            .bind(comment_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.conn)
+            .await
+        {
+            Ok(comment) => Ok(comment.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query("INSERT INTO accounts (email, password) VALUES (?, ?)")
+            .bind(account.email)
+            .bind(account.password)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let code = e
+                    .as_database_error()
+                    .and_then(|err| err.code())
+                    .map(|code| code.into_owned());
+                tracing::event!(tracing::Level::ERROR, code = ?code, "{:?}", e);
+
+                match code {
+                    Some(code) if UNIQUE_VIOLATIONS.contains(&code.as_str()) => {
+                        Err(Error::AccountAlreadyExists)
+                    }
+                    _ => Err(Error::DatabaseQueryError(e)),
+                }
+            }
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE email = ?")
+            .bind(email)
+            .map(|row: SqliteRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: Role::from_db(row.get("role")),
+                verified_at: row.get("verified_at"),
+            })
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account_info(&self, id: AccountId) -> Result<AccountInfo, Error> {
+        match sqlx::query("SELECT id, email, role, verified_at FROM accounts WHERE id = ?")
+            .bind(id.0)
+            .map(account_info_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountInfo>, Error> {
+        match sqlx::query("SELECT id, email, role, verified_at FROM accounts ORDER BY id")
+            .map(account_info_from_row)
+            .fetch_all(&self.conn)
+            .await
+        {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_account_role(&self, id: AccountId, role: Role) -> Result<AccountInfo, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET role = ? WHERE id = ? RETURNING id, email, role, verified_at",
+        )
+        .bind(role.as_str())
+        .bind(id.0)
+        .map(account_info_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_account_password(
+        &self,
+        id: AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        match sqlx::query("UPDATE accounts SET password = ? WHERE id = ?")
+            .bind(password)
+            .bind(id.0)
+            .execute(&self.conn)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_account(&self, id: AccountId, mode: AccountDeletion) -> Result<bool, Error> {
+        let res: Result<bool, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            match mode {
+                AccountDeletion::Anonymize => {
+                    // INFO: テーブル名は固定の候補から選ぶので、クエリに直接埋め込んでも問題ない
+                    for table in ["questions", "answers", "comments", "question_revisions"] {
+                        sqlx::query(&format!(
+                            "UPDATE {} SET account_id = ? WHERE account_id = ?",
+                            table
+                        ))
+                        .bind(AccountId::DELETED.0)
+                        .bind(id.0)
+                        .execute(&mut tx)
+                        .await?;
+                    }
+                }
+                AccountDeletion::Cascade => {
+                    // INFO: 質問は他の回答が参照しているので、`delete_question`と同じく削除済みにするだけにする
+                    for statement in [
+                        "UPDATE questions SET deleted_at = CURRENT_TIMESTAMP
+                        WHERE account_id = ? AND deleted_at IS NULL",
+                        "DELETE FROM comments WHERE account_id = ?",
+                        "DELETE FROM answers WHERE account_id = ?",
+                    ] {
+                        sqlx::query(statement).bind(id.0).execute(&mut tx).await?;
+                    }
+                }
+            }
+
+            // INFO: 票の合計はトリガーで差し引かれる
+            for statement in [
+                "DELETE FROM question_votes WHERE account_id = ?",
+                "DELETE FROM answer_votes WHERE account_id = ?",
+                "DELETE FROM password_reset_tokens WHERE account_id = ?",
+                "DELETE FROM email_verification_tokens WHERE account_id = ?",
+                "DELETE FROM data_exports WHERE account_id = ?",
+                "UPDATE login_sessions SET revoked_at = CURRENT_TIMESTAMP
+                WHERE account_id = ? AND revoked_at IS NULL",
+            ] {
+                sqlx::query(statement).bind(id.0).execute(&mut tx).await?;
+            }
+
+            let deleted = sqlx::query("DELETE FROM accounts WHERE id = ?")
+                .bind(id.0)
+                .execute(&mut tx)
+                .await?
+                .rows_affected()
+                == 1;
+            tx.commit().await?;
+            Ok(deleted)
+        }
+        .await;
+
+        match res {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_password_reset_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO password_reset_tokens (account_id, token_hash, expires_at) VALUES (?, ?, ?)",
+        )
+        .bind(account_id.0)
+        .bind(token_hash)
+        .bind(encode_timestamp(expires_at))
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn use_password_reset_token(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        match sqlx::query(
+            "UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP
+            WHERE token_hash = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            RETURNING account_id",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| AccountId(row.get("account_id")))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_email_verification_token(
+        &self,
+        account_id: AccountId,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO email_verification_tokens (account_id, token_hash, expires_at)
+            VALUES (?, ?, ?)",
+        )
+        .bind(account_id.0)
+        .bind(token_hash)
+        .bind(encode_timestamp(expires_at))
+        .execute(&self.conn)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn verify_email(&self, token_hash: &str) -> Result<Option<AccountId>, Error> {
+        let res: Result<Option<AccountId>, sqlx::Error> = async {
+            let mut tx = self.conn.begin().await?;
+            let account_id = sqlx::query(
+                "UPDATE email_verification_tokens SET used_at = CURRENT_TIMESTAMP
+                WHERE token_hash = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+                RETURNING account_id",
+            )
+            .bind(token_hash)
+            .map(|row: SqliteRow| AccountId(row.get("account_id")))
+            .fetch_optional(&mut tx)
+            .await?;
+
+            if let Some(account_id) = &account_id {
+                sqlx::query(
+                    "UPDATE accounts SET verified_at = CURRENT_TIMESTAMP WHERE id = ? AND verified_at IS NULL",
+                )
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+            }
+            tx.commit().await?;
+            Ok(account_id)
+        }
+        .await;
+
+        match res {
+            Ok(account_id) => Ok(account_id),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_login_session(
+        &self,
+        account_id: AccountId,
+        refresh_token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<LoginSession, Error> {
+        match sqlx::query(
+            "INSERT INTO login_sessions (account_id, refresh_token_hash, expires_at)
+            VALUES (?, ?, ?)
+            RETURNING id, account_id, refresh_token_hash, expires_at, revoked_at",
+        )
+        .bind(account_id.0)
+        .bind(refresh_token_hash)
+        .bind(encode_timestamp(expires_at))
+        .map(login_session_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(session) => Ok(session),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_login_session(&self, id: i32) -> Result<LoginSession, Error> {
+        match sqlx::query("SELECT * FROM login_sessions WHERE id = ?")
+            .bind(id)
+            .map(login_session_from_row)
+            .fetch_one(&self.conn)
+            .await
+        {
+            Ok(session) => Ok(session),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET refresh_token_hash = ?, expires_at = ?
+            WHERE id = ? AND refresh_token_hash = ? AND revoked_at IS NULL",
+        )
+        .bind(new_hash)
+        .bind(encode_timestamp(expires_at))
+        .bind(id)
+        .bind(old_hash)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_login_session(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET revoked_at = CURRENT_TIMESTAMP WHERE id = ? AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_account_sessions(&self, account_id: AccountId) -> Result<u64, Error> {
+        match sqlx::query(
+            "UPDATE login_sessions SET revoked_at = CURRENT_TIMESTAMP WHERE account_id = ? AND revoked_at IS NULL",
+        )
+        .bind(account_id.0)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account_export(&self, account_id: AccountId) -> Result<AccountExport, Error> {
+        let res: Result<AccountExport, sqlx::Error> = async {
+            let account =
+                sqlx::query("SELECT id, email, role, verified_at FROM accounts WHERE id = ?1")
+                    .bind(account_id.0)
+                    .map(account_info_from_row)
+                    .fetch_one(&self.conn)
+                    .await?;
+            let questions =
+                sqlx::query("SELECT * FROM questions WHERE account_id = ?1 ORDER BY id")
+                    .bind(account_id.0)
+                    .map(question_from_row)
+                    .fetch_all(&self.conn)
+                    .await?;
+            let answers = sqlx::query("SELECT * FROM answers WHERE account_id = ?1 ORDER BY id")
+                .bind(account_id.0)
+                .map(answer_from_row)
+                .fetch_all(&self.conn)
+                .await?;
+            let comments = sqlx::query("SELECT * FROM comments WHERE account_id = ?1 ORDER BY id")
+                .bind(account_id.0)
+                .map(comment_from_row)
+                .fetch_all(&self.conn)
+                .await?;
+            let votes = sqlx::query(
+                "SELECT question_id, NULL AS answer_id, value, created_on
+                FROM question_votes WHERE account_id = ?1
+                UNION ALL
+                SELECT NULL, answer_id, value, created_on
+                FROM answer_votes WHERE account_id = ?1
+                ORDER BY created_on",
+            )
+            .bind(account_id.0)
+            .map(exported_vote_from_row)
+            .fetch_all(&self.conn)
+            .await?;
+            let revisions = sqlx::query(
+                "SELECT * FROM question_revisions WHERE account_id = ?1
+                ORDER BY question_id, revision",
+            )
+            .bind(account_id.0)
+            .map(revision_from_row)
+            .fetch_all(&self.conn)
+            .await?;
+
+            Ok(AccountExport {
+                exported_on: Utc::now().naive_utc(),
+                account,
+                questions,
+                answers,
+                comments,
+                votes,
+                revisions,
+            })
+        }
+        .await;
+
+        match res {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        match sqlx::query(
+            "INSERT INTO data_exports (account_id) VALUES (?1)
+            RETURNING id, status, created_on, expires_at",
+        )
+        .bind(account_id.0)
+        .map(data_export_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_latest_data_export(&self, account_id: AccountId) -> Result<DataExport, Error> {
+        match sqlx::query(
+            "SELECT id, status, created_on, expires_at FROM data_exports
+            WHERE account_id = ?1 ORDER BY id DESC LIMIT 1",
+        )
+        .bind(account_id.0)
+        .map(data_export_from_row)
+        .fetch_one(&self.conn)
+        .await
+        {
+            Ok(export) => Ok(export),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn complete_data_export(
+        &self,
+        id: i32,
+        archive: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE data_exports SET status = 'ready', archive = ?2, token_hash = ?3, expires_at = ?4
+            WHERE id = ?1 AND status = 'pending'",
+        )
+        .bind(id)
+        .bind(archive)
+        .bind(token_hash)
+        .bind(encode_timestamp(expires_at))
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn fail_data_export(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE data_exports SET status = 'failed' WHERE id = ?1 AND status = 'pending'",
+        )
+        .bind(id)
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() == 1),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_data_export_archive(&self, token_hash: &str) -> Result<Option<String>, Error> {
+        match sqlx::query(
+            "SELECT archive FROM data_exports
+            WHERE token_hash = ?1 AND status = 'ready' AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| row.get("archive"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_latest_data_export_archive(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Option<String>, Error> {
+        match sqlx::query(
+            "SELECT archive FROM data_exports
+            WHERE id = (SELECT MAX(id) FROM data_exports WHERE account_id = ?1)
+            AND status = 'ready' AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(account_id.0)
+        .fetch_optional(&self.conn)
+        .await
+        {
+            Ok(row) => Ok(row.map(|row| row.get("archive"))),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn purge_expired_data_exports(&self, before: NaiveDateTime) -> Result<u64, Error> {
+        match sqlx::query(
+            "DELETE FROM data_exports
+            WHERE expires_at < ?1 OR (status = 'failed' AND created_on < ?1)",
+        )
+        .bind(encode_timestamp(before))
+        .execute(&self.conn)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod sqlite_store_tests {
+    use super::*;
+    use crate::types::question::{SortDirection, SortField};
+    use chrono::Utc;
+
+    async fn new_store() -> SqliteStore {
+        let store = SqliteStore::new("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&store.conn)
+            .await
+            .unwrap();
+        store
+    }
+
+    async fn add_question(store: &SqliteStore, title: &str, tags: Option<Vec<&str>>) -> Question {
+        store
+            .add_question(
+                NewQuestion {
+                    title: title.to_string(),
+                    content: "content".to_string(),
+                    tags: tags.map(|tags| tags.into_iter().map(String::from).collect()),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tags_round_trip_as_json() {
+        let store = new_store().await;
+        let question = add_question(&store, "title", Some(vec!["rust", "warp"])).await;
+
+        let fetched = store.get_question(question.id.0).await.unwrap();
+        assert_eq!(
+            fetched.tags,
+            Some(vec!["rust".to_string(), "warp".to_string()])
+        );
+        assert_eq!(
+            store
+                .get_questions(
+                    &QuestionFilter::default(),
+                    &QuestionSort::default(),
+                    &Pagination::default()
+                )
+                .await
+                .unwrap()
+                .items
+                .len(),
+            1
+        );
+
+        let answer = store
+            .add_answer(
+                question.id.0,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_answer(answer.id.0).await.unwrap().question_id,
+            question.id
+        );
+    }
+
+    #[tokio::test]
+    async fn questions_are_filtered_by_tags() {
+        let store = new_store().await;
+        for tags in [Some(vec!["rust", "warp"]), Some(vec!["rust"]), None] {
+            add_question(&store, "title", tags).await;
+        }
+
+        let mut filter = QuestionFilter {
+            tags: vec!["warp".to_string(), "rust".to_string()],
+            tag_mode: TagMode::Any,
+            ..QuestionFilter::default()
+        };
+        let pagination = Pagination::default();
+        assert_eq!(
+            store
+                .get_questions(&filter, &QuestionSort::default(), &pagination)
+                .await
+                .unwrap()
+                .items
+                .len(),
+            2
+        );
+        filter.tag_mode = TagMode::All;
+        assert_eq!(
+            store
+                .get_questions(&filter, &QuestionSort::default(), &pagination)
+                .await
+                .unwrap()
+                .items
+                .len(),
+            1
+        );
+
+        let tags = store.get_tags(None, 0).await.unwrap();
+        assert_eq!(
+            tags,
+            vec![
+                Tag {
+                    name: "rust".to_string(),
+                    count: 2
+                },
+                Tag {
+                    name: "warp".to_string(),
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn questions_are_paged_by_cursor() {
+        let store = new_store().await;
+        for title in ["first", "second", "third"] {
+            add_question(&store, title, None).await;
+        }
+
+        let filter = QuestionFilter::default();
+        let mut pagination = Pagination {
+            limit: Some(2),
+            offset: 0,
+            cursor: None,
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &pagination)
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.next_cursor.map(|c| c.id), Some(2));
+
+        pagination.cursor = page.next_cursor;
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &pagination)
+            .await
+            .unwrap();
+        assert_eq!(page.items[0].title, "third");
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn questions_are_sorted_by_answer_count_and_title() {
+        let store = new_store().await;
+        let mut ids = Vec::new();
+        for title in ["banana", "Apple", "cherry"] {
+            let question = add_question(&store, title, None).await;
+            ids.push(question.id.0);
+        }
+        for question_id in [ids[2], ids[2], ids[0]] {
+            store
+                .add_answer(
+                    question_id,
+                    NewAnswer {
+                        content: "answer".to_string(),
+                    },
+                    AccountId(2),
+                )
+                .await
+                .unwrap();
+        }
+
+        let titles = |sort: QuestionSort| {
+            let store = store.clone();
+            async move {
+                store
+                    .get_questions(&QuestionFilter::default(), &sort, &Pagination::default())
+                    .await
+                    .unwrap()
+                    .items
+                    .into_iter()
+                    .map(|q| q.title)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            titles(QuestionSort {
+                field: SortField::AnswerCount,
+                direction: SortDirection::Desc,
+            })
+            .await,
+            vec!["cherry", "banana", "Apple"]
+        );
+        assert_eq!(
+            titles(QuestionSort {
+                field: SortField::Title,
+                direction: SortDirection::Asc,
+            })
+            .await,
+            vec!["Apple", "banana", "cherry"]
+        );
+        assert_eq!(
+            titles(QuestionSort {
+                field: SortField::CreatedOn,
+                direction: SortDirection::Desc,
+            })
+            .await,
+            vec!["cherry", "Apple", "banana"]
+        );
+    }
+
+    #[tokio::test]
+    async fn edits_are_recorded_as_revisions() {
+        let store = new_store().await;
+        let question = add_question(&store, "first", Some(vec!["rust"])).await;
+        store
+            .patch_question(
+                question.id.0,
+                QuestionPatch {
+                    title: Some("second".to_string()),
+                    ..QuestionPatch::default()
+                },
+                AccountId(1),
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        store
+            .patch_question(
+                question.id.0,
+                QuestionPatch {
+                    tags: Some(None),
+                    ..QuestionPatch::default()
+                },
+                AccountId(1),
+                AccountId(3),
+            )
+            .await
+            .unwrap();
+        // 所有者以外の編集は失敗し、版も記録されない
+        assert!(store
+            .patch_question(
+                question.id.0,
+                QuestionPatch {
+                    title: Some("third".to_string()),
+                    ..QuestionPatch::default()
+                },
+                AccountId(2),
+                AccountId(2),
+            )
+            .await
+            .is_err());
+
+        let revisions = store.get_question_revisions(question.id.0).await.unwrap();
+        assert_eq!(
+            revisions
+                .iter()
+                .map(|r| (r.revision, r.title.as_str(), r.tags.clone(), r.account_id.0))
+                .collect::<Vec<_>>(),
+            // 所有者に代わって編集した場合は、編集したアカウントを記録する
+            vec![
+                (1, "first", Some(vec!["rust".to_string()]), 1),
+                (2, "second", Some(vec!["rust".to_string()]), 3),
+            ]
+        );
+        assert_eq!(
+            store.get_question_revision(question.id.0, 2).await.unwrap(),
+            revisions[1]
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_questions_are_hidden_until_purged() {
+        let store = new_store().await;
+        let question = add_question(&store, "title", Some(vec!["rust"])).await;
+        let id = question.id.0;
+        store
+            .add_answer(
+                id,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+
+        // 回答があっても削除できる
+        store.delete_question(id, AccountId(1)).await.unwrap();
+        assert!(store.get_question(id).await.is_err());
+        assert!(store.get_tags(None, 0).await.unwrap().is_empty());
+        assert!(store.restore_question(id, AccountId(2)).await.is_err());
+
+        store.restore_question(id, AccountId(1)).await.unwrap();
+        assert!(store.get_question(id).await.is_ok());
+        assert!(store.restore_question(id, AccountId(1)).await.is_err());
+
+        store.delete_question(id, AccountId(1)).await.unwrap();
+        let yesterday = Utc::now().naive_utc() - chrono::Duration::days(1);
+        assert_eq!(store.purge_deleted_questions(yesterday).await.unwrap(), 0);
+        let tomorrow = Utc::now().naive_utc() + chrono::Duration::days(1);
+        assert_eq!(store.purge_deleted_questions(tomorrow).await.unwrap(), 1);
+        assert!(store.get_answers(id).await.unwrap().is_empty());
+        assert!(!store.is_question_owner(id, &AccountId(1)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn accepted_answers_are_filtered_out() {
+        let store = new_store().await;
+        for title in ["answered", "unanswered"] {
+            add_question(&store, title, None).await;
+        }
+        let answer = store
+            .add_answer(
+                1,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        let titles = |page: Page<Question>| {
+            page.items
+                .into_iter()
+                .map(|question| question.title)
+                .collect::<Vec<_>>()
+        };
+
+        let filter = QuestionFilter {
+            unanswered: true,
+            ..QuestionFilter::default()
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(titles(page), vec!["unanswered"]);
+
+        // 他の質問の回答は採用できない
+        assert!(store.accept_answer(2, answer.id.0).await.is_err());
+        let question = store.accept_answer(1, answer.id.0).await.unwrap();
+        assert_eq!(question.accepted_answer_id, Some(answer.id.clone()));
+
+        let filter = QuestionFilter {
+            unaccepted: true,
+            ..QuestionFilter::default()
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(titles(page), vec!["unanswered"]);
+
+        assert!(store.unaccept_answer(2, answer.id.0).await.is_err());
+        let question = store.unaccept_answer(1, answer.id.0).await.unwrap();
+        assert_eq!(question.accepted_answer_id, None);
+    }
+
+    #[tokio::test]
+    async fn deleting_an_answer_clears_its_acceptance() {
+        let store = new_store().await;
+        add_question(&store, "title", None).await;
+        let answer = store
+            .add_answer(
+                1,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        store.accept_answer(1, answer.id.0).await.unwrap();
+
+        let edited = store
+            .update_answer(
+                answer.id.0,
+                NewAnswer {
+                    content: "edited".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        assert_eq!(edited.content, "edited");
+
+        // 投稿者以外は削除できない
+        store
+            .delete_answer(answer.id.0, AccountId(1))
+            .await
+            .unwrap();
+        assert!(store.get_answer(answer.id.0).await.is_ok());
+
+        store
+            .delete_answer(answer.id.0, AccountId(2))
+            .await
+            .unwrap();
+        assert!(store.get_answer(answer.id.0).await.is_err());
+        assert_eq!(
+            store.get_question(1).await.unwrap().accepted_answer_id,
+            None
+        );
+        let filter = QuestionFilter {
+            unanswered: true,
+            ..QuestionFilter::default()
+        };
+        let page = store
+            .get_questions(&filter, &QuestionSort::default(), &Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn comments_belong_to_a_single_post() {
+        let store = new_store().await;
+        let question = add_question(&store, "title", None).await;
+        let answer = store
+            .add_answer(
+                question.id.0,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        let comment = |content: &str| NewComment {
+            content: content.to_string(),
+        };
+
+        let on_question = store
+            .add_comment(
+                CommentTarget::Question(question.id.0),
+                comment("on question"),
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        store
+            .add_comment(
+                CommentTarget::Answer(answer.id.0),
+                comment("on answer"),
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        assert!(store
+            .add_comment(CommentTarget::Answer(99), comment("missing"), AccountId(1))
+            .await
+            .is_err());
+
+        let comments = store
+            .get_comments(CommentTarget::Question(question.id.0))
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].content, "on question");
+        assert_eq!(comments[0].answer_id, None);
+
+        // 投稿者以外は編集・削除できない
+        let id = on_question.id.0;
+        assert!(store
+            .update_comment(id, comment("edited"), AccountId(1))
+            .await
+            .is_err());
+        store.delete_comment(id, AccountId(1)).await.unwrap();
+        assert!(store.is_comment_owner(id, &AccountId(2)).await.unwrap());
+
+        let edited = store
+            .update_comment(id, comment("edited"), AccountId(2))
+            .await
+            .unwrap();
+        assert_eq!(edited.content, "edited");
+        store.delete_comment(id, AccountId(2)).await.unwrap();
+        assert!(!store.is_comment_owner(id, &AccountId(2)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn votes_are_totalled_into_score() {
+        let store = new_store().await;
+        let question = add_question(&store, "title", None).await;
+        let id = question.id.0;
+
+        let question = store
+            .vote_question(id, AccountId(2), VoteDirection::Up)
+            .await
+            .unwrap();
+        assert_eq!(question.score, 1);
+        let question = store
+            .vote_question(id, AccountId(3), VoteDirection::Up)
+            .await
+            .unwrap();
+        assert_eq!(question.score, 2);
+
+        // 同じアカウントの再投票は票を置き換える
+        let question = store
+            .vote_question(id, AccountId(2), VoteDirection::Down)
+            .await
+            .unwrap();
+        assert_eq!(question.score, 0);
+        let question = store
+            .vote_question(id, AccountId(3), VoteDirection::None)
+            .await
+            .unwrap();
+        assert_eq!(question.score, -1);
+
+        let answer = store
+            .add_answer(
+                id,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        let answer = store
+            .vote_answer(answer.id.0, AccountId(1), VoteDirection::Up)
+            .await
+            .unwrap();
+        assert_eq!(answer.score, 1);
+        assert!(store
+            .is_answer_owner(answer.id.0, &AccountId(2))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn search_ranks_questions_and_answers() {
+        let store = new_store().await;
+        let mut ids = Vec::new();
+        for (title, content) in [
+            ("How to use warp filters", "Composing filters with and"),
+            ("Database pooling", "Which pool size should I use?"),
+        ] {
+            let question = store
+                .add_question(
+                    NewQuestion {
+                        title: title.to_string(),
+                        content: content.to_string(),
+                        tags: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+            ids.push(question.id);
+        }
+        store
+            .add_answer(
+                ids[1].0,
+                NewAnswer {
+                    content: "Filters are not related, but warp handles it".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+
+        let results = store.search_questions("warp", None, 0).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].question.id, ids[0]);
+        assert!(results[0].relevance >= results[1].relevance);
+        assert!(results[1].snippet.contains("<b>warp</b>"));
+
+        let results = store
+            .search_questions("pool size", Some(10), 0)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(store
+            .search_questions("\"*", None, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicate_email_is_rejected() {
+        let store = new_store().await;
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "hash".to_string(),
+            role: Role::User,
+            verified_at: None,
+        };
+
+        store.add_account(account.clone()).await.unwrap();
+        assert!(matches!(
+            store.add_account(account).await,
+            Err(Error::AccountAlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn roles_default_to_user_and_can_be_changed() {
+        let store = new_store().await;
+        for email in ["a@example.com", "b@example.com"] {
+            store
+                .add_account(Account {
+                    id: None,
+                    email: email.to_string(),
+                    password: "hash".to_string(),
+                    role: Role::Admin,
+                    verified_at: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        // INFO: ロールは登録時には指定できず、常にuserになる
+        let account = store
+            .get_account("a@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.role, Role::User);
+
+        let updated = store
+            .update_account_role(AccountId(2), Role::Moderator)
+            .await
+            .unwrap();
+        assert_eq!(updated.role, Role::Moderator);
+        assert_eq!(
+            store.get_accounts().await.unwrap(),
+            vec![
+                AccountInfo {
+                    id: AccountId(1),
+                    email: "a@example.com".to_string(),
+                    role: Role::User,
+                    verified_at: None,
+                },
+                updated,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn verification_tokens_verify_the_account_once() {
+        let store = new_store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "a@example.com".to_string(),
+                password: "hash".to_string(),
+                role: Role::User,
+                verified_at: None,
+            })
+            .await
+            .unwrap();
+        let now = Utc::now().naive_utc();
+        store
+            .add_email_verification_token(
+                AccountId(1),
+                "expired".to_string(),
+                now - chrono::Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+        store
+            .add_email_verification_token(
+                AccountId(1),
+                "valid".to_string(),
+                now + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(store.verify_email("expired").await.unwrap(), None);
+        assert!(store
+            .get_account_info(AccountId(1))
+            .await
+            .unwrap()
+            .verified_at
+            .is_none());
+
+        assert_eq!(
+            store.verify_email("valid").await.unwrap(),
+            Some(AccountId(1))
+        );
+        assert_eq!(store.verify_email("valid").await.unwrap(), None);
+        assert!(store
+            .get_account_info(AccountId(1))
+            .await
+            .unwrap()
+            .verified_at
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens_rotate_only_once() {
+        let store = new_store().await;
+        let expires_at = (Utc::now() + chrono::Duration::days(1)).naive_utc();
+        let session = store
+            .add_login_session(AccountId(1), "first".to_string(), expires_at)
+            .await
+            .unwrap();
+
+        assert!(store
+            .rotate_refresh_token(session.id, "first", "second".to_string(), expires_at)
+            .await
+            .unwrap());
+        // 置き換え済みのハッシュでは置き換えられない
+        assert!(!store
+            .rotate_refresh_token(session.id, "first", "third".to_string(), expires_at)
+            .await
+            .unwrap());
+
+        assert!(store.revoke_login_session(session.id).await.unwrap());
+        let session = store.get_login_session(session.id).await.unwrap();
+        assert_eq!(session.refresh_token_hash, "second");
+        assert_eq!(session.expires_at, expires_at);
+        assert!(session.revoked_at.is_some());
+        assert!(!store
+            .rotate_refresh_token(session.id, "second", "third".to_string(), expires_at)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn deleting_an_account_anonymizes_or_removes_its_posts() {
+        for mode in [AccountDeletion::Anonymize, AccountDeletion::Cascade] {
+            let store = new_store().await;
+            for email in ["leaving@example.com", "staying@example.com"] {
+                store
+                    .add_account(Account {
+                        id: None,
+                        email: email.to_string(),
+                        password: "hash".to_string(),
+                        role: Role::User,
+                        verified_at: None,
+                    })
+                    .await
+                    .unwrap();
+            }
+            let own = add_question(&store, "own", None).await;
+            let other = store
+                .add_question(
+                    NewQuestion {
+                        title: "other".to_string(),
+                        content: "content".to_string(),
+                        tags: None,
+                    },
+                    AccountId(2),
+                )
+                .await
+                .unwrap();
+            store
+                .add_answer(
+                    other.id.0,
+                    NewAnswer {
+                        content: "answer".to_string(),
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+            store
+                .vote_question(other.id.0, AccountId(1), VoteDirection::Up)
+                .await
+                .unwrap();
+            let session = store
+                .add_login_session(
+                    AccountId(1),
+                    "hash".to_string(),
+                    Utc::now().naive_utc() + chrono::Duration::days(1),
+                )
+                .await
+                .unwrap();
+
+            assert!(store.delete_account(AccountId(1), mode).await.unwrap());
+            assert!(!store.delete_account(AccountId(1), mode).await.unwrap());
+            assert!(store.get_account_info(AccountId(1)).await.is_err());
+            assert!(store
+                .get_login_session(session.id)
+                .await
+                .unwrap()
+                .revoked_at
+                .is_some());
+            // 票は取り消される
+            assert_eq!(store.get_question(other.id.0).await.unwrap().score, 0);
+
+            let answers = store.get_answers(other.id.0).await.unwrap();
+            match mode {
+                AccountDeletion::Anonymize => {
+                    assert_eq!(
+                        store.get_question_owner(own.id.0).await.unwrap(),
+                        AccountId::DELETED
+                    );
+                    assert_eq!(
+                        store.get_answer_owner(answers[0].id.0).await.unwrap(),
+                        AccountId::DELETED
+                    );
+                }
+                AccountDeletion::Cascade => {
+                    assert!(store.get_question(own.id.0).await.is_err());
+                    assert!(answers.is_empty());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn data_exports_collect_account_data_and_expire() {
+        let store = new_store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "a@example.com".to_string(),
+                password: "hash".to_string(),
+                role: Role::User,
+                verified_at: None,
+            })
+            .await
+            .unwrap();
+        let question = add_question(&store, "title", Some(vec!["rust"])).await;
+        let answer = store
+            .add_answer(
+                question.id.0,
+                NewAnswer {
+                    content: "answer".to_string(),
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        store
+            .vote_answer(answer.id.0, AccountId(1), VoteDirection::Down)
+            .await
+            .unwrap();
+
+        let export = store.get_account_export(AccountId(1)).await.unwrap();
+        assert_eq!(export.account.email, "a@example.com");
+        assert_eq!(export.questions.len(), 1);
+        assert!(export.answers.is_empty());
+        assert_eq!(export.votes.len(), 1);
+        assert_eq!(export.votes[0].question_id, None);
+        assert_eq!(export.votes[0].answer_id, Some(answer.id));
+        assert_eq!(export.votes[0].value, -1);
+
+        let now = Utc::now().naive_utc();
+        let valid = store.add_data_export(AccountId(1)).await.unwrap();
+        assert_eq!(valid.status, ExportStatus::Pending);
+        assert_eq!(store.get_data_export_archive("valid").await.unwrap(), None);
+        assert!(store
+            .complete_data_export(
+                valid.id,
+                "{}".to_string(),
+                "valid".to_string(),
+                now + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap());
+        assert_eq!(
+            store.get_data_export_archive("valid").await.unwrap(),
+            Some("{}".to_string())
+        );
+        assert_eq!(
+            store
+                .get_latest_data_export_archive(AccountId(1))
+                .await
+                .unwrap(),
+            Some("{}".to_string())
+        );
+        assert_eq!(
+            store
+                .get_latest_data_export(AccountId(1))
+                .await
+                .unwrap()
+                .status,
+            ExportStatus::Ready
+        );
+
+        let expired = store.add_data_export(AccountId(1)).await.unwrap();
+        store
+            .complete_data_export(
+                expired.id,
+                "{}".to_string(),
+                "expired".to_string(),
+                now - chrono::Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_data_export_archive("expired").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            store
+                .get_latest_data_export(AccountId(1))
+                .await
+                .unwrap()
+                .status,
+            ExportStatus::Expired
+        );
+        // 古い書き出しが期限内でも、最も新しい書き出しが期限切れなら返さない
+        assert_eq!(
+            store
+                .get_latest_data_export_archive(AccountId(1))
+                .await
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(store.purge_expired_data_exports(now).await.unwrap(), 1);
+        assert_eq!(
+            store.get_latest_data_export(AccountId(1)).await.unwrap().id,
+            valid.id
+        );
+    }
+}
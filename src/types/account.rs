@@ -1,19 +1,216 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AccountId(pub i32);
 
+impl AccountId {
+    /// 削除されたアカウントの投稿に付け替える所有者。
+    /// SERIAL型は1から採番するので、実在するアカウントのidとは重ならない
+    pub const DELETED: AccountId = AccountId(0);
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     pub id: Option<AccountId>,
     pub email: String,
     pub password: String,
+    /// INFO: 登録時にリクエストからロールを指定できないよう、デシリアライズでは常に既定値にする
+    #[serde(default, skip_deserializing)]
+    pub role: Role,
+    /// メールアドレスを確認した日時。未確認のアカウントは質問・回答を投稿できない
+    #[serde(default, skip_deserializing)]
+    pub verified_at: Option<NaiveDateTime>,
+}
+
+/// 登録時に受け付けるメールアドレスか
+///
+/// INFO: RFC 5322の全ては検証せず、`local@domain.tld`の形であることだけを確かめる。
+/// 実在するかは確認メールで確かめる
+pub fn is_valid_email(email: &str) -> bool {
+    // VARCHAR(255)に収まり、メールのヘッダーに差し込めない文字を含まないこと
+    if email.len() > 254
+        || email
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '<' || c == '>')
+    {
+        return false;
+    }
+
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty())
+        }
+        None => false,
+    }
+}
+
+/// アカウントを削除する際に、そのアカウントの質問・回答・コメントをどう扱うか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccountDeletion {
+    /// 投稿は残し、所有者を`AccountId::DELETED`に付け替える
+    #[default]
+    Anonymize,
+    /// 投稿も削除する。質問は他の削除された質問と同じく、保持期間を過ぎてから完全に削除される
+    Cascade,
+}
+
+impl FromStr for AccountDeletion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "anonymize" => Ok(AccountDeletion::Anonymize),
+            "cascade" => Ok(AccountDeletion::Cascade),
+            _ => Err(format!(
+                "expected `anonymize` or `cascade`, found `{}`",
+                value
+            )),
+        }
+    }
+}
+
+/// アカウントのロール。後の値ほど権限が強く、上位のロールは下位のロールの操作も行える
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    /// 他人の質問・回答を編集・削除できる
+    Moderator,
+    /// モデレーターの操作に加えて、アカウントを管理できる
+    Admin,
 }
 
+impl Role {
+    /// `accounts.role`カラムに保存する値
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Role {
+        match value {
+            "moderator" => Role::Moderator,
+            "admin" => Role::Admin,
+            _ => Role::User,
+        }
+    }
+}
+
+/// 管理者向けのアカウント一覧に返す内容。パスワードのハッシュは含めない
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+    pub verified_at: Option<NaiveDateTime>,
+}
+
+/// `PUT /admin/accounts/:account_id/role`の本文
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoleUpdate {
+    pub role: Role,
+}
+
+/// アクセストークンに含まれる内容
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
     pub exp: DateTime<Utc>,
     pub account_id: AccountId,
     pub nbf: DateTime<Utc>,
+    /// 発行元の`LoginSession`のid。失効しているかの確認に使う
+    pub session_id: i32,
+    /// 発行時のロール。変更はリフレッシュ時に反映される
+    pub role: Role,
+}
+
+/// ログインごとに作られ、リフレッシュトークンを管理する`login_sessions`テーブルの1行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginSession {
+    pub id: i32,
+    pub account_id: AccountId,
+    /// リフレッシュトークンの秘密の部分のSHA-256(16進数)。トークン自体は保存しない
+    pub refresh_token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// `POST /login`・`POST /token/refresh`のレスポンス
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// アクセストークンの有効期間(秒)
+    pub expires_in: i64,
+}
+
+/// `POST /token/refresh`の本文
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// `GET /email/verify`のクエリパラメータ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+/// `POST /password/forgot`の本文
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+/// `PUT /account/password`の本文
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// `POST /password/reset`の本文
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResetPasswordRequest {
+    /// メールで送られたトークン
+    pub token: String,
+    /// 新しいパスワード
+    pub password: String,
+}
+
+#[cfg(test)]
+mod account_tests {
+    use super::is_valid_email;
+
+    #[test]
+    fn emails_need_a_local_part_and_a_dotted_domain() {
+        for email in ["user@example.com", "first.last+tag@mail.example.co.jp"] {
+            assert!(is_valid_email(email), "{}", email);
+        }
+
+        for email in [
+            "",
+            "user",
+            "@example.com",
+            "user@",
+            "user@localhost",
+            "user@example..com",
+            "user@@example.com",
+            "user name@example.com",
+            "user@example.com\r\nBcc: x@example.com",
+            "<user@example.com>",
+        ] {
+            assert!(!is_valid_email(email), "{}", email);
+        }
+    }
 }
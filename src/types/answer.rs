@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::types::question::QuestionId;
+use crate::types::question::{present, QuestionId};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct AnswerId(pub i32);
@@ -10,10 +10,20 @@ pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: QuestionId,
+    /// 票の合計
+    #[serde(default)]
+    pub score: i32,
 }
 
+/// 回答の投稿・編集内容。対象の質問はパス(`/questions/:question_id/answers`)から受け取る
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NewAnswer {
     pub content: String,
-    pub question_id: QuestionId,
+}
+
+/// `PATCH /answers/:id`の本文。`QuestionPatch`と同じくJSON Merge Patchとして解釈する
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
+pub struct AnswerPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub content: Option<String>,
 }
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{answer::AnswerId, question::QuestionId};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct CommentId(pub i32);
+
+/// 質問か回答に付いたコメント。`question_id`と`answer_id`のどちらか一方だけが入る
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: CommentId,
+    pub content: String,
+    pub question_id: Option<QuestionId>,
+    pub answer_id: Option<AnswerId>,
+}
+
+/// コメントの投稿・編集内容。対象の投稿はパスから受け取る
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewComment {
+    pub content: String,
+}
+
+/// コメントを付ける投稿
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentTarget {
+    Question(i32),
+    Answer(i32),
+}
+
+impl CommentTarget {
+    pub fn question_id(&self) -> Option<i32> {
+        match self {
+            CommentTarget::Question(id) => Some(*id),
+            CommentTarget::Answer(_) => None,
+        }
+    }
+
+    pub fn answer_id(&self) -> Option<i32> {
+        match self {
+            CommentTarget::Question(_) => None,
+            CommentTarget::Answer(id) => Some(*id),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    account::AccountInfo,
+    answer::{Answer, AnswerId},
+    comment::Comment,
+    question::{Question, QuestionId},
+    revision::QuestionRevision,
+};
+
+/// 書き出しの状態
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    /// バックグラウンドで作成中
+    Pending,
+    /// ダウンロード用のリンクをメールで送った
+    Ready,
+    Failed,
+    /// ダウンロードできる期限を過ぎた
+    Expired,
+}
+
+impl ExportStatus {
+    /// `data_exports.status`カラムの値と期限から現在の状態を求める。`expired`は保存しない
+    pub fn from_db(value: &str, expires_at: Option<NaiveDateTime>) -> ExportStatus {
+        match value {
+            "ready"
+                if expires_at.is_some_and(|expires_at| expires_at <= Utc::now().naive_utc()) =>
+            {
+                ExportStatus::Expired
+            }
+            "ready" => ExportStatus::Ready,
+            "failed" => ExportStatus::Failed,
+            _ => ExportStatus::Pending,
+        }
+    }
+}
+
+/// `data_exports`テーブルの1行。内容とトークンのハッシュは含めない
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DataExport {
+    pub id: i32,
+    pub status: ExportStatus,
+    pub created_on: NaiveDateTime,
+    /// ダウンロードできる期限。完成するまでは`None`
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// 書き出しに含める票。`question_id`と`answer_id`のどちらか一方だけが値を持つ
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExportedVote {
+    pub question_id: Option<QuestionId>,
+    pub answer_id: Option<AnswerId>,
+    /// +1か-1
+    pub value: i16,
+    pub created_on: NaiveDateTime,
+}
+
+/// アカウントに紐づく全てのデータ。ダウンロードされるJSONの内容になる
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountExport {
+    pub exported_on: NaiveDateTime,
+    pub account: AccountInfo,
+    /// 削除済みで完全な削除を待っている質問も含む
+    pub questions: Vec<Question>,
+    pub answers: Vec<Answer>,
+    pub comments: Vec<Comment>,
+    pub votes: Vec<ExportedVote>,
+    pub revisions: Vec<QuestionRevision>,
+}
+
+/// `GET /account/export/download`のクエリパラメータ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportDownloadQuery {
+    pub token: String,
+}
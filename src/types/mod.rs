@@ -1,4 +1,10 @@
 pub mod account;
 pub mod answer;
+pub mod comment;
+pub mod export;
 pub mod pagination;
 pub mod question;
+pub mod revision;
+pub mod search;
+pub mod tag;
+pub mod vote;
@@ -1,3 +1,5 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::prelude::*;
 use std::collections::HashMap;
 
 use handle_errors::Error;
@@ -9,15 +11,134 @@ pub struct Pagination {
     pub limit: Option<u32>,
     /// The index of the last item which has to be returned
     pub offset: u32,
+    /// Keyset position to continue from, replaces `offset` when present
+    pub cursor: Option<Cursor>,
+}
+
+/// Position of the last item of a page, ordered by `(created_on, id)`
+///
+/// Clients only ever see the opaque token produced by `Cursor::encode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_on: NaiveDateTime,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let micros = self.created_on.and_utc().timestamp_micros();
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", micros, self.id))
+    }
+
+    pub fn decode(token: &str) -> Result<Cursor, Error> {
+        let invalid = || Error::InvalidParameter("cursor".to_string());
+        let decoded = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (micros, id) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        let created_on = micros
+            .parse::<i64>()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?
+            .naive_utc();
+        let id = id.parse::<i32>().map_err(|_| invalid())?;
+
+        Ok(Cursor { created_on, id })
+    }
+}
+
+/// One page of a listing, with the cursor of the following page if there is one.
+/// `next_cursor` also tells that a next page exists for orderings which can't
+/// be continued with a cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows fetched with `limit + 1`, so that the extra row
+    /// tells whether a next page exists
+    pub fn from_rows(mut rows: Vec<(T, Cursor)>, limit: Option<u32>) -> Page<T> {
+        let next_cursor = match limit {
+            Some(limit) if rows.len() > limit as usize => {
+                rows.truncate(limit as usize);
+                rows.last().map(|(_, cursor)| *cursor)
+            }
+            _ => None,
+        };
+
+        Page {
+            items: rows.into_iter().map(|(item, _)| item).collect(),
+            next_cursor,
+        }
+    }
+}
+
+/// Build the value of the `Link` header pointing to the next page.
+/// All query parameters except the pagination ones are kept as they are.
+pub fn next_page_link(
+    path: &str,
+    params: &[(String, String)],
+    cursor: &Cursor,
+    limit: u32,
+) -> String {
+    page_link(
+        path,
+        params,
+        [
+            ("limit".to_string(), limit.to_string()),
+            ("cursor".to_string(), cursor.encode()),
+        ],
+    )
+}
+
+/// Same as `next_page_link`, for orderings which can't be continued with a
+/// cursor and have to fall back to `offset`.
+pub fn next_offset_page_link(
+    path: &str,
+    params: &[(String, String)],
+    offset: u32,
+    limit: u32,
+) -> String {
+    page_link(
+        path,
+        params,
+        [
+            ("limit".to_string(), limit.to_string()),
+            ("offset".to_string(), (offset + limit).to_string()),
+        ],
+    )
+}
+
+fn page_link(path: &str, params: &[(String, String)], next: [(String, String); 2]) -> String {
+    let mut query: Vec<(String, String)> = params
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "limit" | "offset" | "cursor"))
+        .cloned()
+        .collect();
+    query.extend(next);
+
+    format!(
+        "<{}?{}>; rel=\"next\"",
+        path,
+        serde_urlencoded::to_string(query).expect("query is always serializable")
+    )
 }
 
 /// Extract query parameters from the `/question` route
 /// # Example query
 /// GET requests to this route can have a pagination attached so we just
 /// return the questions we need
-/// `/questions?start=1&end=10`
+/// `/questions?limit=10&offset=1`
+/// `/questions?limit=10&cursor=MTY2NDYxNjAwMDAwMDAwMDox`
+/// `cursor` is the token handed out in the `Link` header of the previous page,
+/// it can't be combined with `offset` and `limit` is optional with it.
+/// Other query parameters (e.g. filters) are ignored, and a query without
+/// any pagination returns the default `Pagination`.
 /// # Example usage
-/// ```rust
+/// ```rust,ignore
 /// let mut query = HashMap::new();
 /// query.insert("limit".to_string(), "1".to_string());
 /// query.insert("offset".to_string(), "10".to_string());
@@ -26,6 +147,22 @@ pub struct Pagination {
 /// assert_eq!(p.offset, 10);
 /// ```
 pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
+    if let Some(cursor) = params.get("cursor") {
+        if params.contains_key("offset") {
+            return Err(Error::InvalidParameter("offset".to_string()));
+        }
+
+        return Ok(Pagination {
+            limit: params
+                .get("limit")
+                .map(|limit| limit.parse::<u32>())
+                .transpose()
+                .map_err(Error::ParseError)?,
+            offset: 0,
+            cursor: Some(Cursor::decode(cursor)?),
+        });
+    }
+
     if params.contains_key("limit") && params.contains_key("offset") {
         return Ok(Pagination {
             limit: Some(
@@ -40,15 +177,33 @@ pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination,
                 .unwrap()
                 .parse::<u32>()
                 .map_err(Error::ParseError)?,
+            cursor: None,
         });
     }
 
+    if !params.contains_key("limit") && !params.contains_key("offset") {
+        return Ok(Pagination::default());
+    }
+
     Err(Error::MissingParameters)
 }
 
 #[cfg(test)]
 mod pagination_tests {
-    use super::{extract_pagination, Error, HashMap, Pagination};
+    use super::{
+        extract_pagination, next_offset_page_link, next_page_link, Cursor, Error, HashMap,
+        NaiveDate, Page, Pagination,
+    };
+
+    fn cursor() -> Cursor {
+        Cursor {
+            created_on: NaiveDate::from_ymd_opt(2022, 10, 1)
+                .unwrap()
+                .and_hms_micro_opt(12, 30, 15, 123_456)
+                .unwrap(),
+            id: 42,
+        }
+    }
 
     #[test]
     fn valid_pagination() {
@@ -59,6 +214,7 @@ mod pagination_tests {
         let expected = Pagination {
             limit: Some(1),
             offset: 1,
+            cursor: None,
         };
 
         assert_eq!(pagination_result.unwrap(), expected);
@@ -84,6 +240,105 @@ mod pagination_tests {
         assert_eq!(pagination_result, exptected)
     }
 
+    #[test]
+    fn unrelated_params_are_ignored() {
+        let mut params = HashMap::new();
+        params.insert(String::from("tag"), String::from("rust"));
+        let pagination_result = extract_pagination(params);
+
+        assert_eq!(pagination_result.unwrap(), Pagination::default());
+
+        let mut params = HashMap::new();
+        params.insert(String::from("tag"), String::from("rust"));
+        params.insert(String::from("limit"), String::from("1"));
+        params.insert(String::from("offset"), String::from("1"));
+        let pagination_result = extract_pagination(params);
+        let expected = Pagination {
+            limit: Some(1),
+            offset: 1,
+            cursor: None,
+        };
+
+        assert_eq!(pagination_result.unwrap(), expected);
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let token = cursor().encode();
+        assert_eq!(Cursor::decode(&token).unwrap(), cursor());
+
+        let mut params = HashMap::new();
+        params.insert(String::from("limit"), String::from("10"));
+        params.insert(String::from("cursor"), token);
+        let expected = Pagination {
+            limit: Some(10),
+            offset: 0,
+            cursor: Some(cursor()),
+        };
+
+        assert_eq!(extract_pagination(params).unwrap(), expected);
+    }
+
+    #[test]
+    fn cursor_with_offset() {
+        let mut params = HashMap::new();
+        params.insert(String::from("offset"), String::from("1"));
+        params.insert(String::from("cursor"), cursor().encode());
+
+        let pagination_result = format!("{}", extract_pagination(params).unwrap_err());
+        assert_eq!(pagination_result, "Invalid value for parameter: offset")
+    }
+
+    #[test]
+    fn wrong_cursor() {
+        let mut params = HashMap::new();
+        params.insert(String::from("cursor"), String::from("NOT_A_CURSOR"));
+
+        let pagination_result = format!("{}", extract_pagination(params).unwrap_err());
+        assert_eq!(pagination_result, "Invalid value for parameter: cursor")
+    }
+
+    #[test]
+    fn page_from_extra_row() {
+        let rows = vec![(1, cursor()), (2, cursor()), (3, cursor())];
+        let page = Page::from_rows(rows.clone(), Some(2));
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor, Some(cursor()));
+
+        let page = Page::from_rows(rows, Some(3));
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn next_link_keeps_filters() {
+        let params = vec![
+            (String::from("tag"), String::from("c++")),
+            (String::from("offset"), String::from("10")),
+            (String::from("limit"), String::from("10")),
+        ];
+
+        assert_eq!(
+            next_page_link("/questions", &params, &cursor(), 10),
+            format!(
+                "</questions?tag=c%2B%2B&limit=10&cursor={}>; rel=\"next\"",
+                cursor().encode()
+            )
+        );
+    }
+
+    #[test]
+    fn next_offset_link_skips_current_page() {
+        let params = vec![
+            (String::from("sort"), String::from("title")),
+            (String::from("offset"), String::from("10")),
+        ];
+
+        assert_eq!(
+            next_offset_page_link("/questions", &params, 10, 5),
+            "</questions?sort=title&limit=5&offset=15>; rel=\"next\""
+        );
+    }
+
     #[test]
     fn wrong_offset_type() {
         let mut params = HashMap::new();
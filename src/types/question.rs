@@ -1,4 +1,7 @@
-use serde::{Deserialize, Serialize};
+use handle_errors::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::types::answer::AnswerId;
 
 #[derive(Debug, Serialize, Eq, Clone, PartialEq, Hash, Deserialize)]
 pub struct QuestionId(pub i32);
@@ -9,6 +12,12 @@ pub struct Question {
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// 票の合計。更新時の本文では無視する
+    #[serde(default)]
+    pub score: i32,
+    /// 質問者が採用した回答。更新時の本文では無視する
+    #[serde(default)]
+    pub accepted_answer_id: Option<AnswerId>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,3 +26,303 @@ pub struct NewQuestion {
     pub content: String,
     pub tags: Option<Vec<String>>,
 }
+
+/// `PATCH /questions/:id`の本文。JSON Merge Patch(RFC 7396)として解釈する
+///
+/// 含まれていないフィールドは変更しない。`tags`は`null`で削除できるが、
+/// `title`と`content`は必須の列なので`null`は受け付けない
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
+pub struct QuestionPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub content: Option<String>,
+    /// `None`は変更なし、`Some(None)`は削除
+    #[serde(default, deserialize_with = "nullable")]
+    pub tags: Option<Option<Vec<String>>>,
+}
+
+impl QuestionPatch {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.content.is_none() && self.tags.is_none()
+    }
+}
+
+/// キーが存在する場合だけ呼ばれるので、値が`null`でなければ`Some`にする
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// キーが存在する場合だけ呼ばれるので、`null`を`Some(None)`として区別する
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// 複数のタグを指定した場合の絞り込み方
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TagMode {
+    /// いずれかのタグを含む質問
+    #[default]
+    Any,
+    /// 全てのタグを含む質問
+    All,
+}
+
+/// `GET /questions`の絞り込み条件
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QuestionFilter {
+    pub tags: Vec<String>,
+    pub tag_mode: TagMode,
+    /// 回答のない質問だけを返す
+    pub unanswered: bool,
+    /// 回答が採用されていない質問だけを返す
+    pub unaccepted: bool,
+}
+
+/// Extract the filter from the query parameters of the `/questions` route
+/// # Example query
+/// `/questions?tag=rust&tag=warp&tag_mode=all`
+/// `/questions?unaccepted=true`
+/// `tag` can be repeated, `tag_mode` is either `any` (default) or `all`.
+/// `unanswered` and `unaccepted` take `true` or `false` (default).
+pub fn extract_question_filter(params: &[(String, String)]) -> Result<QuestionFilter, Error> {
+    let mut filter = QuestionFilter::default();
+
+    for (key, value) in params {
+        match key.as_str() {
+            "tag" => filter.tags.push(value.to_owned()),
+            "tag_mode" => {
+                filter.tag_mode = match value.as_str() {
+                    "any" => TagMode::Any,
+                    "all" => TagMode::All,
+                    _ => return Err(Error::InvalidParameter("tag_mode".to_string())),
+                }
+            }
+            "unanswered" => filter.unanswered = parse_flag(key, value)?,
+            "unaccepted" => filter.unaccepted = parse_flag(key, value)?,
+            _ => {}
+        }
+    }
+
+    Ok(filter)
+}
+
+fn parse_flag(key: &str, value: &str) -> Result<bool, Error> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(Error::InvalidParameter(key.to_string())),
+    }
+}
+
+/// 質問一覧を並べ替える基準
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    /// 投稿日時
+    #[default]
+    CreatedOn,
+    /// 回答数
+    AnswerCount,
+    /// 質問の編集か回答の投稿があった最後の日時
+    LastActivity,
+    /// タイトル(大文字・小文字は区別しない)
+    Title,
+    /// 票の合計
+    Score,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// `GET /questions`の並び順。値が同じ質問はidで並べるので、順序は常に一意に決まる
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QuestionSort {
+    pub field: SortField,
+    pub direction: SortDirection,
+}
+
+impl QuestionSort {
+    /// `(created_on, id)`のカーソルで続きを取得できる並び順かどうか
+    pub fn supports_cursor(&self) -> bool {
+        self.field == SortField::CreatedOn
+    }
+}
+
+/// Extract the ordering from the query parameters of the `/questions` route
+/// # Example query
+/// `/questions?sort=most_answers`
+/// `/questions?sort=title&order=desc`
+/// `sort` is one of `newest`, `oldest` (default), `most_answers`,
+/// `recently_active`, `title` or `score`, each with its natural direction which
+/// `order` (`asc` or `desc`) overrides.
+pub fn extract_question_sort(params: &[(String, String)]) -> Result<QuestionSort, Error> {
+    let mut sort = QuestionSort::default();
+    let mut direction = None;
+
+    for (key, value) in params {
+        match key.as_str() {
+            "sort" => {
+                sort = match value.as_str() {
+                    "newest" => QuestionSort {
+                        field: SortField::CreatedOn,
+                        direction: SortDirection::Desc,
+                    },
+                    "oldest" => QuestionSort {
+                        field: SortField::CreatedOn,
+                        direction: SortDirection::Asc,
+                    },
+                    "most_answers" => QuestionSort {
+                        field: SortField::AnswerCount,
+                        direction: SortDirection::Desc,
+                    },
+                    "recently_active" => QuestionSort {
+                        field: SortField::LastActivity,
+                        direction: SortDirection::Desc,
+                    },
+                    "title" => QuestionSort {
+                        field: SortField::Title,
+                        direction: SortDirection::Asc,
+                    },
+                    "score" => QuestionSort {
+                        field: SortField::Score,
+                        direction: SortDirection::Desc,
+                    },
+                    _ => return Err(Error::InvalidParameter("sort".to_string())),
+                }
+            }
+            "order" => {
+                direction = Some(match value.as_str() {
+                    "asc" => SortDirection::Asc,
+                    "desc" => SortDirection::Desc,
+                    _ => return Err(Error::InvalidParameter("order".to_string())),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(direction) = direction {
+        sort.direction = direction;
+    }
+
+    Ok(sort)
+}
+
+#[cfg(test)]
+mod question_filter_tests {
+    use super::{
+        extract_question_filter, extract_question_sort, QuestionFilter, QuestionPatch,
+        QuestionSort, SortDirection, SortField, TagMode,
+    };
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn repeated_tags_are_collected() {
+        let filter =
+            extract_question_filter(&params(&[("tag", "rust"), ("limit", "1"), ("tag", "warp")]))
+                .unwrap();
+
+        assert_eq!(
+            filter,
+            QuestionFilter {
+                tags: vec!["rust".to_string(), "warp".to_string()],
+                tag_mode: TagMode::Any,
+                unanswered: false,
+                unaccepted: false,
+            }
+        );
+    }
+
+    #[test]
+    fn unaccepted_flag() {
+        let filter = extract_question_filter(&params(&[("unaccepted", "true")])).unwrap();
+        assert!(filter.unaccepted);
+        assert!(!filter.unanswered);
+
+        let result = extract_question_filter(&params(&[("unanswered", "yes")]));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid value for parameter: unanswered"
+        );
+    }
+
+    #[test]
+    fn wrong_tag_mode() {
+        let result = extract_question_filter(&params(&[("tag_mode", "some")]));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid value for parameter: tag_mode"
+        );
+    }
+
+    #[test]
+    fn patch_distinguishes_missing_and_null() {
+        let patch: QuestionPatch =
+            serde_json::from_str(r#"{ "title": "new title", "tags": null }"#).unwrap();
+        assert_eq!(
+            patch,
+            QuestionPatch {
+                title: Some("new title".to_string()),
+                content: None,
+                tags: Some(None),
+            }
+        );
+
+        assert!(serde_json::from_str::<QuestionPatch>(r#"{ "id": 1 }"#)
+            .unwrap()
+            .is_empty());
+        assert!(serde_json::from_str::<QuestionPatch>(r#"{ "content": null }"#).is_err());
+    }
+
+    #[test]
+    fn sort_defaults_to_oldest_first() {
+        assert_eq!(
+            extract_question_sort(&params(&[("limit", "1")])).unwrap(),
+            QuestionSort {
+                field: SortField::CreatedOn,
+                direction: SortDirection::Asc,
+            }
+        );
+    }
+
+    #[test]
+    fn order_overrides_natural_direction() {
+        let sort =
+            extract_question_sort(&params(&[("order", "asc"), ("sort", "most_answers")])).unwrap();
+        assert_eq!(
+            sort,
+            QuestionSort {
+                field: SortField::AnswerCount,
+                direction: SortDirection::Asc,
+            }
+        );
+        assert!(!sort.supports_cursor());
+    }
+
+    #[test]
+    fn wrong_sort() {
+        let result = extract_question_sort(&params(&[("sort", "votes")]));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid value for parameter: sort"
+        );
+    }
+}
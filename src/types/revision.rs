@@ -0,0 +1,181 @@
+use chrono::prelude::*;
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::types::account::AccountId;
+use crate::types::question::{Question, QuestionId};
+
+/// 質問の過去の版。編集のたびに、編集前のタイトル・本文・タグを記録する
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct QuestionRevision {
+    /// 質問ごとに1から振られる版の番号
+    pub revision: i32,
+    pub question_id: QuestionId,
+    /// この版を書き換えたアカウント
+    pub account_id: AccountId,
+    /// 書き換えられた日時
+    pub created_on: NaiveDateTime,
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+}
+
+impl QuestionRevision {
+    /// この版の内容を質問として返す。ロールバック時の更新内容になる。
+    /// 票と採用した回答は版に含まれないので、`score`は0、`accepted_answer_id`は`None`になる
+    pub fn to_question(&self) -> Question {
+        Question {
+            id: self.question_id.clone(),
+            title: self.title.clone(),
+            content: self.content.clone(),
+            tags: self.tags.clone(),
+            score: 0,
+            accepted_answer_id: None,
+        }
+    }
+}
+
+/// 差分の1行分
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "op", content = "line", rename_all = "lowercase")]
+pub enum DiffLine {
+    Equal(String),
+    Delete(String),
+    Insert(String),
+}
+
+/// 2つの版の差分。タグは1つを1行として扱う
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RevisionDiff {
+    pub from: i32,
+    /// `None`は現在の質問
+    pub to: Option<i32>,
+    pub title: Vec<DiffLine>,
+    pub content: Vec<DiffLine>,
+    pub tags: Vec<DiffLine>,
+}
+
+impl RevisionDiff {
+    pub fn new(from: i32, old: &Question, to: Option<i32>, new: &Question) -> Self {
+        let tags =
+            |question: &Question| -> Vec<String> { question.tags.clone().unwrap_or_default() };
+
+        RevisionDiff {
+            from,
+            to,
+            title: diff_lines(&[old.title.as_str()], &[new.title.as_str()]),
+            content: diff_lines(
+                &old.content.lines().collect::<Vec<_>>(),
+                &new.content.lines().collect::<Vec<_>>(),
+            ),
+            tags: diff_lines(
+                &tags(old).iter().map(String::as_str).collect::<Vec<_>>(),
+                &tags(new).iter().map(String::as_str).collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+/// 最長共通部分列を求めて、`old`から`new`への行単位の差分を作る
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    // lcs[i][j]: old[i..]とnew[j..]の最長共通部分列の長さ
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Equal(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Delete(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Insert(new[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(
+        old[i..]
+            .iter()
+            .map(|line| DiffLine::Delete(line.to_string())),
+    );
+    lines.extend(
+        new[j..]
+            .iter()
+            .map(|line| DiffLine::Insert(line.to_string())),
+    );
+
+    lines
+}
+
+/// Extract the revisions to compare from the query parameters of the
+/// `/questions/:id/revisions/diff` route
+/// # Example query
+/// `/questions/1/revisions/diff?from=1&to=3`
+/// `from` is required, without `to` the revision is compared with the
+/// current question.
+pub fn extract_revision_range(
+    params: HashMap<String, String>,
+) -> Result<(i32, Option<i32>), Error> {
+    let from = params
+        .get("from")
+        .ok_or(Error::MissingParameters)?
+        .parse::<i32>()
+        .map_err(Error::ParseError)?;
+    let to = params
+        .get("to")
+        .map(|to| to.parse::<i32>())
+        .transpose()
+        .map_err(Error::ParseError)?;
+
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod revision_tests {
+    use super::{diff_lines, extract_revision_range, DiffLine, HashMap};
+
+    #[test]
+    fn diff_keeps_common_lines() {
+        let diff = diff_lines(&["a", "b", "c"], &["a", "c", "d"]);
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Equal("a".to_string()),
+                DiffLine::Delete("b".to_string()),
+                DiffLine::Equal("c".to_string()),
+                DiffLine::Insert("d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_line_serializes_with_op() {
+        assert_eq!(
+            serde_json::to_value(DiffLine::Insert("new".to_string())).unwrap(),
+            serde_json::json!({ "op": "insert", "line": "new" })
+        );
+    }
+
+    #[test]
+    fn revision_range_requires_from() {
+        let mut params = HashMap::new();
+        params.insert(String::from("to"), String::from("2"));
+        assert_eq!(
+            extract_revision_range(params).unwrap_err().to_string(),
+            "Missing parameter"
+        );
+    }
+}
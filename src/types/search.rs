@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::question::Question;
+
+/// `/search`の検索結果。質問の各フィールドに関連度とハイライト済みの抜粋を加えて返す
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub question: Question,
+    /// 値が大きいほど検索語との関連度が高い。投票の`score`とは別の値
+    pub relevance: f32,
+    /// 検索語を`<b>`〜`</b>`で囲んだ本文(または回答)の抜粋
+    pub snippet: String,
+}
+
+/// 検索語を英数字のみの単語に分割する。各バックエンドのクエリ構文を壊さないように記号は取り除く
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
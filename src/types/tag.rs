@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// タグと、そのタグが付いた質問の数
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub count: i64,
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// 投票の向き。`none`は投票の取り消し
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteDirection {
+    Up,
+    Down,
+    None,
+}
+
+impl VoteDirection {
+    /// `score`に加算される値
+    pub fn value(&self) -> i16 {
+        match self {
+            VoteDirection::Up => 1,
+            VoteDirection::Down => -1,
+            VoteDirection::None => 0,
+        }
+    }
+}
+
+/// `POST /questions/:id/vote`・`POST /answers/:id/vote`の本文
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewVote {
+    pub vote: VoteDirection,
+}